@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+DROP INDEX IF EXISTS worklog_search_vector_idx;
+DROP INDEX IF EXISTS skills_search_vector_idx;
+DROP INDEX IF EXISTS posts_search_vector_idx;
+
+ALTER TABLE worklog DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE skills DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE posts DROP COLUMN IF EXISTS search_vector;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- up.sql
+CREATE TABLE post_revisions (
+    id SERIAL PRIMARY KEY,
+    post_id VARCHAR NOT NULL,
+    revision_number INT NOT NULL,
+    title VARCHAR NOT NULL,
+    body TEXT NOT NULL,
+    author_id VARCHAR(50),
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (post_id, revision_number)
+);
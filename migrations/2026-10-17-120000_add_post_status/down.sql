@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+DROP INDEX IF EXISTS posts_status_publish_at_idx;
+
+ALTER TABLE posts
+    DROP COLUMN IF EXISTS publish_at,
+    DROP COLUMN IF EXISTS status;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- up.sql
+ALTER TABLE posts
+    ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'published'
+        CHECK (status IN ('draft', 'published', 'scheduled', 'archived')),
+    ADD COLUMN publish_at TIMESTAMP;
+
+CREATE INDEX posts_status_publish_at_idx ON posts (status, publish_at);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+DROP TABLE IF EXISTS post_tags;
+DROP TABLE IF EXISTS tags;
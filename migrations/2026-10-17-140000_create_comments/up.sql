@@ -0,0 +1,19 @@
+-- Your SQL goes here
+-- up.sql
+CREATE TABLE comments (
+    id SERIAL PRIMARY KEY,
+    post_id VARCHAR NOT NULL,
+    parent_id INT REFERENCES comments (id) ON DELETE CASCADE,
+    author_id VARCHAR(50),
+    author_name VARCHAR(100) NOT NULL,
+    body TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected', 'spam')),
+    moderated_by VARCHAR(50),
+    moderated_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX comments_post_id_status_idx ON comments (post_id, status);
+CREATE INDEX comments_status_created_at_idx ON comments (status, created_at);
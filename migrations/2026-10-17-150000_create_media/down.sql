@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+DROP TABLE IF EXISTS media;
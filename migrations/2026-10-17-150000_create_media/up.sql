@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- up.sql
+CREATE TABLE media (
+    id SERIAL PRIMARY KEY,
+    media_id VARCHAR(36) NOT NULL UNIQUE,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    checksum_sha256 VARCHAR(64) NOT NULL,
+    owner_id VARCHAR(50),
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX media_owner_id_idx ON media (owner_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+DROP TABLE IF EXISTS slug_history;
+ALTER TABLE posts DROP COLUMN IF EXISTS slug;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+-- up.sql
+ALTER TABLE posts ADD COLUMN slug VARCHAR(255);
+
+-- Existing posts get a slug from their title, repeated titles get the post id appended
+UPDATE posts
+SET slug = numbered.base || CASE WHEN numbered.position > 1 THEN '-' || posts.id ELSE '' END
+FROM (
+    SELECT id, base, row_number() OVER (PARTITION BY base ORDER BY id) AS position
+    FROM (
+        SELECT id,
+               COALESCE(NULLIF(trim(BOTH '-' FROM lower(regexp_replace(title, '[^a-zA-Z0-9]+', '-', 'g'))), ''), 'post') AS base
+        FROM posts
+    ) bases
+) numbered
+WHERE numbered.id = posts.id;
+
+ALTER TABLE posts ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE posts ADD CONSTRAINT posts_slug_key UNIQUE (slug);
+
+-- Slugs a post used to have, so old links can be redirected
+CREATE TABLE slug_history (
+    id SERIAL PRIMARY KEY,
+    post_id VARCHAR NOT NULL,
+    slug VARCHAR(255) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX slug_history_post_id_idx ON slug_history (post_id);
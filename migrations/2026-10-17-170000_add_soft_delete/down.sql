@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+-- Anything still in the trash would come back as live content, so it goes for good
+DELETE FROM posts WHERE deleted_at IS NOT NULL;
+DELETE FROM skills WHERE deleted_at IS NOT NULL;
+DELETE FROM worklog WHERE deleted_at IS NOT NULL;
+
+ALTER TABLE posts DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE skills DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE worklog DROP COLUMN IF EXISTS deleted_at;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- up.sql
+-- Deleted rows stay in the table with deleted_at set until they are restored or purged from the trash
+ALTER TABLE posts ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE skills ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE worklog ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX posts_deleted_at_idx ON posts (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX skills_deleted_at_idx ON skills (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX worklog_deleted_at_idx ON worklog (deleted_at) WHERE deleted_at IS NOT NULL;
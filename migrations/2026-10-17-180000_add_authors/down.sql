@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+ALTER TABLE posts DROP COLUMN IF EXISTS author_id;
+ALTER TABLE skills DROP COLUMN IF EXISTS author_id;
+ALTER TABLE worklog DROP COLUMN IF EXISTS author_id;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_user_id_key;
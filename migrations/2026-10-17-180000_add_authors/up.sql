@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- up.sql
+-- Authors are referenced by user_id, the id sessions carry, so it has to be unique
+ALTER TABLE users ADD CONSTRAINT users_user_id_key UNIQUE (user_id);
+
+-- Content written before authorship was tracked, or by a since deleted account, has no author
+ALTER TABLE posts ADD COLUMN author_id VARCHAR(50) REFERENCES users (user_id) ON DELETE SET NULL;
+ALTER TABLE skills ADD COLUMN author_id VARCHAR(50) REFERENCES users (user_id) ON DELETE SET NULL;
+ALTER TABLE worklog ADD COLUMN author_id VARCHAR(50) REFERENCES users (user_id) ON DELETE SET NULL;
+
+CREATE INDEX posts_author_id_idx ON posts (author_id);
+CREATE INDEX skills_author_id_idx ON skills (author_id);
+CREATE INDEX worklog_author_id_idx ON worklog (author_id);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- up.sql
+-- Daily view counts rolled up from Redis. Visitors are only ever counted, never stored.
+CREATE TABLE post_views (
+    id SERIAL PRIMARY KEY,
+    post_id VARCHAR NOT NULL,
+    day DATE NOT NULL,
+    views BIGINT NOT NULL DEFAULT 0,
+    uniques BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (post_id, day)
+);
+
+CREATE INDEX post_views_day_idx ON post_views (day);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+ALTER TABLE posts DROP COLUMN IF EXISTS series_position;
+ALTER TABLE posts DROP COLUMN IF EXISTS series_id;
+
+DROP TABLE IF EXISTS series;
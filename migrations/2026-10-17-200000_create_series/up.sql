@@ -0,0 +1,16 @@
+-- Your SQL goes here
+-- up.sql
+CREATE TABLE series (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    slug VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- A post belongs to at most one series, at a position counted from 1
+ALTER TABLE posts ADD COLUMN series_id INT REFERENCES series (id) ON DELETE SET NULL;
+ALTER TABLE posts ADD COLUMN series_position INT CHECK (series_position >= 1);
+
+CREATE INDEX posts_series_id_position_idx ON posts (series_id, series_position);
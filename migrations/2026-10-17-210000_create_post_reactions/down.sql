@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+-- down.sql
+ALTER TABLE posts DROP COLUMN IF EXISTS insightful_count;
+ALTER TABLE posts DROP COLUMN IF EXISTS love_count;
+ALTER TABLE posts DROP COLUMN IF EXISTS like_count;
+
+DROP TABLE IF EXISTS post_reactions;
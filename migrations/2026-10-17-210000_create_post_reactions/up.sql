@@ -0,0 +1,20 @@
+-- Your SQL goes here
+-- up.sql
+-- One row per reader and reaction. Signed-in readers are told apart by user_id, anonymous readers by a cookie token.
+CREATE TABLE post_reactions (
+    id SERIAL PRIMARY KEY,
+    post_id VARCHAR NOT NULL,
+    reaction VARCHAR(20) NOT NULL CHECK (reaction IN ('like', 'love', 'insightful')),
+    user_id VARCHAR(50),
+    reader_token VARCHAR(64),
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CHECK ((user_id IS NULL) <> (reader_token IS NULL))
+);
+
+CREATE UNIQUE INDEX post_reactions_user_idx ON post_reactions (post_id, reaction, user_id) WHERE user_id IS NOT NULL;
+CREATE UNIQUE INDEX post_reactions_reader_token_idx ON post_reactions (post_id, reaction, reader_token) WHERE reader_token IS NOT NULL;
+
+-- Totals kept on the post so lists can show them without counting reactions per post
+ALTER TABLE posts ADD COLUMN like_count INT NOT NULL DEFAULT 0;
+ALTER TABLE posts ADD COLUMN love_count INT NOT NULL DEFAULT 0;
+ALTER TABLE posts ADD COLUMN insightful_count INT NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- up.sql
+-- Title and body of a post in another language, the post itself holds the original
+CREATE TABLE post_translations (
+    id SERIAL PRIMARY KEY,
+    post_id VARCHAR NOT NULL,
+    locale VARCHAR(20) NOT NULL,
+    title VARCHAR NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (post_id, locale)
+);
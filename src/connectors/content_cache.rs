@@ -0,0 +1,324 @@
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::etag::{conditional_body, etag_for, versioned_etag};
+
+const DEFAULT_TTL_SECONDS: u64 = 60;
+
+// A slow or missing Redis should cost a request next to nothing, the database answers instead
+const REDIS_TIMEOUT: Duration = Duration::from_millis(250);
+
+// The content types with cached reads. Each has its own generation, so a write only drops its own kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheKind {
+    Posts,
+    Skills,
+    Worklog,
+}
+
+impl CacheKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CacheKind::Posts => "posts",
+            CacheKind::Skills => "skills",
+            CacheKind::Worklog => "worklog",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+// Hit and miss counts for one kind since the server started
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct KindStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub ttl_seconds: u64,
+    pub posts: KindStats,
+    pub skills: KindStats,
+    pub worklog: KindStats,
+    // Redis calls that failed, those requests went straight to the database
+    pub errors: u64,
+}
+
+// A response body as stored in Redis, with the updated_at its ETag and Last-Modified come from
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedBody {
+    updated_at: Option<NaiveDateTime>,
+    // Set for posts, whose body changes without updated_at moving. Their ETag adds a digest of the body and there's no Last-Modified.
+    #[serde(default)]
+    digested: bool,
+    body: String,
+}
+
+impl CachedBody {
+    fn respond(self, req: &HttpRequest) -> HttpResponse {
+        let body = self.body.into_bytes();
+        match self.updated_at {
+            Some(updated_at) if self.digested => conditional_body(req, Some(versioned_etag(updated_at, &body)), None, body),
+            updated_at => conditional_body(req, updated_at.map(etag_for), updated_at, body),
+        }
+    }
+}
+
+// Read-through cache of serialized content responses in Redis.
+// Keys carry a per-kind generation, and invalidating a kind bumps it, so every older key stops being read and expires with its TTL.
+pub struct ContentCache {
+    redis_client: redis::Client,
+    ttl_seconds: u64,
+    hits: [AtomicU64; 3],
+    misses: [AtomicU64; 3],
+    errors: AtomicU64,
+}
+
+impl ContentCache {
+    pub fn new(redis_client: redis::Client, ttl_seconds: u64) -> Self {
+        ContentCache {
+            redis_client,
+            ttl_seconds,
+            hits: Default::default(),
+            misses: Default::default(),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    // TTL from CONTENT_CACHE_TTL_SECONDS, 0 turns the cache off
+    pub fn from_env(redis_client: redis::Client) -> Self {
+        let ttl_seconds =
+            env::var("CONTENT_CACHE_TTL_SECONDS")
+                .ok()
+                .and_then(|seconds| seconds.parse::<u64>().ok())
+                .unwrap_or(DEFAULT_TTL_SECONDS);
+        ContentCache::new(redis_client, ttl_seconds)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let kind_stats = |kind: CacheKind| KindStats {
+            hits: self.hits[kind.index()].load(Ordering::Relaxed),
+            misses: self.misses[kind.index()].load(Ordering::Relaxed),
+        };
+        CacheStats {
+            ttl_seconds: self.ttl_seconds,
+            posts: kind_stats(CacheKind::Posts),
+            skills: kind_stats(CacheKind::Skills),
+            worklog: kind_stats(CacheKind::Worklog),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    fn generation_key(kind: CacheKind) -> String {
+        format!("content_cache:{}:generation", kind.as_str())
+    }
+
+    async fn connection(&self) -> Option<redis::aio::MultiplexedConnection> {
+        let config =
+            redis::AsyncConnectionConfig::new()
+                .set_connection_timeout(REDIS_TIMEOUT)
+                .set_response_timeout(REDIS_TIMEOUT);
+
+        match self.redis_client.get_multiplexed_async_connection_with_config(&config).await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                log::warn!("Content cache unavailable: {:?}", e);
+                None
+            }
+        }
+    }
+
+    // Drops every cached read of `kind`. Called after each write that changes what those reads return.
+    pub async fn invalidate(&self, kind: CacheKind) {
+        let Some(mut conn) = self.connection().await else { return };
+
+        if let Err(e) = conn.incr::<_, _, i64>(Self::generation_key(kind), 1).await {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            log::warn!("Failed to invalidate the {} cache: {:?}", kind.as_str(), e);
+        }
+    }
+
+    // The cached body for `request_key`, or the key to store it under once it has been loaded
+    async fn get(&self, kind: CacheKind, request_key: &str) -> Result<CachedBody, Option<String>> {
+        let mut conn = self.connection().await.ok_or(None)?;
+
+        let lookup = async {
+            let generation: Option<i64> = conn.get(Self::generation_key(kind)).await?;
+            let key = format!("content_cache:{}:{}:{}", kind.as_str(), generation.unwrap_or(0), request_key);
+            let cached: Option<String> = conn.get(&key).await?;
+            Ok::<_, redis::RedisError>((key, cached))
+        };
+
+        match lookup.await {
+            Ok((key, Some(cached))) => match serde_json::from_str(&cached) {
+                Ok(cached) => {
+                    self.hits[kind.index()].fetch_add(1, Ordering::Relaxed);
+                    Ok(cached)
+                }
+                // Written by an older version of the server, load it afresh
+                Err(_) => {
+                    self.misses[kind.index()].fetch_add(1, Ordering::Relaxed);
+                    Err(Some(key))
+                }
+            },
+            Ok((key, None)) => {
+                self.misses[kind.index()].fetch_add(1, Ordering::Relaxed);
+                Err(Some(key))
+            }
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                log::warn!("Content cache read failed: {:?}", e);
+                Err(None)
+            }
+        }
+    }
+
+    async fn put(&self, key: &str, cached: &CachedBody) {
+        let Ok(value) = serde_json::to_string(cached) else { return };
+        let Some(mut conn) = self.connection().await else { return };
+
+        if let Err(e) = conn.set_ex::<_, _, ()>(key, value, self.ttl_seconds).await {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            log::warn!("Content cache write failed: {:?}", e);
+        }
+    }
+}
+
+// Invalidates `kind` through the app's ContentCache, if it has one
+pub async fn invalidate_cached(req: &HttpRequest, kind: CacheKind) {
+    if let Some(cache) = req.app_data::<web::Data<ContentCache>>() {
+        cache.invalidate(kind).await;
+    }
+}
+
+// One read handler's trip through the cache: either a hit to send straight back,
+// or a miss whose response is stored on the way out by `respond`.
+pub struct CacheLookup {
+    cache: Option<web::Data<ContentCache>>,
+    key: Option<String>,
+    hit: Option<CachedBody>,
+}
+
+impl CacheLookup {
+    // Looks the request up by its path and query string. Nothing is read or stored unless `cacheable`,
+    // the app has a ContentCache and its TTL is above 0.
+    pub async fn new(req: &HttpRequest, kind: CacheKind, cacheable: bool) -> Self {
+        let cache =
+            req.app_data::<web::Data<ContentCache>>()
+                .filter(|cache| cacheable && cache.ttl_seconds > 0)
+                .cloned();
+
+        let (key, hit) =
+            match &cache {
+                Some(cache) => match cache.get(kind, &request_key(req)).await {
+                    Ok(hit) => (None, Some(hit)),
+                    Err(key) => (key, None),
+                },
+                None => (None, None),
+            };
+
+        CacheLookup { cache, key, hit }
+    }
+
+    // A top level string field of the cached body, e.g. the post_id of a cached post
+    pub fn cached_string(&self, field: &str) -> Option<String> {
+        let hit = self.hit.as_ref()?;
+        let body: serde_json::Value = serde_json::from_str(&hit.body).ok()?;
+        body.get(field)?.as_str().map(str::to_string)
+    }
+
+    // The response for a cache hit
+    pub fn cached_response(&mut self, req: &HttpRequest) -> Option<HttpResponse> {
+        self.hit.take().map(|hit| hit.respond(req))
+    }
+
+    // Serializes a freshly loaded body, stores it on a miss and answers the request the way conditional_json does.
+    // Single items pass their `updated_at` for the ETag and Last-Modified, lists are tagged by content.
+    pub async fn respond<T: Serialize>(self, req: &HttpRequest, updated_at: Option<NaiveDateTime>, body: &T) -> HttpResponse {
+        self.store_and_respond(req, updated_at, false, body).await
+    }
+
+    // respond for a single post, tagged by versioned_etag so reactions and series changes revalidate
+    pub async fn respond_post<T: Serialize>(self, req: &HttpRequest, updated_at: NaiveDateTime, body: &T) -> HttpResponse {
+        self.store_and_respond(req, Some(updated_at), true, body).await
+    }
+
+    async fn store_and_respond<T: Serialize>(
+        self,
+        req: &HttpRequest,
+        updated_at: Option<NaiveDateTime>,
+        digested: bool,
+        body: &T,
+    ) -> HttpResponse {
+        let body = match serde_json::to_string(body) {
+            Ok(body) => body,
+            Err(e) => {
+                eprintln!("Error serializing response: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        let cached = CachedBody { updated_at, digested, body };
+
+        if let (Some(cache), Some(key)) = (&self.cache, &self.key) {
+            cache.put(key, &cached).await;
+        }
+        cached.respond(req)
+    }
+}
+
+// Accept-Language picks the translation of a post, so requests differing in it are kept apart
+fn request_key(req: &HttpRequest) -> String {
+    let key = match req.query_string() {
+        "" => req.path().to_string(),
+        query => format!("{}?{}", req.path(), query),
+    };
+    match req.headers().get(header::ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok()) {
+        Some(languages) => format!("{} lang:{}", key, languages),
+        None => key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_request_key() {
+        assert_eq!(request_key(&TestRequest::get().uri("/blog/post/get/all").to_http_request()), "/blog/post/get/all");
+        assert_eq!(request_key(&TestRequest::get().uri("/blog/post/get/all?limit=5&render=true").to_http_request()), "/blog/post/get/all?limit=5&render=true");
+        assert_eq!(
+            request_key(&TestRequest::get().uri("/blog/post/get/all").insert_header(("Accept-Language", "de, en;q=0.5")).to_http_request()),
+            "/blog/post/get/all lang:de, en;q=0.5"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_content_cache_fails_open_without_redis() {
+        // Nothing listens on port 1, so every Redis call fails
+        let cache = web::Data::new(ContentCache::new(redis::Client::open("redis://127.0.0.1:1").unwrap(), 60));
+        let req = TestRequest::get().uri("/blog/skill/get/all").app_data(cache.clone()).to_http_request();
+
+        let mut lookup = CacheLookup::new(&req, CacheKind::Skills, true).await;
+        assert!(lookup.cached_response(&req).is_none());
+        let response = lookup.respond(&req, None, &vec!["rust"]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        invalidate_cached(&req, CacheKind::Skills).await;
+
+        let stats = cache.stats();
+        assert_eq!(stats.skills, KindStats { hits: 0, misses: 0 });
+        assert_eq!(stats.errors, 2);
+    }
+}
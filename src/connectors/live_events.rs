@@ -0,0 +1,305 @@
+use std::time::Duration;
+
+use actix_web::{web, HttpRequest};
+use chrono::NaiveDateTime;
+use futures::{Stream, StreamExt};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+const CHANNEL: &str = "live_events";
+const SEQUENCE_KEY: &str = "live_events:sequence";
+// The newest events are kept for clients resuming with Last-Event-ID, older ones can't be replayed
+const RECENT_KEY: &str = "live_events:recent";
+pub const MAX_REPLAYED_EVENTS: isize = 1000;
+
+// Publishing should cost a write next to nothing when Redis is slow or gone, like the content cache
+const REDIS_TIMEOUT: Duration = Duration::from_millis(250);
+
+// Numbers, keeps and sends an event in one step, so two writers can't send their ids out of order.
+// ARGV[1] is the event without its id, which the script puts in front.
+const PUBLISH_SCRIPT: &str = r#"
+local id = redis.call('INCR', KEYS[1])
+local payload = '{"id":' .. id .. ',' .. string.sub(ARGV[1], 2)
+redis.call('ZADD', KEYS[2], id, payload)
+redis.call('ZREMRANGEBYRANK', KEYS[2], 0, -(tonumber(ARGV[2]) + 1))
+redis.call('PUBLISH', ARGV[3], payload)
+return id
+"#;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveKind {
+    Post,
+    Comment,
+    Skill,
+    Worklog,
+}
+
+impl LiveKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveKind::Post => "post",
+            LiveKind::Comment => "comment",
+            LiveKind::Skill => "skill",
+            LiveKind::Worklog => "worklog",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl LiveAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveAction::Created => "created",
+            LiveAction::Updated => "updated",
+            LiveAction::Deleted => "deleted",
+        }
+    }
+}
+
+// One change pushed to /events. Ids come from one Redis counter, so they are shared by every instance.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct LiveEvent {
+    pub id: u64,
+    pub kind: LiveKind,
+    pub action: LiveAction,
+    pub item_id: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl LiveEvent {
+    // The SSE event name, e.g. post.created
+    pub fn name(&self) -> String {
+        format!("{}.{}", self.kind.as_str(), self.action.as_str())
+    }
+
+    // The event as an SSE frame, its id is what the client sends back as Last-Event-ID
+    pub fn to_frame(&self) -> String {
+        let data = serde_json::to_string(self).unwrap_or_default();
+        format!("id: {}\nevent: {}\ndata: {}\n\n", self.id, self.name(), data)
+    }
+}
+
+// The id of the last event a reconnecting client saw, None for a fresh connection
+pub fn last_event_id(req: &HttpRequest) -> Option<u64> {
+    req.headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+}
+
+// Publishes content changes through Redis, so every instance behind the load balancer streams the same events
+pub struct LiveEvents {
+    redis_client: redis::Client,
+}
+
+impl LiveEvents {
+    pub fn new(redis_client: redis::Client) -> Self {
+        LiveEvents { redis_client }
+    }
+
+    async fn connection(&self) -> Option<redis::aio::MultiplexedConnection> {
+        let config =
+            redis::AsyncConnectionConfig::new()
+                .set_connection_timeout(REDIS_TIMEOUT)
+                .set_response_timeout(REDIS_TIMEOUT);
+
+        match self.redis_client.get_multiplexed_async_connection_with_config(&config).await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                log::warn!("Live events unavailable: {:?}", e);
+                None
+            }
+        }
+    }
+
+    // Numbers the event, keeps it for replays and sends it to every subscribed instance
+    pub async fn publish(&self, kind: LiveKind, action: LiveAction, item_id: &str) {
+        let Some(mut conn) = self.connection().await else { return };
+
+        let event = LiveEvent { id: 0, kind, action, item_id: item_id.to_string(), created_at: chrono::Utc::now().naive_utc() };
+        let published =
+            redis::Script::new(PUBLISH_SCRIPT)
+                .key(SEQUENCE_KEY)
+                .key(RECENT_KEY)
+                .arg(fields_without_id(&event))
+                .arg(MAX_REPLAYED_EVENTS)
+                .arg(CHANNEL)
+                .invoke_async::<u64>(&mut conn)
+                .await;
+
+        if let Err(e) = published {
+            log::warn!("Failed to publish a live event: {:?}", e);
+        }
+    }
+
+    // The id of the newest event, 0 before the first one
+    async fn sequence(&self) -> redis::RedisResult<u64> {
+        let mut conn = self.connection().await.ok_or_else(|| redis::RedisError::from((redis::ErrorKind::IoError, "Redis unavailable")))?;
+        let sequence: Option<u64> = conn.get(SEQUENCE_KEY).await?;
+        Ok(sequence.unwrap_or(0))
+    }
+
+    // The kept events newer than `last_id`, oldest first
+    async fn since(&self, last_id: u64) -> redis::RedisResult<Vec<LiveEvent>> {
+        let mut conn = self.connection().await.ok_or_else(|| redis::RedisError::from((redis::ErrorKind::IoError, "Redis unavailable")))?;
+        let payloads: Vec<String> = conn.zrangebyscore(RECENT_KEY, format!("({}", last_id), "+inf").await?;
+        Ok(payloads.iter().filter_map(|payload| serde_json::from_str(payload).ok()).collect())
+    }
+
+    // The live stream for one client. It subscribes before reading the kept events, so nothing published
+    // in between is lost, and events it has already replayed are skipped when they come in live as well.
+    pub async fn subscribe(&self, last_id: Option<u64>) -> redis::RedisResult<impl Stream<Item = LiveEvent>> {
+        let mut pubsub = self.redis_client.get_async_pubsub().await?;
+        pubsub.subscribe(CHANNEL).await?;
+
+        let last_id = known_last_id(last_id, self.sequence().await?);
+        let replayed = match last_id {
+            Some(last_id) => self.since(last_id).await?,
+            None => Vec::new(),
+        };
+
+        let live =
+            pubsub
+                .into_on_message()
+                .filter_map(|message| async move { message.get_payload::<String>().ok() })
+                .filter_map(|payload| async move { serde_json::from_str::<LiveEvent>(&payload).ok() });
+
+        Ok(resume(replayed, last_id, live))
+    }
+}
+
+// The event as JSON without its id, for the publish script to number
+fn fields_without_id(event: &LiveEvent) -> String {
+    let mut fields = serde_json::to_value(event).unwrap_or_default();
+    if let Some(fields) = fields.as_object_mut() {
+        fields.remove("id");
+    }
+    fields.to_string()
+}
+
+// A Last-Event-ID above the newest id comes from before Redis lost its data. The numbering has
+// started over since, so everything that is kept is new to the client.
+fn known_last_id(last_id: Option<u64>, sequence: u64) -> Option<u64> {
+    last_id.map(|last_id| if last_id > sequence { 0 } else { last_id })
+}
+
+// The replayed events, then the live ones that came after them
+fn resume(replayed: Vec<LiveEvent>, last_id: Option<u64>, live: impl Stream<Item = LiveEvent>) -> impl Stream<Item = LiveEvent> {
+    let replayed_up_to = replayed.last().map(|event| event.id).or(last_id).unwrap_or(0);
+    futures::stream::iter(replayed).chain(live.filter(move |event| std::future::ready(event.id > replayed_up_to)))
+}
+
+// Publishes a change through the app's LiveEvents, if it has one
+pub async fn publish_live(req: &HttpRequest, kind: LiveKind, action: LiveAction, item_id: &str) {
+    if let Some(live_events) = req.app_data::<web::Data<LiveEvents>>() {
+        live_events.publish(kind, action, item_id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_live_event_frame() {
+        let event = LiveEvent {
+            id: 42,
+            kind: LiveKind::Comment,
+            action: LiveAction::Created,
+            item_id: "7".to_string(),
+            created_at: NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+        assert_eq!(event.name(), "comment.created");
+        assert_eq!(
+            event.to_frame(),
+            "id: 42\nevent: comment.created\ndata: {\"id\":42,\"kind\":\"comment\",\"action\":\"created\",\"item_id\":\"7\",\"created_at\":\"2024-08-29T14:00:00\"}\n\n"
+        );
+    }
+
+    fn event(id: u64) -> LiveEvent {
+        LiveEvent {
+            id,
+            kind: LiveKind::Post,
+            action: LiveAction::Updated,
+            item_id: "resumed".to_string(),
+            created_at: NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_resume() {
+        // Events published between subscribing and reading the kept ones arrive twice, the live copy is dropped
+        let live = futures::stream::iter(vec![event(6), event(7)]);
+        let ids: Vec<u64> = resume(vec![event(5), event(6)], Some(4), live).map(|event| event.id).collect().await;
+        assert_eq!(ids, vec![5, 6, 7]);
+
+        // Nothing newer was kept, so only what is live and after Last-Event-ID goes out
+        let live = futures::stream::iter(vec![event(9), event(10)]);
+        let ids: Vec<u64> = resume(Vec::new(), Some(9), live).map(|event| event.id).collect().await;
+        assert_eq!(ids, vec![10]);
+    }
+
+    #[test]
+    fn test_fields_without_id() {
+        // The publish script puts the id back in front the same way
+        let fields = fields_without_id(&event(0));
+        assert!(!fields.contains("\"id\""));
+        let numbered = format!("{{\"id\":{},{}", 43, &fields[1..]);
+        assert_eq!(serde_json::from_str::<LiveEvent>(&numbered).unwrap(), event(43));
+    }
+
+    #[test]
+    fn test_known_last_id() {
+        assert_eq!(known_last_id(Some(41), 42), Some(41));
+        assert_eq!(known_last_id(Some(42), 42), Some(42));
+        // Redis was flushed since the client saw event 900
+        assert_eq!(known_last_id(Some(900), 12), Some(0));
+        assert_eq!(known_last_id(None, 12), None);
+    }
+
+    // Needs a Redis at TEST_REDIS_URL, there is none to talk to otherwise
+    #[actix_rt::test]
+    async fn test_subscribe_replays_from_last_event_id() {
+        dotenv::dotenv().ok();
+        let Ok(redis_url) = std::env::var("TEST_REDIS_URL") else {
+            eprintln!("TEST_REDIS_URL is not set, skipping the live events round trip");
+            return;
+        };
+        let live_events = LiveEvents::new(redis::Client::open(redis_url).expect("Invalid TEST_REDIS_URL"));
+        let item_id = uuid::Uuid::new_v4().to_string();
+
+        live_events.publish(LiveKind::Post, LiveAction::Created, &item_id).await;
+        let kept = live_events.since(0).await.expect("Failed to read kept events");
+        let last_seen = kept.iter().find(|event| event.item_id == item_id).expect("The event was not kept").id;
+
+        // Missed while disconnected, then published while subscribed
+        live_events.publish(LiveKind::Post, LiveAction::Updated, &item_id).await;
+        let events = live_events.subscribe(Some(last_seen)).await.expect("Failed to subscribe");
+        live_events.publish(LiveKind::Post, LiveAction::Deleted, &item_id).await;
+
+        let mine = events.filter(|event| std::future::ready(event.item_id == item_id));
+        let actions: Vec<LiveAction> =
+            tokio::time::timeout(Duration::from_secs(5), mine.take(2).map(|event| event.action).collect())
+                .await
+                .expect("Timed out waiting for live events");
+        assert_eq!(actions, vec![LiveAction::Updated, LiveAction::Deleted]);
+    }
+
+    #[test]
+    fn test_last_event_id() {
+        let req = TestRequest::default().insert_header(("Last-Event-ID", " 17 ")).to_http_request();
+        assert_eq!(last_event_id(&req), Some(17));
+        let req = TestRequest::default().insert_header(("Last-Event-ID", "abc")).to_http_request();
+        assert_eq!(last_event_id(&req), None);
+        assert_eq!(last_event_id(&TestRequest::default().to_http_request()), None);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+// Define custom error type
+#[derive(Debug)]
+pub enum MediaStoreError {
+    NotFound,
+    InvalidKey(String),
+    UnsupportedBackend(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for MediaStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaStoreError::NotFound => write!(f, "Media object not found"),
+            MediaStoreError::InvalidKey(key) => write!(f, "Invalid media key '{}'", key),
+            MediaStoreError::UnsupportedBackend(backend) => write!(f, "Unsupported media store '{}'", backend),
+            MediaStoreError::Io(e) => write!(f, "Media store I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for MediaStoreError {}
+
+impl From<io::Error> for MediaStoreError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => MediaStoreError::NotFound,
+            _ => MediaStoreError::Io(e),
+        }
+    }
+}
+
+// Where uploaded files live. Metadata is kept in the media table, a store only deals in bytes by key,
+// so an S3-compatible backend can be added next to these without touching the handlers.
+pub trait MediaStore: Send + Sync {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaStoreError>;
+
+    // Reads `length` bytes starting at `offset`
+    fn get_range(&self, key: &str, offset: u64, length: u64) -> Result<Vec<u8>, MediaStoreError>;
+
+    fn delete(&self, key: &str) -> Result<(), MediaStoreError>;
+}
+
+// Keys become file names, so only allow what we generate ourselves
+fn validate_key(key: &str) -> Result<(), MediaStoreError> {
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(MediaStoreError::InvalidKey(key.to_string()));
+    }
+    Ok(())
+}
+
+// Stores every object as a file under `root`
+pub struct LocalFsMediaStore {
+    root: PathBuf,
+}
+
+impl LocalFsMediaStore {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, MediaStoreError> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(LocalFsMediaStore { root })
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, MediaStoreError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+impl MediaStore for LocalFsMediaStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaStoreError> {
+        // Write to a temporary name first so a half written upload is never served
+        let path = self.path_for(key)?;
+        let partial_path = path.with_extension("partial");
+        fs::write(&partial_path, data)?;
+        fs::rename(&partial_path, &path)?;
+        Ok(())
+    }
+
+    fn get_range(&self, key: &str, offset: u64, length: u64) -> Result<Vec<u8>, MediaStoreError> {
+        let mut file = fs::File::open(self.path_for(key)?)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut data = Vec::with_capacity(length as usize);
+        file.take(length).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        fs::remove_file(self.path_for(key)?)?;
+        Ok(())
+    }
+}
+
+// Keeps objects in memory, a stand-in for a remote object store in development and tests
+#[derive(Default)]
+pub struct InMemoryMediaStore {
+    objects: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MediaStore for InMemoryMediaStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaStoreError> {
+        validate_key(key)?;
+        self.objects.write().unwrap().insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get_range(&self, key: &str, offset: u64, length: u64) -> Result<Vec<u8>, MediaStoreError> {
+        let objects = self.objects.read().unwrap();
+        let data = objects.get(key).ok_or(MediaStoreError::NotFound)?;
+
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(length as usize).min(data.len());
+        Ok(data[start..end].to_vec())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        self.objects.write().unwrap().remove(key).map(|_| ()).ok_or(MediaStoreError::NotFound)
+    }
+}
+
+// Picks the store from MEDIA_STORE ("local" by default, or "memory"). Local files go under MEDIA_ROOT.
+pub fn media_store_from_env() -> Result<Arc<dyn MediaStore>, MediaStoreError> {
+    let backend = env::var("MEDIA_STORE").unwrap_or_else(|_| "local".to_string());
+
+    match backend.as_str() {
+        "local" => {
+            let root = env::var("MEDIA_ROOT").unwrap_or_else(|_| "./media".to_string());
+            Ok(Arc::new(LocalFsMediaStore::new(root)?))
+        }
+        "memory" => Ok(Arc::new(InMemoryMediaStore::default())),
+        _ => Err(MediaStoreError::UnsupportedBackend(backend)),
+    }
+}
+
+use mockall::mock;
+
+mock! {
+    pub MediaStore {}
+
+    impl MediaStore for MediaStore {
+        fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaStoreError>;
+        fn get_range(&self, key: &str, offset: u64, length: u64) -> Result<Vec<u8>, MediaStoreError>;
+        fn delete(&self, key: &str) -> Result<(), MediaStoreError>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise_store(store: &dyn MediaStore) {
+        store.put("abc-123", b"hello media store").expect("Failed to put object");
+
+        assert_eq!(store.get_range("abc-123", 0, 5).unwrap(), b"hello");
+        assert_eq!(store.get_range("abc-123", 6, 100).unwrap(), b"media store");
+
+        store.delete("abc-123").expect("Failed to delete object");
+        assert!(matches!(store.get_range("abc-123", 0, 5), Err(MediaStoreError::NotFound)));
+        assert!(matches!(store.put("../escape", b"nope"), Err(MediaStoreError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_local_fs_media_store() {
+        let root = env::temp_dir().join(format!("tarnish-media-{}", uuid::Uuid::new_v4()));
+        let store = LocalFsMediaStore::new(&root).expect("Failed to create media root");
+
+        exercise_store(&store);
+        fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_in_memory_media_store() {
+        exercise_store(&InMemoryMediaStore::default());
+    }
+}
@@ -1 +1,5 @@
+pub mod content_cache;
+pub mod live_events;
+pub mod media_store;
 pub mod postgres_connector;
+pub mod view_counter;
@@ -0,0 +1,192 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest};
+use chrono::NaiveDate;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+// Counting a view should never hold up the read it came from
+const REDIS_TIMEOUT: Duration = Duration::from_millis(250);
+
+// Long enough for the rollup to pick up a day after it ends, even with a few missed runs
+const KEY_TTL_SECONDS: i64 = 3 * 24 * 60 * 60;
+
+// Views of one post on one day, as counted in Redis
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyViews {
+    pub post_id: String,
+    pub views: i64,
+    pub uniques: i64,
+}
+
+// Counts post views in Redis, the view total with INCR and unique visitors with a HyperLogLog per post and day.
+// Visitors go into the HyperLogLog as a hash of their IP and user agent with a salt that changes every day,
+// so nothing stored can be traced back to a visitor, nor linked across days.
+pub struct ViewCounter {
+    redis_client: redis::Client,
+    errors: AtomicU64,
+}
+
+impl ViewCounter {
+    pub fn new(redis_client: redis::Client) -> Self {
+        ViewCounter {
+            redis_client,
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    // Redis calls that failed since the server started, those views went uncounted
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    fn day_key(kind: &str, day: NaiveDate) -> String {
+        format!("views:{}:{}", kind, day.format("%Y-%m-%d"))
+    }
+
+    fn post_key(kind: &str, day: NaiveDate, post_id: &str) -> String {
+        format!("views:{}:{}:{}", kind, day.format("%Y-%m-%d"), post_id)
+    }
+
+    async fn connection(&self) -> Option<redis::aio::MultiplexedConnection> {
+        let config =
+            redis::AsyncConnectionConfig::new()
+                .set_connection_timeout(REDIS_TIMEOUT)
+                .set_response_timeout(REDIS_TIMEOUT);
+
+        match self.redis_client.get_multiplexed_async_connection_with_config(&config).await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                log::warn!("View counter unavailable: {:?}", e);
+                None
+            }
+        }
+    }
+
+    // The salt for `day`. Whichever server asks first picks it, and it expires with the day's counters.
+    async fn daily_salt(conn: &mut redis::aio::MultiplexedConnection, day: NaiveDate) -> redis::RedisResult<String> {
+        let key = Self::day_key("salt", day);
+        let _: Option<String> =
+            redis::cmd("SET")
+                .arg(&key)
+                .arg(Uuid::new_v4().to_string())
+                .arg("NX")
+                .arg("EX")
+                .arg(KEY_TTL_SECONDS)
+                .query_async(conn)
+                .await?;
+        conn.get(&key).await
+    }
+
+    // Counts one view of `post_id` on `day` by the visitor behind `ip` and `user_agent`
+    pub async fn record(&self, post_id: &str, day: NaiveDate, ip: &str, user_agent: &str) {
+        let Some(mut conn) = self.connection().await else { return };
+
+        let result = async {
+            let salt = Self::daily_salt(&mut conn, day).await?;
+            let visitor = visitor_hash(&salt, ip, user_agent);
+
+            let views_key = Self::post_key("count", day, post_id);
+            let uniques_key = Self::post_key("uniques", day, post_id);
+            let posts_key = Self::day_key("posts", day);
+            redis::pipe()
+                .incr(&views_key, 1).ignore()
+                .pfadd(&uniques_key, visitor).ignore()
+                .sadd(&posts_key, post_id).ignore()
+                .expire(&views_key, KEY_TTL_SECONDS).ignore()
+                .expire(&uniques_key, KEY_TTL_SECONDS).ignore()
+                .expire(&posts_key, KEY_TTL_SECONDS).ignore()
+                .query_async::<()>(&mut conn)
+                .await
+        };
+
+        if let Err(e) = result.await {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            log::warn!("Failed to count a view of '{}': {:?}", post_id, e);
+        }
+    }
+
+    // Every post viewed on `day` with its view and unique visitor counts so far
+    pub async fn load_day(&self, day: NaiveDate) -> Result<Vec<DailyViews>, String> {
+        let mut conn = self.connection().await.ok_or_else(|| "Redis is unavailable".to_string())?;
+
+        let post_ids: Vec<String> = conn.smembers(Self::day_key("posts", day)).await.map_err(|e| e.to_string())?;
+
+        let mut daily_views = Vec::with_capacity(post_ids.len());
+        for post_id in post_ids {
+            let (views, uniques): (Option<i64>, i64) =
+                redis::pipe()
+                    .get(Self::post_key("count", day, &post_id))
+                    .pfcount(Self::post_key("uniques", day, &post_id))
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            daily_views.push(DailyViews { post_id, views: views.unwrap_or(0), uniques });
+        }
+        Ok(daily_views)
+    }
+}
+
+// What a visitor goes into the HyperLogLog as
+pub fn visitor_hash(salt: &str, ip: &str, user_agent: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(salt.as_bytes())
+        .chain_update(b"\n")
+        .chain_update(ip.as_bytes())
+        .chain_update(b"\n")
+        .chain_update(user_agent.as_bytes())
+        .finalize();
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Counts a view of `post_id` through the app's ViewCounter, if it has one.
+// The count happens in the background so the read is answered straight away.
+pub fn count_view(req: &HttpRequest, post_id: &str) {
+    let Some(counter) = req.app_data::<web::Data<ViewCounter>>().cloned() else { return };
+
+    let post_id = post_id.to_string();
+    let ip = req.connection_info().realip_remote_addr().unwrap_or("unknown").to_string();
+    let user_agent =
+        req.headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+    let day = chrono::Utc::now().date_naive();
+
+    actix_web::rt::spawn(async move {
+        counter.record(&post_id, day, &ip, &user_agent).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visitor_hash() {
+        let hash = visitor_hash("salt", "203.0.113.7", "Mozilla/5.0");
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains("203.0.113.7"));
+
+        // Same visitor on the same day, a different visitor, and the same visitor on another day
+        assert_eq!(hash, visitor_hash("salt", "203.0.113.7", "Mozilla/5.0"));
+        assert_ne!(hash, visitor_hash("salt", "203.0.113.8", "Mozilla/5.0"));
+        assert_ne!(hash, visitor_hash("other salt", "203.0.113.7", "Mozilla/5.0"));
+    }
+
+    #[actix_web::test]
+    async fn test_view_counter_fails_open_without_redis() {
+        // Nothing listens on port 1, so every Redis call fails
+        let counter = ViewCounter::new(redis::Client::open("redis://127.0.0.1:1").unwrap());
+        let day = NaiveDate::from_ymd_opt(2024, 8, 29).unwrap();
+
+        counter.record("post_id_mikey", day, "203.0.113.7", "Mozilla/5.0").await;
+        assert!(counter.load_day(day).await.is_err());
+        assert_eq!(counter.errors(), 2);
+    }
+}
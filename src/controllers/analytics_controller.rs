@@ -0,0 +1,158 @@
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Nullable, Text};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::login_controller::session_from_request;
+use crate::models::Pagination::parse_date_filter;
+
+// The range covered when the client does not give one, ending today
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+// Query string for the views report, e.g. ?from=2024-08-01&to=2024-08-31. Both ends are included.
+#[derive(Debug, Default, Deserialize)]
+pub struct ViewsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// One post's views over the report's range. Uniques are the daily unique visitors added up,
+// a visitor who came back on another day counts again.
+#[derive(Debug, QueryableByName, Serialize, Deserialize)]
+pub struct PostViewTotals {
+    #[diesel(sql_type = Text)]
+    pub post_id: String,
+    // None once the post is gone
+    #[diesel(sql_type = Nullable<Text>)]
+    pub title: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub views: i64,
+    #[diesel(sql_type = BigInt)]
+    pub uniques: i64,
+}
+
+// Rolled up views and uniques per post between `from` and `to`, most viewed first
+pub fn load_view_totals(conn: &mut PgConnection, from: NaiveDate, to: NaiveDate) -> QueryResult<Vec<PostViewTotals>> {
+    diesel::sql_query(
+        "SELECT v.post_id, p.title, SUM(v.views)::BIGINT AS views, SUM(v.uniques)::BIGINT AS uniques
+         FROM post_views v
+         LEFT JOIN posts p ON p.post_id = v.post_id AND p.deleted_at IS NULL
+         WHERE v.day BETWEEN $1 AND $2
+         GROUP BY v.post_id, p.title
+         ORDER BY views DESC, v.post_id ASC",
+    )
+        .bind::<Date, _>(from)
+        .bind::<Date, _>(to)
+        .load::<PostViewTotals>(conn)
+}
+
+// Works out the report's range, the last DEFAULT_RANGE_DAYS days up to `today` by default
+pub fn views_range(query: &ViewsQuery, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+    let to = match query.to.as_deref() {
+        Some(raw) => parse_date_filter(raw)?.date(),
+        None => today,
+    };
+    let from = match query.from.as_deref() {
+        Some(raw) => parse_date_filter(raw)?.date(),
+        None => to - chrono::Duration::days(DEFAULT_RANGE_DAYS - 1),
+    };
+
+    if from > to {
+        return Err("from has to be on or before to".to_string());
+    }
+    Ok((from, to))
+}
+
+// Views and unique visitors per post, for admins. Counts reach Postgres with the view rollup job.
+#[get("/analytics/views")]
+async fn get_post_views(
+    pool: web::Data<DbPool>,
+    query: web::Query<ViewsQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    match session_from_request(&req).await {
+        Some(session) if session.is_admin() => {}
+        Some(_) => return Ok(HttpResponse::Forbidden().json(json!({ "error": "Only admins can view analytics" }))),
+        None => return Ok(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+
+    let (from, to) = match views_range(&query, chrono::Utc::now().date_naive()) {
+        Ok(range) => range,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match load_view_totals(&mut conn, from, to) {
+        Ok(posts) => Ok(HttpResponse::Ok().json(json!({ "from": from, "to": to, "posts": posts }))),
+        Err(e) => {
+            eprintln!("Error loading post views: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::view_counter::DailyViews;
+    use crate::jobs::view_rollup::store_daily_views;
+    use crate::schemas::post_view_schema::post_views;
+    use diesel::r2d2::{self, ConnectionManager};
+    use dotenv::dotenv;
+    use std::env;
+
+    fn establish_connection() -> DbPool {
+        dotenv().ok();
+        let database_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        r2d2::Pool::builder().build(manager).expect("Failed to create pool.")
+    }
+
+    fn day(raw: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_views_range() {
+        let today = day("2024-08-29");
+        assert_eq!(views_range(&ViewsQuery::default(), today), Ok((day("2024-07-31"), today)));
+
+        let query = ViewsQuery { from: Some("2024-08-01".to_string()), to: Some("2024-08-10".to_string()) };
+        assert_eq!(views_range(&query, today), Ok((day("2024-08-01"), day("2024-08-10"))));
+
+        let backwards = ViewsQuery { from: Some("2024-08-10".to_string()), to: Some("2024-08-01".to_string()) };
+        assert!(views_range(&backwards, today).is_err());
+        assert!(views_range(&ViewsQuery { to: Some("yesterday".to_string()), ..Default::default() }, today).is_err());
+    }
+
+    #[test]
+    fn test_rolled_up_views() {
+        let pool = establish_connection();
+        let mut conn = pool.get().unwrap();
+        let daily = |post_id: &str, views: i64, uniques: i64| DailyViews { post_id: post_id.to_string(), views, uniques };
+
+        store_daily_views(&mut conn, day("2024-08-01"), &[daily("views_post_a", 10, 4), daily("views_post_b", 3, 3)]).unwrap();
+        store_daily_views(&mut conn, day("2024-08-02"), &[daily("views_post_a", 5, 2)]).unwrap();
+        // A later rollup of the same day replaces the counts, but never with lower ones
+        store_daily_views(&mut conn, day("2024-08-02"), &[daily("views_post_a", 7, 3)]).unwrap();
+        store_daily_views(&mut conn, day("2024-08-02"), &[daily("views_post_a", 1, 1)]).unwrap();
+        store_daily_views(&mut conn, day("2024-08-20"), &[daily("views_post_b", 100, 50)]).unwrap();
+
+        let totals = load_view_totals(&mut conn, day("2024-08-01"), day("2024-08-10")).unwrap();
+        let totals: Vec<(&str, i64, i64)> =
+            totals
+                .iter()
+                .filter(|total| total.post_id.starts_with("views_post_"))
+                .map(|total| (total.post_id.as_str(), total.views, total.uniques))
+                .collect();
+        assert_eq!(totals, vec![("views_post_a", 17, 7), ("views_post_b", 3, 3)]);
+
+        diesel::delete(post_views::table.filter(post_views::post_id.like("views_post_%"))).execute(&mut conn).unwrap();
+    }
+}
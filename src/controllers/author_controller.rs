@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use crate::models::Author::{Author, Authored};
+use crate::schemas::user_schema::users;
+
+// The authors behind a set of user ids, keyed by user id
+pub fn load_authors(conn: &mut PgConnection, author_ids: &[String]) -> QueryResult<HashMap<String, Author>> {
+    if author_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let authors =
+        users::table
+            .filter(users::user_id.eq_any(author_ids))
+            .select((users::user_id, users::username))
+            .load::<Author>(conn)?;
+
+    Ok(authors.into_iter().map(|author| (author.user_id.clone(), author)).collect())
+}
+
+// Pairs each item with its author in one query for the whole batch
+pub fn attach_authors<T, F>(conn: &mut PgConnection, items: Vec<T>, author_id: F) -> QueryResult<Vec<Authored<T>>>
+where
+    F: Fn(&T) -> Option<&String>,
+{
+    let mut author_ids: Vec<String> = items.iter().filter_map(|item| author_id(item).cloned()).collect();
+    author_ids.sort();
+    author_ids.dedup();
+    let authors = load_authors(conn, &author_ids)?;
+
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let author = author_id(&item).and_then(|id| authors.get(id)).cloned();
+            Authored { item, author }
+        })
+        .collect())
+}
+
+// The user id behind an author username, for filtering lists with ?author=
+pub fn author_ids_named(username: &str) -> users::BoxedQuery<'static, diesel::pg::Pg, diesel::sql_types::Nullable<diesel::sql_types::Varchar>> {
+    users::table
+        .filter(users::username.eq(username.to_string()))
+        .select(users::user_id.nullable())
+        .into_boxed()
+}
@@ -1,74 +1,203 @@
-use actix_web::{delete, get, post, put, web, Error, HttpResponse};
+use actix_web::http::header::ETag;
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-
+use uuid::Uuid;
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind, CacheLookup};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::view_counter::count_view;
+use crate::controllers::author_controller::author_ids_named;
+use crate::controllers::login_controller::session_from_request;
+use crate::controllers::post_revision_controller::{ensure_initial_revision, snapshot_post};
+use crate::controllers::slug_controller::{refresh_post_slug, unique_slug};
+use crate::controllers::tag_controller::{attach_tags, normalize_tag_names, set_post_tags};
+use crate::controllers::translation_controller::{preferred_locales, translate_posts, vary_on_language, LangQuery};
+use crate::controllers::webhook_controller::enqueue_event;
+use crate::models::Pagination::{CursorKey, ListParams, ListQuery, Page, SortField, SortOrder};
+use crate::models::PostStatus::PostStatus;
+use crate::models::Rendered::{RenderQuery, Rendered};
+use crate::models::WebhookEvent::WebhookEvent;
 use crate::schemas::blog_schema::posts;
+use crate::schemas::tag_schema::{post_tags, tags};
 use crate::table_models::blog_models::{NewPost, Post};
+use crate::utils::etag::{etag_for, if_match_version, precondition_failed};
+use crate::utils::markdown::{highlight_css, render_markdown};
 // Import schema
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDateTime};
 
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+// Body of the create and update endpoints. The post_id, id and timestamps are the server's to set,
+// clients that still send them have them ignored.
 #[derive(Serialize, Deserialize)]
 pub struct PostInput {
-    pub id: i32,
-    pub post_id: String,
     pub title: String,
     pub body: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub status: Option<PostStatus>,
+    pub publish_at: Option<String>,
+    pub tags: Option<Vec<String>>,
 }
 
-impl PostInput {
-    // Constructor method for creating a new PostInput
-    pub fn new(id: i32, post_id: String, title: String, body: String, created_at: String, updated_at: String) -> Self {
-        PostInput {
-            id,
-            post_id,
-            title,
-            body,
-            created_at,
-            updated_at,
+// Post specific query string for the list endpoint, e.g. ?status=draft&tag=rust&author=mikey
+#[derive(Debug, Default, Deserialize)]
+pub struct PostListQuery {
+    pub status: Option<PostStatus>,
+    pub tag: Option<String>,
+    pub author: Option<String>,
+}
+
+// Post specific filters layered on top of the shared ListParams, an empty list of statuses means any status
+#[derive(Debug, Default)]
+pub struct PostFilters {
+    pub statuses: Vec<PostStatus>,
+    pub tag: Option<String>,
+    // Author username
+    pub author: Option<String>,
+}
+
+impl PostFilters {
+    pub fn published_only() -> Self {
+        PostFilters {
+            statuses: vec![PostStatus::Published],
+            ..Default::default()
         }
     }
 }
 
+// Admins and editors can see posts that have not been published yet
+pub async fn can_view_unpublished(req: &HttpRequest) -> bool {
+    matches!(session_from_request(req).await, Some(session) if session.is_editor_or_admin())
+}
+
+// Puts `event` for a post in the webhook outbox, with the post as it is now. Call it in the write's transaction.
+// Trashed copies can share the post_id, so the live post wins, then the most recently trashed copy.
+pub fn enqueue_post_event(conn: &mut PgConnection, event: WebhookEvent, post_id: &str) -> QueryResult<i32> {
+    let id =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .order(posts::deleted_at.desc().nulls_first())
+            .select(posts::id)
+            .first::<i32>(conn)?;
+    enqueue_post_row_event(conn, event, id)
+}
+
+// Same as `enqueue_post_event`, for one row of the posts table picked by its `id`
+pub fn enqueue_post_row_event(conn: &mut PgConnection, event: WebhookEvent, id: i32) -> QueryResult<i32> {
+    let (post_id, slug, title, status) =
+        posts::table
+            .find(id)
+            .select((posts::post_id, posts::slug, posts::title, posts::status))
+            .first::<(String, String, String, PostStatus)>(conn)?;
+    enqueue_event(conn, event, json!({ "post_id": post_id, "slug": slug, "title": title, "status": status }))
+}
+
+// Works out the status and publish time for a write. Scheduled posts need a publish time,
+// and a publish time in the future without an explicit status schedules the post.
+pub fn resolve_publication(
+    status: Option<PostStatus>,
+    publish_at: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<(PostStatus, Option<NaiveDateTime>), String> {
+    let publish_at =
+        publish_at
+            .map(|raw| {
+                DateTime::parse_from_rfc3339(raw)
+                    .map(|timestamp| timestamp.naive_utc())
+                    .map_err(|_| format!("publish_at '{}' is not a valid RFC 3339 timestamp", raw))
+            })
+            .transpose()?;
+    resolve_publication_at(status, publish_at, now)
+}
+
+// Same as `resolve_publication`, for a publish time that is already parsed
+pub fn resolve_publication_at(
+    status: Option<PostStatus>,
+    publish_at: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+) -> Result<(PostStatus, Option<NaiveDateTime>), String> {
+    let status =
+        status.unwrap_or(match publish_at {
+            Some(publish_at) if publish_at > now => PostStatus::Scheduled,
+            _ => PostStatus::Published,
+        });
+
+    match (status, publish_at) {
+        (PostStatus::Scheduled, None) => Err("Scheduled posts need a publish_at timestamp".to_string()),
+        (PostStatus::Published, None) => Ok((status, Some(now))),
+        _ => Ok((status, publish_at)),
+    }
+}
+
 #[post("/blog/post/create")]
 async fn create_post(
     pool: web::Data<DbPool>,
     post: web::Json<PostInput>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let post_input = post.into_inner();
+    let author_id = session_from_request(&req).await.map(|session| session.user_id);
+
+    let tag_labels =
+        match post_input.tags.as_deref().map(normalize_tag_names).transpose() {
+            Ok(tag_labels) => tag_labels,
+            Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+        };
 
-    let new_post =
+    let now = chrono::Utc::now().naive_utc();
+    let (status, publish_at) =
+        match resolve_publication(post_input.status, post_input.publish_at.as_deref(), now) {
+            Ok(publication) => publication,
+            Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+        };
+
+    let mut new_post =
         NewPost {
-            post_id: post_input.post_id,
+            post_id: Uuid::new_v4().to_string(),
             title: post_input.title,
             body: post_input.body,
-            created_at: DateTime::parse_from_rfc3339(&post_input.created_at)
-                .unwrap()
-                .naive_utc(), // Convert to NaiveDateTime
-            updated_at: DateTime::parse_from_rfc3339(&post_input.updated_at)
-                .unwrap()
-                .naive_utc(), // Convert to NaiveDateTime
+            created_at: now,
+            updated_at: now,
+            status,
+            publish_at,
+            // Filled in from the title once we have a connection
+            slug: String::new(),
+            author_id: author_id.clone(),
         };
     let mut conn =
         pool.get().map_err(|e| {
             actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
         })?;
 
-    conn.transaction::<_, diesel::result::Error, _>(|conn| {
-        // Insert the new post
-        diesel::insert_into(posts::table)
-            .values(&new_post)
-            .get_result::<Post>(conn)
-            .map_err(|e| {
-                eprintln!("Error inserting new post: {:?}", e);
-                e
-            })
-    }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))
-        .map(|post| HttpResponse::Created().json(post))
+    let created =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            new_post.slug = unique_slug(conn, &new_post.title, None)?;
+
+            // Insert the new post
+            let post =
+                diesel::insert_into(posts::table)
+                    .values(&new_post)
+                    .returning(Post::as_returning())
+                    .get_result(conn)
+                    .map_err(|e| {
+                        eprintln!("Error inserting new post: {:?}", e);
+                        e
+                    })?;
+
+            if let Some(tag_labels) = &tag_labels {
+                set_post_tags(conn, &post.post_id, tag_labels)?;
+            }
+
+            // The first revision is the post as it was created
+            snapshot_post(conn, &post.post_id, author_id)?;
+            enqueue_post_event(conn, WebhookEvent::PostCreated, &post.post_id)?;
+            attach_tags(conn, vec![post])
+        }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))?;
+
+    invalidate_cached(&req, CacheKind::Posts).await;
+    publish_live(&req, LiveKind::Post, LiveAction::Created, &created[0].post.post_id).await;
+    Ok(HttpResponse::Created().json(&created[0]))
 }
 
 
@@ -76,14 +205,49 @@ async fn create_post(
 async fn get_by_post_id(
     path: web::Path<String>,
     pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    lang_query: web::Query<LangQuery>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let post_id = path.into_inner();
+    let preferred = match preferred_locales(&req, lang_query.lang.as_deref()) {
+        Ok(preferred) => preferred,
+        Err(response) => return Ok(response),
+    };
+    // Drafts are never cached, so only what everyone sees goes through the cache
+    let show_unpublished = can_view_unpublished(&req).await;
+    let mut cache = CacheLookup::new(&req, CacheKind::Posts, !show_unpublished).await;
+    if let Some(response) = cache.cached_response(&req) {
+        count_view(&req, &post_id);
+        return Ok(vary_on_language(response));
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match posts::table.filter(posts::post_id.eq(post_id)).first::<Post>(&mut conn) {
-        Ok(post) => Ok(HttpResponse::Ok().json(post)),
+    let mut query = posts::table.filter(posts::post_id.eq(&post_id)).filter(posts::deleted_at.is_null()).into_boxed();
+    if !show_unpublished {
+        query = query.filter(posts::status.eq(PostStatus::Published));
+    }
+
+    let post =
+        query
+            .select(Post::as_select())
+            .first(&mut conn)
+            .and_then(|post| attach_tags(&mut conn, vec![post]))
+            .and_then(|mut posts| translate_posts(&mut conn, &mut posts, &preferred).map(|_| posts));
+
+    match post {
+        Ok(posts) => {
+            // Staff reading drafts or checking their edits are not readers
+            if !show_unpublished {
+                count_view(&req, &posts[0].post.post_id);
+            }
+            // Reactions, tags, series navigation and translations change the body without touching updated_at
+            let updated_at = posts[0].post.updated_at;
+            Ok(vary_on_language(cache.respond_post(&req, updated_at, &Rendered::all(posts, render_query.render).remove(0)).await))
+        }
         Err(_) => Ok(HttpResponse::NotFound().finish()),
     }
 }
@@ -92,40 +256,223 @@ async fn get_by_post_id(
 async fn get_post(
     path: web::Path<i32>,
     pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    lang_query: web::Query<LangQuery>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let id = path.into_inner();
+    let preferred = match preferred_locales(&req, lang_query.lang.as_deref()) {
+        Ok(preferred) => preferred,
+        Err(response) => return Ok(response),
+    };
+    // Drafts are never cached, so only what everyone sees goes through the cache
+    let show_unpublished = can_view_unpublished(&req).await;
+    let mut cache = CacheLookup::new(&req, CacheKind::Posts, !show_unpublished).await;
+    if let Some(post_id) = cache.cached_string("post_id") {
+        count_view(&req, &post_id);
+    }
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(vary_on_language(response));
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match posts::table.find(id).first::<Post>(&mut conn) {
-        Ok(post) => Ok(HttpResponse::Ok().json(post)),
+    let mut query = posts::table.filter(posts::id.eq(id)).filter(posts::deleted_at.is_null()).into_boxed();
+    if !show_unpublished {
+        query = query.filter(posts::status.eq(PostStatus::Published));
+    }
+
+    let post =
+        query
+            .select(Post::as_select())
+            .first(&mut conn)
+            .and_then(|post| attach_tags(&mut conn, vec![post]))
+            .and_then(|mut posts| translate_posts(&mut conn, &mut posts, &preferred).map(|_| posts));
+
+    match post {
+        Ok(posts) => {
+            // Staff reading drafts or checking their edits are not readers
+            if !show_unpublished {
+                count_view(&req, &posts[0].post.post_id);
+            }
+            // Reactions, tags, series navigation and translations change the body without touching updated_at
+            let updated_at = posts[0].post.updated_at;
+            Ok(vary_on_language(cache.respond_post(&req, updated_at, &Rendered::all(posts, render_query.render).remove(0)).await))
+        }
         Err(_) => Ok(HttpResponse::NotFound().finish()),
     }
 }
 
+// Loads one keyset page of posts along with the total number of posts matching the filters
+pub fn load_posts_page(conn: &mut PgConnection, params: &ListParams, filters: &PostFilters) -> QueryResult<Page<Post>> {
+    let filtered = || {
+        // Trashed posts only show up in the trash
+        let mut query = posts::table.filter(posts::deleted_at.is_null()).into_boxed();
+        if !filters.statuses.is_empty() {
+            query = query.filter(posts::status.eq_any(filters.statuses.clone()));
+        }
+        if let Some(from) = params.from {
+            query = query.filter(posts::created_at.ge(from));
+        }
+        if let Some(to) = params.to {
+            query = query.filter(posts::created_at.lt(to));
+        }
+        if let Some(tag) = &filters.tag {
+            query = query.filter(
+                posts::post_id.eq_any(
+                    post_tags::table
+                        .inner_join(tags::table)
+                        .filter(tags::slug.eq(tag.clone()))
+                        .select(post_tags::post_id),
+                ),
+            );
+        }
+        if let Some(author) = &filters.author {
+            query = query.filter(posts::author_id.eq_any(author_ids_named(author)));
+        }
+        query
+    };
+
+    let total_count = filtered().count().get_result::<i64>(conn)?;
+
+    let after = params.after.as_ref();
+    let ordered =
+        match params.sort {
+            SortField::CreatedAt =>
+                crate::keyset_paginate!(filtered(), posts::created_at, posts::id, params.order, after.and_then(|c| c.timestamp().map(|t| (t, c.id)))),
+            SortField::UpdatedAt =>
+                crate::keyset_paginate!(filtered(), posts::updated_at, posts::id, params.order, after.and_then(|c| c.timestamp().map(|t| (t, c.id)))),
+            SortField::Title =>
+                crate::keyset_paginate!(filtered(), posts::title, posts::id, params.order, after.and_then(|c| c.text().map(|t| (t, c.id)))),
+        };
+
+    let rows = ordered.select(Post::as_select()).limit(params.limit + 1).load(conn)?;
+
+    Ok(Page::from_rows(rows, params, total_count, |post| {
+        let key = match params.sort {
+            SortField::CreatedAt => CursorKey::Timestamp(post.created_at),
+            SortField::UpdatedAt => CursorKey::Timestamp(post.updated_at),
+            SortField::Title => CursorKey::Text(post.title.clone()),
+        };
+        (key, post.id)
+    }))
+}
+
 #[get("/blog/post/get/all")]
-async fn get_all_posts(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+async fn get_all_posts(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+    post_query: web::Query<PostListQuery>,
+    render_query: web::Query<RenderQuery>,
+    lang_query: web::Query<LangQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    // Newest posts first unless the client asks otherwise
+    let params = match query.into_inner().into_params(SortOrder::Desc) {
+        Ok(params) => params,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+    };
+
+    let post_query = post_query.into_inner();
+    let preferred = match preferred_locales(&req, lang_query.lang.as_deref()) {
+        Ok(preferred) => preferred,
+        Err(response) => return Ok(response),
+    };
+
+    // Everyone else only ever sees published posts
+    let show_unpublished = can_view_unpublished(&req).await;
+    let mut filters =
+        if show_unpublished {
+            PostFilters {
+                statuses: post_query.status.into_iter().collect(),
+                ..Default::default()
+            }
+        } else {
+            PostFilters::published_only()
+        };
+    filters.tag = post_query.tag;
+    filters.author = post_query.author;
+
+    // Drafts are never cached, so only what everyone sees goes through the cache
+    let mut cache = CacheLookup::new(&req, CacheKind::Posts, !show_unpublished).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(vary_on_language(response));
+    }
+
     // Get a connection from the pool
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    // Query the posts, ordering them by creation time (assuming created_at is the timestamp column)
-    match posts::table.order(posts::created_at.desc()).load::<Post>(&mut conn) {
-        Ok(posts) => Ok(HttpResponse::Ok().json(posts)),
+    match load_posts_page(&mut conn, &params, &filters).and_then(|page| page.try_map_items(|posts| {
+        let mut posts = attach_tags(&mut conn, posts)?;
+        translate_posts(&mut conn, &mut posts, &preferred)?;
+        Ok(posts)
+    })) {
+        Ok(page) => Ok(vary_on_language(cache.respond(&req, None, &page.map_items(|posts| Rendered::all(posts, render_query.render))).await)),
         Err(_) => Ok(HttpResponse::InternalServerError().finish()),
     }
 }
 
+// Body of the preview endpoint, only the Markdown is needed
+#[derive(Serialize, Deserialize)]
+pub struct PreviewInput {
+    pub body: String,
+}
+
+// Renders a Markdown body the same way retrieval with ?render=true does, without saving anything
+#[post("/blog/post/preview")]
+async fn preview_post(
+    preview: web::Json<PreviewInput>,
+) -> Result<HttpResponse, Error> {
+    let preview_input = preview.into_inner();
+
+    let rendered =
+        web::block(move || render_markdown(&preview_input.body))
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Rendering failed: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(rendered))
+}
+
+// Stylesheet for the highlighted code blocks in body_html
+#[get("/blog/markdown/highlight.css")]
+async fn get_highlight_css() -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/css; charset=utf-8")
+        .body(highlight_css()))
+}
+
 #[put("/blog/posts/update/{post_id}")]
 async fn update_post(
     path: web::Path<String>,
     post: web::Json<PostInput>,
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let post_id = path.into_inner();
     let post_input = post.into_inner();
+    let author_id = session_from_request(&req).await.map(|session| session.user_id);
+
+    // Tags are only replaced when the client sends them
+    let tag_labels =
+        match post_input.tags.as_deref().map(normalize_tag_names).transpose() {
+            Ok(tag_labels) => tag_labels,
+            Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+        };
+
+    // Status is only touched when the client sends one
+    let publication =
+        if post_input.status.is_some() || post_input.publish_at.is_some() {
+            match resolve_publication(post_input.status, post_input.publish_at.as_deref(), chrono::Utc::now().naive_utc()) {
+                Ok(publication) => Some(publication),
+                Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+            }
+        } else {
+            None
+        };
 
     let mut conn =
         pool.get().map_err(|e| {
@@ -136,31 +483,75 @@ async fn update_post(
     let post_title =
         posts::table
             .filter(posts::post_id.eq(&post_id))
-            .select(posts::title)
-            .first::<String>(&mut conn)
+            .filter(posts::deleted_at.is_null())
+            .select((posts::title, posts::updated_at))
+            .first::<(String, NaiveDateTime)>(&mut conn)
             .optional()
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error retrieving post title: {}", e))
             })?;
 
     match post_title {
-        Some(title) => {
-            match diesel::update(posts::table.filter(posts::post_id.eq(post_id)))
-                .set((
-                    posts::id.eq(post_input.id),
-                    posts::post_id.eq(post_input.post_id),
-                    posts::title.eq(post_input.title),
-                    posts::body.eq(post_input.body),
-                ))
-                .execute(&mut conn)
+        Some((title, updated_at)) => {
+            let expected_version = match if_match_version(&req, updated_at) {
+                Ok(expected_version) => expected_version,
+                Err(response) => return Ok(response),
+            };
+
+            // The update and its revision snapshot are written together or not at all
+            let update_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                ensure_initial_revision(conn, &post_id)?;
+
+                let mut update =
+                    diesel::update(posts::table.filter(posts::post_id.eq(&post_id)).filter(posts::deleted_at.is_null()))
+                        .set((
+                            posts::title.eq(post_input.title),
+                            posts::body.eq(post_input.body),
+                            posts::updated_at.eq(chrono::Utc::now().naive_utc()),
+                        ))
+                        .returning(posts::updated_at)
+                        .into_boxed();
+                // With If-Match, only overwrite the version that was checked, a concurrent write in between rolls this one back
+                if let Some(expected_version) = expected_version {
+                    update = update.filter(posts::updated_at.eq(expected_version));
+                }
+                let new_updated_at =
+                    update
+                        .get_result::<NaiveDateTime>(conn)
+                        .optional()?
+                        .ok_or(diesel::result::Error::RollbackTransaction)?;
+
+                if let Some((status, publish_at)) = publication {
+                    diesel::update(posts::table.filter(posts::post_id.eq(&post_id)).filter(posts::deleted_at.is_null()))
+                        .set((posts::status.eq(status), posts::publish_at.eq(publish_at)))
+                        .execute(conn)?;
+                }
+
+                // A new title gets a new slug, the old one keeps redirecting
+                refresh_post_slug(conn, &post_id)?;
+
+                if let Some(tag_labels) = &tag_labels {
+                    set_post_tags(conn, &post_id, tag_labels)?;
+                }
+
+                snapshot_post(conn, &post_id, author_id)?;
+                enqueue_post_event(conn, WebhookEvent::PostUpdated, &post_id)?;
+                Ok(new_updated_at)
+            });
+
+            match update_result
             {
-                Ok(_) => {
+                Ok(new_updated_at) => {
+                    invalidate_cached(&req, CacheKind::Posts).await;
+                    publish_live(&req, LiveKind::Post, LiveAction::Updated, &post_id).await;
                     let response_body =
                         json!({"message": format!("Blog post '{}' has been updated", title)});
                     Ok(HttpResponse::Ok()
                         .content_type("application/json")
+                        .insert_header(ETag(etag_for(new_updated_at)))
                         .json(response_body))
                 }
+                Err(diesel::result::Error::RollbackTransaction) => Ok(precondition_failed(updated_at)),
                 Err(e) => {
                     eprintln!("Error updating post: {:?}", e);
                     Ok(HttpResponse::InternalServerError().finish())
@@ -185,6 +576,7 @@ async fn update_post(
 async fn delete_post(
     path: web::Path<String>,  // Changed to String since post_id is a varchar
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let post_id = path.into_inner();
     let mut conn =
@@ -196,20 +588,44 @@ async fn delete_post(
     let post_title =
         posts::table
             .filter(posts::post_id.eq(&post_id))
-            .select(posts::title)
-            .first::<String>(&mut conn)
+            .filter(posts::deleted_at.is_null())
+            .select((posts::title, posts::updated_at))
+            .first::<(String, NaiveDateTime)>(&mut conn)
             .optional()
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error retrieving post title: {}", e))
             })?;
 
     match post_title {
-        Some(title) => {
-            // Now delete the post
-            match diesel::delete(posts::table.filter(posts::post_id.eq(&post_id))).execute(&mut conn) {
+        Some((title, updated_at)) => {
+            let expected_version = match if_match_version(&req, updated_at) {
+                Ok(expected_version) => expected_version,
+                Err(response) => return Ok(response),
+            };
+
+            // Move the post to the trash, its revision history, tags, comments and old slugs stay for a restore
+            let delete_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let mut trash =
+                    diesel::update(posts::table.filter(posts::post_id.eq(&post_id)).filter(posts::deleted_at.is_null()))
+                        .set(posts::deleted_at.eq(chrono::Utc::now().naive_utc()))
+                        .into_boxed();
+                if let Some(expected_version) = expected_version {
+                    trash = trash.filter(posts::updated_at.eq(expected_version));
+                }
+                let trashed = trash.execute(conn)?;
+                if trashed > 0 {
+                    enqueue_post_event(conn, WebhookEvent::PostDeleted, &post_id)?;
+                }
+                Ok(trashed)
+            });
+
+            match delete_result {
+                Ok(0) => Ok(precondition_failed(updated_at)),
                 Ok(_) => {
+                    invalidate_cached(&req, CacheKind::Posts).await;
+                    publish_live(&req, LiveKind::Post, LiveAction::Deleted, &post_id).await;
                     let response_body = json!({
-                        "message": format!("Blog post '{}' has been deleted", title)
+                        "message": format!("Blog post '{}' has been moved to the trash", title)
                     });
 
                     Ok(HttpResponse::Ok()
@@ -238,15 +654,32 @@ async fn delete_post(
 #[delete("/blog/post/all")]
 async fn delete_all_posts(
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match diesel::sql_query("TRUNCATE TABLE posts RESTART IDENTITY CASCADE").execute(&mut conn) {
-        Ok(_) => {
+    let trash_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let trashed =
+            diesel::update(posts::table.filter(posts::deleted_at.is_null()))
+                .set(posts::deleted_at.eq(chrono::Utc::now().naive_utc()))
+                .returning(posts::post_id)
+                .get_results::<String>(conn)?;
+        for post_id in &trashed {
+            enqueue_post_event(conn, WebhookEvent::PostDeleted, post_id)?;
+        }
+        Ok(trashed)
+    });
+
+    match trash_result {
+        Ok(trashed) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            for post_id in &trashed {
+                publish_live(&req, LiveKind::Post, LiveAction::Deleted, post_id).await;
+            }
             let response_body = json!({
-                "message": "All posts have been deleted."
+                "message": "All posts have been moved to the trash."
             });
             Ok(HttpResponse::Ok()
                 .content_type("application/json")
@@ -264,8 +697,32 @@ mod blog_controller_spec {
     use std::env;
 
     use crate::controllers::blog_controller::*;
+    use crate::jobs::publish_scheduler::publish_due_posts;
+    use crate::models::PostStatus::PostStatus;
+    use crate::controllers::post_revision_controller::{diff_post_revisions, get_post_revision, get_post_revisions, restore_post_revision};
+    use crate::controllers::comment_controller::{create_comment, get_moderation_queue, get_post_comments, moderate_comment};
+    use crate::controllers::feed_controller::{atom_feed, json_feed, rss_feed};
+    use crate::controllers::slug_controller::get_by_slug;
+    use crate::controllers::trash_controller::{
+        empty_trash, get_trash, purge_from_trash, purge_trash, restore_from_trash, TrashKind,
+    };
+    use crate::connectors::content_cache::ContentCache;
+    use crate::controllers::login_controller::TestSessions;
+    use crate::controllers::worklog_controller::delete_worklog;
+    use crate::jobs::trash_purger::purge_expired_trash;
+    use crate::schemas::comment_schema::comments;
+    use crate::schemas::post_reaction_schema::post_reactions;
+    use crate::schemas::post_revision_schema::post_revisions;
+    use crate::schemas::slug_history_schema::slug_history;
+    use crate::schemas::webhook_schema::webhook_events;
+    use crate::schemas::worklog_schema::worklog;
+    use crate::table_models::slug_history_models::NewSlugHistory;
+    use crate::table_models::worklog_models::NewWorklog;
+    use crate::controllers::tag_controller::get_tag_cloud;
+    use crate::models::CommentStatus::CommentStatus;
     // use crate::{delete_post, posts, DbPool, NewPost};
 
+    use actix_web::cookie::Cookie;
     use actix_web::{body::to_bytes, http::StatusCode, test, web, App};
     use bytes::Bytes;
     use chrono::NaiveDateTime;
@@ -273,6 +730,7 @@ mod blog_controller_spec {
     use diesel::{r2d2, PgConnection};
     use dotenv::dotenv;
     use serde_json::{json, Value};
+    use std::collections::HashMap;
 
     #[ctor::ctor]
     fn init() {
@@ -280,7 +738,7 @@ mod blog_controller_spec {
         let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
             pool.get().expect("Failed to get connection from pool");
 
-        diesel::sql_query("TRUNCATE TABLE posts RESTART IDENTITY CASCADE;")
+        diesel::sql_query("TRUNCATE TABLE posts, post_revisions, post_tags, post_reactions, post_translations, comments, slug_history RESTART IDENTITY CASCADE;")
             .execute(&mut conn)
             .expect("Failed to reset ID sequence");
     }
@@ -313,6 +771,9 @@ mod blog_controller_spec {
                 self.pool.get().expect("Failed to get connection from pool");
 
             for post_id in &self.post_ids {
+                diesel::delete(slug_history::table.filter(slug_history::post_id.eq(post_id)))
+                    .execute(&mut conn)
+                    .expect("Failed to delete test slug history");
                 diesel::delete(posts::table.filter(posts::post_id.eq(post_id)))
                     .execute(&mut conn)
                     .expect("Failed to delete test post");
@@ -331,12 +792,114 @@ mod blog_controller_spec {
     async fn run_all_tests_in_order_blog() {
         test_get_by_post_id().await;
         test_get_all_posts().await;
+        test_get_all_posts_paginated().await;
+        test_post_authors().await;
         test_create_post().await;
         test_update_post().await;
+        test_post_revisions().await;
+        test_post_status_visibility().await;
+        test_resolve_publication();
+        test_post_tags().await;
+        test_post_series().await;
+        test_related_posts().await;
+        test_post_reactions().await;
+        test_post_translations().await;
+        test_content_stats().await;
+        test_post_comments().await;
+        test_get_post_rendered().await;
+        test_feeds().await;
+        test_post_slugs().await;
+        test_trash().await;
+        test_import_export().await;
+        test_post_if_match().await;
+        test_post_conditional_get().await;
         test_delete_post().await;
         test_delete_all_posts().await;
     }
 
+    #[actix_rt::test]
+    async fn test_preview_post() {
+        let app = test::init_service(App::new().service(preview_post)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/blog/post/preview")
+            .set_json(json!({ "body": "## Try it\n\n```rust\nlet x = 1;\n```\n<img src=x onerror=alert(1)>" }))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+
+        let body_html = json_body["body_html"].as_str().unwrap();
+        assert!(body_html.contains("<h2 id=\"try-it\">Try it</h2>"));
+        assert!(body_html.contains("<code class=\"language-rust\">"));
+        assert!(!body_html.contains("onerror"));
+        assert_eq!(json_body["toc"][0]["anchor"], "try-it");
+    }
+
+    async fn test_post_authors() {
+        use crate::schemas::user_schema::users;
+        use crate::table_models::users::NewUsers;
+
+        let pool = web::Data::new(establish_connection());
+        let mut conn = pool.get().unwrap();
+        let now = chrono::Utc::now().naive_utc();
+
+        diesel::insert_into(users::table)
+            .values(&NewUsers {
+                user_id: "author_user".to_string(),
+                user_type: "editor".to_string(),
+                username: "post_author".to_string(),
+                password_hash: "not a real hash".to_string(),
+                email: "post_author@example.com".to_string(),
+                created_at: now,
+                updated_at: now,
+            })
+            .execute(&mut conn)
+            .expect("Failed to insert test user");
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(get_all_posts)
+                .service(get_by_post_id),
+        )
+            .await;
+
+        let post = |post_id: &str, author_id: Option<&str>| NewPost {
+            post_id: post_id.to_string(),
+            slug: post_id.to_string(),
+            title: post_id.to_string(),
+            body: "Written by someone.".to_string(),
+            created_at: now,
+            updated_at: now,
+            status: PostStatus::Published,
+            publish_at: None,
+            author_id: author_id.map(str::to_string),
+        };
+        let guard = TestGuard::new(pool.clone(), vec![post("authored_post", Some("author_user")), post("anonymous_post", None)]);
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/authored_post").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["author_id"], "author_user");
+        assert_eq!(json_body["author"], json!({ "user_id": "author_user", "username": "post_author" }));
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/anonymous_post").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body["author"].is_null());
+
+        // Only the author's posts, and nothing for a username nobody has
+        let req = test::TestRequest::get().uri("/blog/post/get/all?author=post_author").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["total_count"], 1);
+        assert_eq!(json_body["items"][0]["post_id"], "authored_post");
+        assert_eq!(json_body["items"][0]["author"]["username"], "post_author");
+
+        let req = test::TestRequest::get().uri("/blog/post/get/all?author=nobody").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["total_count"], 0);
+
+        drop(guard);
+        diesel::delete(users::table.filter(users::user_id.eq("author_user"))).execute(&mut conn).unwrap();
+    }
+
     async fn test_create_post() {
         let pool = web::Data::new(establish_connection());
 
@@ -354,22 +917,31 @@ mod blog_controller_spec {
         let posts_to_insert = vec![
             NewPost {
                 post_id: "abc123".to_string(),
+                slug: "abc123".to_string(),
                 title: "Test Post 1".to_string(),
                 body: "This is the first test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
             NewPost {
                 post_id: "def456".to_string(),
+                slug: "def456".to_string(),
                 title: "Test Post 2".to_string(),
                 body: "This is the second test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
         ];
 
         let _guard = TestGuard::new(pool.clone(), posts_to_insert);
 
+        // Ids and timestamps sent by the client are ignored, the server picks its own
         let payload =
             json!({
             "id": 200,
@@ -393,7 +965,29 @@ mod blog_controller_spec {
         let json_body: Value = serde_json::from_str(body_str).unwrap();
 
         let post_id = json_body.get("post_id").unwrap().as_str().unwrap();
-        assert_eq!(post_id, "abc200");
+        assert!(uuid::Uuid::parse_str(post_id).is_ok());
+        assert_ne!(json_body["id"], 200);
+        assert!(!json_body["created_at"].as_str().unwrap().starts_with("2023-08-29"));
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+
+        // The post.created event went into the webhook outbox along with the post
+        let outboxed =
+            webhook_events::table
+                .filter(webhook_events::event.eq(WebhookEvent::PostCreated))
+                .filter(webhook_events::payload.like(format!("%{}%", post_id)))
+                .count()
+                .get_result::<i64>(&mut conn)
+                .expect("Failed to read the webhook outbox");
+        assert_eq!(outboxed, 1);
+
+        diesel::delete(post_revisions::table.filter(post_revisions::post_id.eq(post_id)))
+            .execute(&mut conn)
+            .expect("Failed to delete test revisions");
+        diesel::delete(posts::table.filter(posts::post_id.eq(post_id)))
+            .execute(&mut conn)
+            .expect("Failed to delete test post");
     }
 
     async fn test_get_by_post_id() {
@@ -413,17 +1007,25 @@ mod blog_controller_spec {
         let posts_to_insert = vec![
             NewPost {
                 post_id: "abc123".to_string(),
+                slug: "abc123".to_string(),
                 title: "Test Post 1".to_string(),
                 body: "This is the first test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
             NewPost {
                 post_id: "def456".to_string(),
+                slug: "def456".to_string(),
                 title: "Test Post 2".to_string(),
                 body: "This is the second test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
         ];
 
@@ -472,25 +1074,37 @@ mod blog_controller_spec {
         let posts_to_insert = vec![
             NewPost {
                 post_id: "fake_id_1".to_string(),
+                slug: "fake_id_1".to_string(),
                 title: "Test Post 1".to_string(),
                 body: "This is the first test post.".to_string(),
 
                 created_at: post_1_datetime, // Current time in ISO 8601 format
                 updated_at: post_1_datetime, // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
             NewPost {
                 post_id: "fake_id_2".to_string(),
+                slug: "fake_id_2".to_string(),
                 title: "Test Post 2".to_string(),
                 body: "This is the second test post.".to_string(),
                 created_at: post_2_datetime, // Current time in ISO 8601 format
                 updated_at: post_2_datetime, // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
             NewPost {
                 post_id: "fake_id_3".to_string(),
+                slug: "fake_id_3".to_string(),
                 title: "Test Post 3".to_string(),
                 body: "This is the third test post.".to_string(),
                 created_at: post_3_datetime, // Current time in ISO 8601 format
                 updated_at: post_3_datetime, // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
         ];
 
@@ -506,8 +1120,10 @@ mod blog_controller_spec {
         let body: Bytes = to_bytes(resp.into_body()).await.unwrap();
         let json_body: Value = serde_json::from_slice(&body).unwrap();
 
-        // Assuming the response is an array of posts
-        let posts = json_body.as_array().expect("Expected an array of posts");
+        // The response is a page with the posts under "items"
+        let posts = json_body["items"].as_array().expect("Expected an array of posts");
+        assert_eq!(json_body["total_count"], 3);
+        assert!(json_body["next_cursor"].is_null());
 
         // Assert the length of the array
         assert_eq!(posts.len(), 3);
@@ -527,6 +1143,95 @@ mod blog_controller_spec {
         assert_eq!(posts[2]["body"], "This is the first test post.");
     }
 
+    async fn test_get_all_posts_paginated() {
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(get_all_posts),
+        )
+            .await;
+
+        let posts_to_insert = vec![
+            NewPost {
+                post_id: "page_id_1".to_string(),
+                slug: "page_id_1".to_string(),
+                title: "Charlie".to_string(),
+                body: "Third alphabetically.".to_string(),
+                created_at: NaiveDateTime::parse_from_str("2024-07-01 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                updated_at: NaiveDateTime::parse_from_str("2024-07-01 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+            NewPost {
+                post_id: "page_id_2".to_string(),
+                slug: "page_id_2".to_string(),
+                title: "Alpha".to_string(),
+                body: "First alphabetically.".to_string(),
+                created_at: NaiveDateTime::parse_from_str("2024-08-01 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                updated_at: NaiveDateTime::parse_from_str("2024-08-01 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+            NewPost {
+                post_id: "page_id_3".to_string(),
+                slug: "page_id_3".to_string(),
+                title: "Bravo".to_string(),
+                body: "Second alphabetically.".to_string(),
+                created_at: NaiveDateTime::parse_from_str("2024-09-01 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                updated_at: NaiveDateTime::parse_from_str("2024-09-01 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+        ];
+
+        let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+
+        // First page sorted by title
+        let req = test::TestRequest::get()
+            .uri("/blog/post/get/all?limit=2&sort=title&order=asc")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let json_body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(json_body["total_count"], 3);
+        assert_eq!(json_body["items"][0]["title"], "Alpha");
+        assert_eq!(json_body["items"][1]["title"], "Bravo");
+        let cursor = json_body["next_cursor"].as_str().expect("Expected a next_cursor").to_string();
+
+        // Second page continues after the cursor
+        let req = test::TestRequest::get()
+            .uri(&format!("/blog/post/get/all?limit=2&sort=title&order=asc&after={}", cursor))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let json_body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        let items = json_body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["title"], "Charlie");
+        assert!(json_body["next_cursor"].is_null());
+
+        // Date range filter on created_at
+        let req = test::TestRequest::get()
+            .uri("/blog/post/get/all?from=2024-07-15&to=2024-08-15")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let json_body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(json_body["total_count"], 1);
+        assert_eq!(json_body["items"][0]["post_id"], "page_id_2");
+
+        // A cursor issued for another sort field is rejected
+        let req = test::TestRequest::get()
+            .uri(&format!("/blog/post/get/all?sort=created_at&after={}", cursor))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
     async fn test_update_post() {
         let pool = web::Data::new(establish_connection());
 
@@ -544,18 +1249,26 @@ mod blog_controller_spec {
         let posts_to_insert = vec![
             NewPost {
                 post_id: "abc888".to_string(),
+                slug: "abc888".to_string(),
                 title: "Test Post 1".to_string(),
                 body: "This is the first test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
         ];
 
         let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+        let id_before: i32 = posts::table.filter(posts::post_id.eq("abc888")).select(posts::id).first(&mut conn).unwrap();
 
+        // The id and post_id in the body are ignored, neither can be changed
         let payload = json!({
-            "id": 1,
-            "post_id": "abc888",
+            "id": 9999,
+            "post_id": "abc999",
             "title": "Updated Title",
             "body": "Updated body content.",
             "created_at": "2023-08-29T14:00:00Z", // Example timestamp
@@ -579,6 +1292,1531 @@ mod blog_controller_spec {
         });
 
         assert_eq!(json_body, expected_message);
+
+        let (id_after, title): (i32, String) =
+            posts::table.filter(posts::post_id.eq("abc888")).select((posts::id, posts::title)).first(&mut conn).unwrap();
+        assert_eq!(id_after, id_before);
+        assert_eq!(title, "Updated Title");
+    }
+
+    async fn test_post_revisions() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(get_by_post_id)
+                    .service(update_post)
+                    .service(get_post_revisions)
+                    .service(diff_post_revisions)
+                    .service(get_post_revision)
+                    .service(restore_post_revision),
+            )
+                .await;
+
+        let posts_to_insert = vec![
+            NewPost {
+                post_id: "rev001".to_string(),
+                slug: "rev001".to_string(),
+                title: "Original Title".to_string(),
+                body: "line one\nline two\n".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+        ];
+
+        let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+
+        for (title, body) in [("Second Title", "line one\nline 2\n"), ("Third Title", "line one\nline 2\nline three\n")] {
+            let payload = json!({
+                "id": 1,
+                "post_id": "rev001",
+                "title": title,
+                "body": body,
+                "created_at": "2023-08-29T14:00:00Z",
+                "updated_at": "2023-08-29T14:00:01Z"
+            });
+            let put_req = test::TestRequest::put()
+                .uri("/blog/posts/update/rev001")
+                .set_json(&payload)
+                .to_request();
+            let put_resp = test::call_service(&app, put_req).await;
+            assert!(put_resp.status().is_success());
+        }
+
+        // The pre-existing text is kept as revision 1, then one revision per update
+        let req = test::TestRequest::get().uri("/blog/post/rev001/revisions").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let revisions = json_body.as_array().expect("Expected an array of revisions");
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(revisions[0]["revision_number"], 3);
+        assert_eq!(revisions[2]["title"], "Original Title");
+
+        let req = test::TestRequest::get().uri("/blog/post/rev001/revisions/1").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["body"], "line one\nline two\n");
+
+        let req = test::TestRequest::get().uri("/blog/post/rev001/revisions/diff?from=1&to=3").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["title"]["from"], "Original Title");
+        assert_eq!(json_body["title"]["to"], "Third Title");
+        let tags: Vec<&str> = json_body["lines"].as_array().unwrap().iter().map(|line| line["tag"].as_str().unwrap()).collect();
+        assert_eq!(tags, vec!["equal", "delete", "insert", "insert"]);
+
+        let req = test::TestRequest::get().uri("/blog/post/rev001/revisions/9").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Restoring writes the old content back and records it as a new revision
+        let req = test::TestRequest::post().uri("/blog/post/rev001/revisions/1/restore").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["revision_number"], 4);
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/rev001").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["title"], "Original Title");
+        assert_eq!(json_body["body"], "line one\nline two\n");
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+        diesel::delete(post_revisions::table.filter(post_revisions::post_id.eq("rev001")))
+            .execute(&mut conn)
+            .expect("Failed to delete test revisions");
+    }
+
+    async fn test_post_status_visibility() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(get_by_post_id)
+                    .service(get_all_posts)
+                    .service(create_post),
+            )
+                .await;
+
+        let an_hour_ago = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+
+        let posts_to_insert = vec![
+            NewPost {
+                post_id: "status_draft".to_string(),
+                slug: "status_draft".to_string(),
+                title: "Draft Post".to_string(),
+                body: "Not ready yet.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Draft,
+                publish_at: None,
+                author_id: None,
+            },
+            NewPost {
+                post_id: "status_scheduled".to_string(),
+                slug: "status_scheduled".to_string(),
+                title: "Scheduled Post".to_string(),
+                body: "Goes live on its own.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Scheduled,
+                publish_at: Some(an_hour_ago),
+                author_id: None,
+            },
+        ];
+
+        let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+
+        for post_id in ["status_draft", "status_scheduled"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/blog/post/retrieve/post-id/{}", post_id))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        }
+
+        let req = test::TestRequest::get().uri("/blog/post/get/all").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let listed_ids: Vec<&str> = json_body["items"].as_array().unwrap().iter().map(|post| post["post_id"].as_str().unwrap()).collect();
+        assert!(!listed_ids.contains(&"status_draft"));
+        assert!(!listed_ids.contains(&"status_scheduled"));
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+        let published_at = chrono::Utc::now().naive_utc();
+        assert_eq!(publish_due_posts(&mut conn).expect("Failed to publish due posts"), vec!["status_scheduled".to_string()]);
+
+        // Going live is announced to webhooks like any other update
+        let outbox: Vec<String> =
+            webhook_events::table
+                .filter(webhook_events::event.eq(WebhookEvent::PostUpdated))
+                .filter(webhook_events::payload.like("%\"post_id\":\"status_scheduled\"%"))
+                .filter(webhook_events::created_at.ge(published_at))
+                .select(webhook_events::payload)
+                .load(&mut conn)
+                .expect("Failed to load webhook events");
+        assert_eq!(outbox.len(), 1);
+        assert!(outbox[0].contains("\"status\":\"published\""));
+
+        let req = test::TestRequest::get()
+            .uri("/blog/post/retrieve/post-id/status_scheduled")
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["status"], "published");
+
+        let req = test::TestRequest::get()
+            .uri("/blog/post/retrieve/post-id/status_draft")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Scheduling without a publish time is rejected
+        let payload = json!({
+            "title": "Invalid",
+            "body": "Missing publish_at.",
+            "status": "scheduled"
+        });
+        let req = test::TestRequest::post()
+            .uri("/blog/post/create")
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn test_post_tags() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(get_by_post_id)
+                    .service(get_all_posts)
+                    .service(create_post)
+                    .service(update_post)
+                    .service(delete_post)
+                    .service(get_tag_cloud),
+            )
+                .await;
+
+        let drafts = [
+            ("tagged_one", vec!["Spec Zebra", "Spec Yak"], "published"),
+            ("tagged_two", vec!["spec zebra"], "published"),
+            ("tagged_draft", vec!["Spec Zebra"], "draft"),
+        ];
+        let mut post_ids = Vec::new();
+        for (name, tags, status) in &drafts {
+            let payload = json!({
+                "title": format!("Tagged {}", name),
+                "body": "Tagged body.",
+                "status": status,
+                "tags": tags
+            });
+            let req = test::TestRequest::post()
+                .uri("/blog/post/create")
+                .set_json(&payload)
+                .to_request();
+            let json_body: Value = test::call_and_read_body_json(&app, req).await;
+            post_ids.push(json_body["post_id"].as_str().unwrap().to_string());
+        }
+        let (tagged_one, tagged_two) = (post_ids[0].as_str(), post_ids[1].as_str());
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/blog/post/retrieve/post-id/{}", tagged_one))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            json_body["tags"],
+            json!([{ "name": "Spec Yak", "slug": "spec-yak" }, { "name": "Spec Zebra", "slug": "spec-zebra" }])
+        );
+
+        // Drafts stay out of the filtered list for the public
+        let req = test::TestRequest::get().uri("/blog/post/get/all?tag=spec-zebra&order=asc").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let listed_ids: Vec<&str> = json_body["items"].as_array().unwrap().iter().map(|post| post["post_id"].as_str().unwrap()).collect();
+        assert_eq!(listed_ids, vec![tagged_one, tagged_two]);
+        assert_eq!(json_body["total_count"], 2);
+
+        let req = test::TestRequest::get().uri("/blog/tag/cloud").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let cloud = json_body.as_array().unwrap();
+        let count_for = |slug: &str| cloud.iter().find(|tag| tag["slug"] == slug).map(|tag| tag["post_count"].clone());
+        assert_eq!(count_for("spec-zebra"), Some(json!(2)));
+        assert_eq!(count_for("spec-yak"), Some(json!(1)));
+
+        // Sending tags on update replaces them, a bad tag name is rejected
+        let mut payload = json!({
+            "title": "Tagged tagged_one",
+            "body": "Tagged body.",
+            "tags": ["Spec Yak"]
+        });
+        let req = test::TestRequest::put()
+            .uri(&format!("/blog/posts/update/{}", tagged_one))
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        payload["tags"] = json!(["--"]);
+        let req = test::TestRequest::put()
+            .uri(&format!("/blog/posts/update/{}", tagged_one))
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get().uri("/blog/post/get/all?tag=spec-zebra").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["total_count"], 1);
+        assert_eq!(json_body["items"][0]["post_id"], tagged_two);
+
+        for post_id in &post_ids {
+            let req = test::TestRequest::delete()
+                .uri(&format!("/blog/post/single/{}", post_id))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+        for post_id in &post_ids {
+            purge_trash(&mut conn, TrashKind::Posts, Some(post_id), None).expect("Failed to purge test post");
+        }
+        diesel::delete(tags::table.filter(tags::slug.like("spec-%")))
+            .execute(&mut conn)
+            .expect("Failed to delete test tags");
+    }
+
+    async fn test_post_series() {
+        use crate::controllers::series_controller::{add_post_to_series, create_series, delete_series, get_series, remove_post_from_series};
+
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(get_by_post_id)
+                .service(create_series)
+                .service(get_series)
+                .service(delete_series)
+                .service(add_post_to_series)
+                .service(remove_post_from_series),
+        )
+            .await;
+
+        let now = chrono::Utc::now().naive_utc();
+        let post = |post_id: &str, status: PostStatus| NewPost {
+            post_id: post_id.to_string(),
+            slug: post_id.to_string(),
+            title: post_id.to_string(),
+            body: "One part of the tutorial.".to_string(),
+            created_at: now,
+            updated_at: now,
+            status,
+            publish_at: None,
+            author_id: None,
+        };
+        let _guard = TestGuard::new(pool.clone(), vec![
+            post("series_part_a", PostStatus::Published),
+            post("series_part_b", PostStatus::Published),
+            post("series_part_c", PostStatus::Published),
+            post("series_part_draft", PostStatus::Draft),
+        ]);
+
+        let req = test::TestRequest::post()
+            .uri("/blog/series/create")
+            .set_json(json!({ "title": "Series Spec Tutorial", "description": "In three parts" }))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["slug"], "series-spec-tutorial");
+
+        // a and b are appended, c goes in front of them and the draft in front of all three
+        for (post_id, placement, position) in [
+            ("series_part_a", json!({}), 1),
+            ("series_part_b", json!({}), 2),
+            ("series_part_c", json!({ "position": 1 }), 1),
+            ("series_part_draft", json!({ "position": 1 }), 1),
+        ] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/blog/series/series-spec-tutorial/posts/{}", post_id))
+                .set_json(placement)
+                .to_request();
+            let json_body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(json_body["position"], position);
+        }
+
+        let req = test::TestRequest::put()
+            .uri("/blog/series/series-spec-tutorial/posts/no_such_post")
+            .set_json(json!({}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+        // Anonymous readers only get the published parts, in order
+        let req = test::TestRequest::get().uri("/blog/series/retrieve/series-spec-tutorial").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["description"], "In three parts");
+        let post_ids: Vec<&str> = json_body["posts"].as_array().unwrap().iter().map(|post| post["post_id"].as_str().unwrap()).collect();
+        assert_eq!(post_ids, vec!["series_part_c", "series_part_a", "series_part_b"]);
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/series_part_a").to_request();
+        let resp = test::call_service(&app, req).await;
+        let part_a_etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        let json_body: Value = test::read_body_json(resp).await;
+        // a is stored third, after the draft, but readers see it as the second of three parts
+        assert_eq!(json_body["series"]["slug"], "series-spec-tutorial");
+        assert_eq!(json_body["series"]["position"], 2);
+        assert_eq!(json_body["series"]["post_count"], 3);
+        assert_eq!(json_body["series"]["previous"]["post_id"], "series_part_c");
+        assert_eq!(json_body["series"]["previous"]["position"], 1);
+        assert_eq!(json_body["series"]["next"]["post_id"], "series_part_b");
+        assert_eq!(json_body["series"]["next"]["position"], 3);
+
+        // The last published part has no next, and the first has no previous, the draft in front of it is not linked
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/series_part_b").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body["series"]["next"].is_null());
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/series_part_c").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["series"]["position"], 1);
+        assert!(json_body["series"]["previous"].is_null());
+
+        // Taking c out closes the gap it leaves
+        let req = test::TestRequest::delete().uri("/blog/series/series-spec-tutorial/posts/series_part_c").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::delete().uri("/blog/series/series-spec-tutorial/posts/series_part_c").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+        // a's updated_at stays put, but its navigation changed, so a reader revalidating gets the new one
+        let req =
+            test::TestRequest::get()
+                .uri("/blog/post/retrieve/post-id/series_part_a")
+                .insert_header(("If-None-Match", part_a_etag.as_str()))
+                .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json_body: Value = test::read_body_json(resp).await;
+        assert_eq!(json_body["series"]["position"], 1);
+        assert!(json_body["series"]["previous"].is_null());
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/series_part_c").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body["series"].is_null());
+
+        // Deleting the series leaves its posts without one
+        let req = test::TestRequest::delete().uri("/blog/series/single/series-spec-tutorial").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/series_part_b").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body["series"].is_null());
+        assert!(json_body["series_position"].is_null());
+    }
+
+    async fn test_related_posts() {
+        use crate::controllers::related_controller::get_related_posts;
+        use crate::controllers::tag_controller::{normalize_tag_names, set_post_tags};
+
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(App::new().app_data(pool.clone()).service(get_related_posts)).await;
+
+        let now = chrono::Utc::now().naive_utc();
+        let post = |post_id: &str, title: &str, body: &str, status: PostStatus| NewPost {
+            post_id: post_id.to_string(),
+            slug: post_id.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            created_at: now,
+            updated_at: now,
+            status,
+            publish_at: None,
+            author_id: None,
+        };
+        let borrowing = "The borrow checker enforces ownership and borrowing rules.";
+        let gardening = "Notes on growing tomatoes in a small garden.";
+        let _guard = TestGuard::new(pool.clone(), vec![
+            post("related_source", "Understanding ownership", borrowing, PostStatus::Published),
+            post("related_two_tags", "Tomato season", gardening, PostStatus::Published),
+            post("related_one_tag", "Tomato harvest", gardening, PostStatus::Published),
+            post("related_text", "Borrowing explained", borrowing, PostStatus::Published),
+            post("related_unrelated", "Sourdough", "Baking bread with a starter.", PostStatus::Published),
+            post("related_draft", "Ownership draft", borrowing, PostStatus::Draft),
+            post("related_trashed", "Ownership trashed", borrowing, PostStatus::Published),
+        ]);
+
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+        let both = normalize_tag_names(&["Spec Ferris".to_string(), "Spec Lifetimes".to_string()]).unwrap();
+        let one = normalize_tag_names(&["Spec Ferris".to_string()]).unwrap();
+        for (post_id, labels) in [
+            ("related_source", &both),
+            ("related_two_tags", &both),
+            ("related_one_tag", &one),
+            ("related_draft", &both),
+            ("related_trashed", &both),
+        ] {
+            set_post_tags(&mut conn, post_id, labels).expect("Failed to tag test post");
+        }
+        diesel::update(posts::table.filter(posts::post_id.eq("related_trashed")))
+            .set(posts::deleted_at.eq(Some(now)))
+            .execute(&mut conn)
+            .expect("Failed to trash test post");
+
+        // Shared tags come first, then shared wording. Drafts, the trash and the post itself are left out.
+        let req = test::TestRequest::get().uri("/blog/post/related_source/related").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let related = json_body.as_array().unwrap();
+        let post_ids: Vec<&str> = related.iter().map(|post| post["post_id"].as_str().unwrap()).collect();
+        assert_eq!(post_ids, vec!["related_two_tags", "related_one_tag", "related_text"]);
+        assert_eq!(related[0]["shared_tags"], 2);
+        assert_eq!(related[2]["shared_tags"], 0);
+        assert!(related[2]["score"].as_f64().unwrap() > 0.0);
+        assert!(related[0]["score"].as_f64().unwrap() > related[1]["score"].as_f64().unwrap());
+
+        let req = test::TestRequest::get().uri("/blog/post/related_source/related?limit=1").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body.as_array().unwrap().len(), 1);
+
+        let req = test::TestRequest::get().uri("/blog/post/related_source/related?limit=0").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        // Readers can't get related posts for a draft or a trashed post
+        for post_id in ["related_draft", "related_trashed", "no_such_post"] {
+            let req = test::TestRequest::get().uri(&format!("/blog/post/{}/related", post_id)).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    async fn test_post_reactions() {
+        use crate::controllers::reaction_controller::{add_post_reaction, add_reaction, get_post_reactions, remove_post_reaction, Reader};
+        use crate::models::Reaction::Reaction;
+
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(get_by_post_id)
+                .service(get_post_reactions)
+                .service(add_post_reaction)
+                .service(remove_post_reaction),
+        )
+            .await;
+
+        let now = chrono::Utc::now().naive_utc();
+        let post = |post_id: &str, status: PostStatus| NewPost {
+            post_id: post_id.to_string(),
+            slug: post_id.to_string(),
+            title: post_id.to_string(),
+            body: "Worth reacting to.".to_string(),
+            created_at: now,
+            updated_at: now,
+            status,
+            publish_at: None,
+            author_id: None,
+        };
+        let _guard = TestGuard::new(pool.clone(), vec![
+            post("reacted_post", PostStatus::Published),
+            post("reacted_draft", PostStatus::Draft),
+        ]);
+
+        // A first reaction hands the anonymous reader a token to be recognised by
+        let req = test::TestRequest::put().uri("/blog/post/reacted_post/reactions/like").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let token = resp.response().cookies().find(|cookie| cookie.name() == "reader_token").unwrap().value().to_string();
+        let json_body: Value = test::read_body_json(resp).await;
+        assert_eq!(json_body["reactions"], json!({ "like": 1, "love": 0, "insightful": 0 }));
+        assert_eq!(json_body["mine"], json!(["like"]));
+
+        // Reacting again with the same token doesn't count twice
+        let reader_cookie = actix_web::cookie::Cookie::new("reader_token", token.clone());
+        let req = test::TestRequest::put().uri("/blog/post/reacted_post/reactions/like").cookie(reader_cookie.clone()).to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["reactions"]["like"], 1);
+
+        let req = test::TestRequest::put().uri("/blog/post/reacted_post/reactions/insightful").cookie(reader_cookie.clone()).to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::put().uri("/blog/post/reacted_post/reactions/like").to_request();
+        test::call_service(&app, req).await;
+
+        // Totals come with the post itself, another reader has reacted nothing yet
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/reacted_post").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["reactions"], json!({ "like": 2, "love": 0, "insightful": 1 }));
+        assert!(json_body.get("like_count").is_none());
+
+        // A reaction leaves updated_at alone, yet a reader revalidating the post gets the new totals
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/reacted_post").to_request();
+        let etag = test::call_service(&app, req).await.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        let req = test::TestRequest::put().uri("/blog/post/reacted_post/reactions/love").to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/reacted_post").insert_header(("If-None-Match", etag.as_str())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json_body: Value = test::read_body_json(resp).await;
+        assert_eq!(json_body["reactions"]["love"], 1);
+
+        let req = test::TestRequest::get().uri("/blog/post/reacted_post/reactions").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "private, no-store");
+        let json_body: Value = test::read_body_json(resp).await;
+        assert_eq!(json_body["mine"], json!([]));
+
+        let req = test::TestRequest::delete().uri("/blog/post/reacted_post/reactions/like").cookie(reader_cookie.clone()).to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["reactions"]["like"], 1);
+        assert_eq!(json_body["mine"], json!(["insightful"]));
+
+        // Taking back a reaction that isn't there changes nothing
+        let req = test::TestRequest::delete().uri("/blog/post/reacted_post/reactions/like").cookie(reader_cookie.clone()).to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["reactions"]["like"], 1);
+
+        // Reactions on a trashed post are kept along with its counts, ready for a restore
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+        diesel::update(posts::table.filter(posts::post_id.eq("reacted_post")))
+            .set(posts::deleted_at.eq(Some(chrono::Utc::now().naive_utc())))
+            .execute(&mut conn)
+            .expect("Failed to trash test post");
+        let req = test::TestRequest::delete().uri("/blog/post/reacted_post/reactions/insightful").cookie(reader_cookie).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+        let insightful =
+            post_reactions::table
+                .filter(post_reactions::post_id.eq("reacted_post"))
+                .filter(post_reactions::reaction.eq(Reaction::Insightful))
+                .count()
+                .get_result::<i64>(&mut conn)
+                .unwrap();
+        let insightful_count =
+            posts::table.filter(posts::post_id.eq("reacted_post")).select(posts::insightful_count).first::<i32>(&mut conn).unwrap();
+        assert_eq!((insightful, insightful_count), (1, 1));
+        // Trashed after the handler checked it, the post still doesn't take the reaction
+        let reader = Reader::Token(token.clone());
+        assert!(!add_reaction(&mut conn, "reacted_post", Reaction::Love, &reader).unwrap());
+        let love =
+            post_reactions::table
+                .filter(post_reactions::post_id.eq("reacted_post"))
+                .filter(post_reactions::reaction.eq(Reaction::Love))
+                .filter(post_reactions::reader_token.eq(&token))
+                .count()
+                .get_result::<i64>(&mut conn)
+                .unwrap();
+        assert_eq!(love, 0);
+
+        let req = test::TestRequest::put().uri("/blog/post/reacted_post/reactions/dislike").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        for uri in ["/blog/post/reacted_draft/reactions/like", "/blog/post/no_such_post/reactions/like"] {
+            let req = test::TestRequest::put().uri(uri).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+        }
+
+        diesel::delete(post_reactions::table.filter(post_reactions::post_id.eq("reacted_post")))
+            .execute(&mut conn)
+            .expect("Failed to delete test reactions");
+    }
+
+    async fn test_post_translations() {
+        use crate::controllers::translation_controller::{delete_post_translation, get_post_locales, put_post_translation};
+
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(get_by_post_id)
+                .service(get_all_posts)
+                .service(get_by_slug)
+                .service(get_post_locales)
+                .service(put_post_translation)
+                .service(delete_post_translation),
+        )
+            .await;
+
+        let now = chrono::Utc::now().naive_utc();
+        let _guard = TestGuard::new(pool.clone(), vec![NewPost {
+            post_id: "translated_post".to_string(),
+            slug: "translated-post".to_string(),
+            title: "Good morning".to_string(),
+            body: "Hello world.".to_string(),
+            created_at: now,
+            updated_at: now,
+            status: PostStatus::Published,
+            publish_at: None,
+            author_id: None,
+        }]);
+
+        for (locale, title, body) in [("de", "Guten Morgen", "Hallo Welt."), ("pt_BR", "Bom dia", "Olá mundo.")] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/blog/post/translated_post/translations/{}", locale))
+                .set_json(json!({ "title": title, "body": body }))
+                .to_request();
+            let json_body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(json_body["title"], title);
+        }
+
+        // Saving a locale again replaces its translation
+        let req = test::TestRequest::put()
+            .uri("/blog/post/translated_post/translations/DE")
+            .set_json(json!({ "title": "Guten Tag", "body": "Hallo Welt." }))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["locale"], "de");
+
+        let req = test::TestRequest::get().uri("/blog/post/translated_post/locales").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["locales"], json!(["de", "pt-br"]));
+
+        // ?lang wins over Accept-Language, a language without a translation falls back to the original,
+        // and readers who prefer the language the post is written in get the original too
+        for (uri, accept_language, title, locale) in [
+            ("/blog/post/retrieve/post-id/translated_post", None, "Good morning", Value::Null),
+            ("/blog/post/retrieve/post-id/translated_post", Some("en, de;q=0.8"), "Good morning", Value::Null),
+            ("/blog/post/retrieve/post-id/translated_post", Some("en-US, pt-BR;q=0.8"), "Good morning", Value::Null),
+            ("/blog/post/retrieve/post-id/translated_post?lang=de", Some("pt-BR"), "Guten Tag", json!("de")),
+            ("/blog/post/retrieve/post-id/translated_post", Some("fr, pt;q=0.8"), "Bom dia", json!("pt-br")),
+            ("/blog/post/retrieve/post-id/translated_post?lang=fr", None, "Good morning", Value::Null),
+            ("/blog/post/translated-post", Some("de-AT"), "Guten Tag", json!("de")),
+        ] {
+            let mut req = test::TestRequest::get().uri(uri);
+            if let Some(accept_language) = accept_language {
+                req = req.insert_header(("Accept-Language", accept_language));
+            }
+            let json_body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+            assert_eq!(json_body["title"], title, "{} {:?}", uri, accept_language);
+            assert_eq!(json_body["locale"], locale);
+        }
+
+        // Caches are told the body depends on Accept-Language
+        for uri in ["/blog/post/retrieve/post-id/translated_post", "/blog/post/translated-post", "/blog/post/get/all"] {
+            let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.headers().get("vary").unwrap(), "Accept-Language", "{}", uri);
+        }
+
+        // The translation is tagged apart from the original
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/translated_post").to_request();
+        let original_etag = test::call_service(&app, req).await.headers().get("etag").unwrap().clone();
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/translated_post?lang=de").to_request();
+        assert_ne!(test::call_service(&app, req).await.headers().get("etag").unwrap(), &original_etag);
+
+        let req = test::TestRequest::get().uri("/blog/post/get/all?lang=de").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let translated = json_body["items"].as_array().unwrap().iter().find(|post| post["post_id"] == "translated_post").unwrap();
+        assert_eq!(translated["body"], "Hallo Welt.");
+
+        let req = test::TestRequest::get().uri("/blog/post/retrieve/post-id/translated_post?lang=english").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::put()
+            .uri("/blog/post/no_such_post/translations/de")
+            .set_json(json!({ "title": "Nichts", "body": "" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+        for (locale, status) in [("pt-br", StatusCode::OK), ("de", StatusCode::OK), ("de", StatusCode::NOT_FOUND)] {
+            let req = test::TestRequest::delete().uri(&format!("/blog/post/translated_post/translations/{}", locale)).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    async fn test_content_stats() {
+        use crate::controllers::stats_controller::{get_archive, get_content_stats};
+        use crate::controllers::tag_controller::{normalize_tag_names, set_post_tags};
+
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(App::new().app_data(pool.clone()).service(get_content_stats).service(get_archive)).await;
+
+        let at = |raw: &str| NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S").unwrap();
+        let post = |post_id: &str, created_at: &str, publish_at: Option<&str>, status: PostStatus| NewPost {
+            post_id: post_id.to_string(),
+            slug: post_id.to_string(),
+            title: format!("Title of {}", post_id),
+            body: "...".to_string(),
+            created_at: at(created_at),
+            updated_at: at(created_at),
+            status,
+            publish_at: publish_at.map(at),
+            author_id: None,
+        };
+        let _guard = TestGuard::new(pool.clone(), vec![
+            post("stats_march_early", "2019-03-02 10:00:00", None, PostStatus::Published),
+            post("stats_march_late", "2019-03-28 10:00:00", None, PostStatus::Published),
+            // Counted in the month it was published, not the one it was written in
+            post("stats_scheduled", "2019-02-20 10:00:00", Some("2019-04-01 08:00:00"), PostStatus::Published),
+            post("stats_draft", "2019-03-15 10:00:00", None, PostStatus::Draft),
+            post("stats_trashed", "2019-03-16 10:00:00", None, PostStatus::Published),
+        ]);
+
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+        let labels = normalize_tag_names(&["Spec Stats".to_string()]).unwrap();
+        for post_id in ["stats_march_early", "stats_march_late", "stats_draft"] {
+            set_post_tags(&mut conn, post_id, &labels).expect("Failed to tag test post");
+        }
+        diesel::update(posts::table.filter(posts::post_id.eq("stats_trashed")))
+            .set(posts::deleted_at.eq(Some(chrono::Utc::now().naive_utc())))
+            .execute(&mut conn)
+            .expect("Failed to trash test post");
+
+        let req = test::TestRequest::get().uri("/blog/stats").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let stats: Value = test::read_body_json(resp).await;
+        assert_eq!(stats["totals"]["posts"], 3);
+        for total in ["skills", "worklog", "users"] {
+            assert!(stats["totals"][total].is_i64());
+        }
+        let months: Vec<(i64, i64, i64)> =
+            stats["posts_per_month"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|month| (month["year"].as_i64().unwrap(), month["month"].as_i64().unwrap(), month["posts"].as_i64().unwrap()))
+                .collect();
+        assert_eq!(months, vec![(2019, 4, 1), (2019, 3, 2)]);
+        let spec_tag = stats["posts_per_tag"].as_array().unwrap().iter().find(|tag| tag["slug"] == "spec-stats").unwrap();
+        assert_eq!(spec_tag["post_count"], 2);
+
+        let req = test::TestRequest::get().uri("/blog/archive").to_request();
+        let archive: Value = test::call_and_read_body_json(&app, req).await;
+        let archive = archive.as_array().unwrap();
+        assert_eq!(archive.len(), 2);
+        assert_eq!((archive[0]["year"].as_i64(), archive[0]["month"].as_i64()), (Some(2019), Some(4)));
+        assert_eq!(archive[0]["posts"][0]["post_id"], "stats_scheduled");
+        assert_eq!(archive[0]["posts"][0]["published_at"], "2019-04-01T08:00:00");
+        let march: Vec<&str> = archive[1]["posts"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap()).collect();
+        assert_eq!(march, vec!["Title of stats_march_late", "Title of stats_march_early"]);
+    }
+
+    async fn test_post_comments() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(create_comment)
+                    .service(get_post_comments)
+                    .service(get_moderation_queue),
+            )
+                .await;
+
+        let posts_to_insert = vec![
+            NewPost {
+                post_id: "commented_post".to_string(),
+                slug: "commented_post".to_string(),
+                title: "Commented Post".to_string(),
+                body: "Tell me what you think.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+            NewPost {
+                post_id: "commented_draft".to_string(),
+                slug: "commented_draft".to_string(),
+                title: "Commented Draft".to_string(),
+                body: "Not open for comments.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Draft,
+                publish_at: None,
+                author_id: None,
+            },
+        ];
+
+        let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+
+        let req = test::TestRequest::post()
+            .uri("/blog/post/commented_post/comments")
+            .set_json(json!({ "body": "First!", "author_name": "Guest" }))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["status"], "pending");
+        let first_id = json_body["id"].as_i64().unwrap() as i32;
+
+        // Pending comments are not public yet
+        let req = test::TestRequest::get().uri("/blog/post/commented_post/comments").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body, json!([]));
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+        moderate_comment(&mut conn, first_id, CommentStatus::Approved, "moderator").expect("Failed to moderate comment");
+
+        let mut reply_ids = Vec::new();
+        for body in ["Good point", "Buy cheap watches"] {
+            let req = test::TestRequest::post()
+                .uri("/blog/post/commented_post/comments")
+                .set_json(json!({ "body": body, "author_name": "Replier", "parent_id": first_id }))
+                .to_request();
+            let json_body: Value = test::call_and_read_body_json(&app, req).await;
+            reply_ids.push(json_body["id"].as_i64().unwrap() as i32);
+        }
+        moderate_comment(&mut conn, reply_ids[0], CommentStatus::Approved, "moderator").expect("Failed to moderate comment");
+        moderate_comment(&mut conn, reply_ids[1], CommentStatus::Spam, "moderator").expect("Failed to moderate comment");
+
+        let req = test::TestRequest::get().uri("/blog/post/commented_post/comments").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body.as_array().unwrap().len(), 1);
+        assert_eq!(json_body[0]["body"], "First!");
+        assert_eq!(json_body[0]["author_name"], "Guest");
+        let replies = json_body[0]["replies"].as_array().unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0]["body"], "Good point");
+
+        // Guests need a name, drafts take no comments and replies have to stay on the same post
+        let bad_requests = [
+            ("/blog/post/commented_post/comments", json!({ "body": "Anonymous" }), StatusCode::BAD_REQUEST),
+            ("/blog/post/commented_draft/comments", json!({ "body": "Hi", "author_name": "Guest" }), StatusCode::NOT_FOUND),
+            ("/blog/post/commented_post/comments", json!({ "body": "Hi", "author_name": "Guest", "parent_id": -1 }), StatusCode::BAD_REQUEST),
+        ];
+        for (uri, payload, expected_status) in bad_requests {
+            let req = test::TestRequest::post().uri(uri).set_json(&payload).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), expected_status);
+        }
+
+        let req = test::TestRequest::get().uri("/blog/comments/moderation").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        diesel::delete(comments::table.filter(comments::post_id.eq("commented_post")))
+            .execute(&mut conn)
+            .expect("Failed to delete test comments");
+    }
+
+    async fn test_get_post_rendered() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(get_by_post_id)
+                    .service(get_all_posts),
+            )
+                .await;
+
+        let posts_to_insert = vec![
+            NewPost {
+                post_id: "rendered_post".to_string(),
+                slug: "rendered_post".to_string(),
+                title: "Rendered Post".to_string(),
+                body: "# Heading\n\nSome *emphasis* <script>alert(1)</script>".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+        ];
+
+        let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+
+        // Raw Markdown only unless rendering is asked for
+        let req = test::TestRequest::get()
+            .uri("/blog/post/retrieve/post-id/rendered_post")
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body.get("body_html").is_none());
+
+        let req = test::TestRequest::get()
+            .uri("/blog/post/retrieve/post-id/rendered_post?render=true")
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        let body_html = json_body["body_html"].as_str().unwrap();
+        assert!(body_html.contains("<h1 id=\"heading\">Heading</h1>"));
+        assert!(body_html.contains("<em>emphasis</em>"));
+        assert!(!body_html.contains("<script"));
+        assert_eq!(json_body["toc"], json!([{ "level": 1, "text": "Heading", "anchor": "heading" }]));
+        assert_eq!(json_body["body"], "# Heading\n\nSome *emphasis* <script>alert(1)</script>");
+
+        let req = test::TestRequest::get().uri("/blog/post/get/all?render=true").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body["items"].as_array().unwrap().iter().all(|post| post["body_html"].is_string()));
+    }
+
+    async fn test_post_slugs() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(create_post)
+                    .service(update_post)
+                    .service(get_by_slug),
+            )
+                .await;
+
+        // Filled in with the post_ids the server hands out
+        let mut guard = TestGuard { pool: pool.clone(), post_ids: Vec::new() };
+
+        let create = || {
+            test::TestRequest::post()
+                .uri("/blog/post/create")
+                .set_json(json!({ "title": "Slug Me!", "body": "Slugged." }))
+                .to_request()
+        };
+        let update = |post_id: &Value, title: &str| {
+            test::TestRequest::put()
+                .uri(&format!("/blog/posts/update/{}", post_id.as_str().unwrap()))
+                .set_json(json!({ "title": title, "body": "Slugged." }))
+                .to_request()
+        };
+        let get = |slug: &str| test::TestRequest::get().uri(&format!("/blog/post/{}", slug)).to_request();
+
+        // The same title twice gets a numbered slug the second time
+        let first: Value = test::call_and_read_body_json(&app, create()).await;
+        guard.post_ids.push(first["post_id"].as_str().unwrap().to_string());
+        assert_eq!(first["slug"], "slug-me");
+        let second: Value = test::call_and_read_body_json(&app, create()).await;
+        guard.post_ids.push(second["post_id"].as_str().unwrap().to_string());
+        assert_eq!(second["slug"], "slug-me-2");
+
+        let found: Value = test::call_and_read_body_json(&app, get("slug-me")).await;
+        assert_eq!(found["post_id"], first["post_id"]);
+
+        // After a title change the old slug redirects to the new one
+        assert!(test::call_service(&app, update(&first["post_id"], "Slug Me Again")).await.status().is_success());
+        let resp = test::call_service(&app, get("slug-me")).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers().get("location").unwrap(), "/blog/post/slug-me-again");
+
+        // Old slugs are not handed out again
+        let third: Value = test::call_and_read_body_json(&app, create()).await;
+        guard.post_ids.push(third["post_id"].as_str().unwrap().to_string());
+        assert_eq!(third["slug"], "slug-me-3");
+
+        // Changing the title back reclaims the original slug
+        assert!(test::call_service(&app, update(&first["post_id"], "Slug Me")).await.status().is_success());
+        let found: Value = test::call_and_read_body_json(&app, get("slug-me")).await;
+        assert_eq!(found["post_id"], first["post_id"]);
+        let resp = test::call_service(&app, get("slug-me-again")).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers().get("location").unwrap(), "/blog/post/slug-me");
+
+        let resp = test::call_service(&app, get("no-such-slug")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn test_trash() {
+        let pool = web::Data::new(establish_connection());
+        // Nothing listens on port 1, so each cache invalidation shows up as an error
+        let cache = web::Data::new(ContentCache::new(redis::Client::open("redis://127.0.0.1:1").unwrap(), 60));
+        let sessions = TestSessions(HashMap::from([
+            ("trash_viewer".to_string(), "viewer".to_string()),
+            ("trash_editor".to_string(), "editor".to_string()),
+        ]));
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .app_data(cache.clone())
+                    .app_data(web::Data::new(sessions))
+                    .service(get_by_post_id)
+                    .service(delete_post)
+                    .service(delete_worklog)
+                    .service(get_trash)
+                    .service(restore_from_trash)
+                    .service(purge_from_trash)
+                    .service(empty_trash),
+            )
+                .await;
+
+        // Every trash endpoint needs a logged in admin or editor
+        let trash_endpoints = || [
+            test::TestRequest::get().uri("/trash"),
+            test::TestRequest::post().uri("/trash/posts/trash_post_1/restore"),
+            test::TestRequest::delete().uri("/trash/posts/trash_post_1"),
+            test::TestRequest::delete().uri("/trash"),
+        ];
+        for req in trash_endpoints() {
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+        for req in trash_endpoints() {
+            let resp = test::call_service(&app, req.cookie(Cookie::new("session_id", "trash_viewer")).to_request()).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        }
+        let as_editor = |req: test::TestRequest| req.cookie(Cookie::new("session_id", "trash_editor")).to_request();
+
+        let trash_post = |post_id: &str| NewPost {
+            post_id: post_id.to_string(),
+            slug: post_id.to_string(),
+            title: "Trash Me".to_string(),
+            body: "Soon gone.".to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+            status: PostStatus::Published,
+            publish_at: None,
+            author_id: None,
+        };
+        let _guard = TestGuard::new(pool.clone(), vec![trash_post("trash_post_1"), trash_post("trash_post_2"), trash_post("trash_post_3")]);
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+        diesel::insert_into(slug_history::table)
+            .values(&NewSlugHistory {
+                post_id: "trash_post_1".to_string(),
+                slug: "trash-me-before".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+            })
+            .execute(&mut conn)
+            .expect("Failed to insert test slug history");
+
+        // Deleting moves the post to the trash, where normal reads can't see it
+        let resp = test::call_service(&app, test::TestRequest::delete().uri("/blog/post/single/trash_post_1").to_request()).await;
+        assert!(resp.status().is_success());
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/blog/post/retrieve/post-id/trash_post_1").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = test::call_service(&app, as_editor(test::TestRequest::get().uri("/trash"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let trash: Value = test::read_body_json(resp).await;
+        let trashed = trash["posts"].as_array().unwrap().iter().find(|post| post["post_id"] == "trash_post_1").unwrap();
+        assert!(!trashed["deleted_at"].is_null());
+
+        // Restoring brings it back, drops the cached lists, and tells webhooks it is there again
+        let restored_at = chrono::Utc::now().naive_utc();
+        let cache_errors = cache.stats().errors;
+        let resp = test::call_service(&app, as_editor(test::TestRequest::post().uri("/trash/posts/trash_post_1/restore"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(cache.stats().errors > cache_errors);
+        let outboxed =
+            webhook_events::table
+                .filter(webhook_events::event.eq(WebhookEvent::PostCreated))
+                .filter(webhook_events::payload.like("%\"post_id\":\"trash_post_1\"%"))
+                .filter(webhook_events::created_at.ge(restored_at))
+                .count()
+                .get_result::<i64>(&mut conn)
+                .unwrap();
+        assert_eq!(outboxed, 1);
+        // The restore counts as a change, so feeds see a newer Last-Modified
+        let updated_at =
+            posts::table.filter(posts::post_id.eq("trash_post_1")).select(posts::updated_at).first::<NaiveDateTime>(&mut conn).unwrap();
+        assert!(updated_at >= restored_at);
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/blog/post/retrieve/post-id/trash_post_1").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, as_editor(test::TestRequest::post().uri("/trash/posts/trash_post_1/restore"))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // A live post that took over the post_id blocks the restore
+        test::call_service(&app, test::TestRequest::delete().uri("/blog/post/single/trash_post_1").to_request()).await;
+        let mut replacement = trash_post("trash_post_1");
+        replacement.slug = "trash_post_1_replacement".to_string();
+        diesel::insert_into(posts::table).values(&replacement).execute(&mut conn).expect("Failed to insert test post");
+        let resp = test::call_service(&app, as_editor(test::TestRequest::post().uri("/trash/posts/trash_post_1/restore"))).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        // Purging the trashed copy doesn't tell webhooks the live post is gone
+        let purge_events = |conn: &mut PgConnection, purged_at: NaiveDateTime| {
+            webhook_events::table
+                .filter(webhook_events::event.eq(WebhookEvent::PostDeleted))
+                .filter(webhook_events::payload.like("%\"post_id\":\"trash_post_1\"%"))
+                .filter(webhook_events::created_at.ge(purged_at))
+                .select(webhook_events::payload)
+                .load::<String>(conn)
+                .unwrap()
+                .iter()
+                .map(|payload| serde_json::from_str::<Value>(payload).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let purged_at = chrono::Utc::now().naive_utc();
+        let resp = test::call_service(&app, as_editor(test::TestRequest::delete().uri("/trash/posts/trash_post_1"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(purge_events(&mut conn, purged_at).is_empty());
+        let live_posts =
+            posts::table.filter(posts::post_id.eq("trash_post_1")).count().get_result::<i64>(&mut conn).unwrap();
+        assert_eq!(live_posts, 1);
+
+        // Purging removes the post and what hangs off it for good
+        test::call_service(&app, test::TestRequest::delete().uri("/blog/post/single/trash_post_1").to_request()).await;
+        let purged_at = chrono::Utc::now().naive_utc();
+        let resp = test::call_service(&app, as_editor(test::TestRequest::delete().uri("/trash/posts/trash_post_1"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let purge_events = purge_events(&mut conn, purged_at);
+        assert_eq!(purge_events.len(), 1);
+        assert_eq!(purge_events[0]["slug"], "trash_post_1_replacement");
+        let remaining_posts =
+            posts::table.filter(posts::post_id.eq("trash_post_1")).count().get_result::<i64>(&mut conn).unwrap();
+        let remaining_slugs =
+            slug_history::table.filter(slug_history::post_id.eq("trash_post_1")).count().get_result::<i64>(&mut conn).unwrap();
+        assert_eq!((remaining_posts, remaining_slugs), (0, 0));
+        let resp = test::call_service(&app, as_editor(test::TestRequest::delete().uri("/trash/posts/trash_post_1"))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Only what has been in the trash longer than the retention period is purged by the job
+        let now = chrono::Utc::now().naive_utc();
+        diesel::update(posts::table.filter(posts::post_id.eq("trash_post_2")))
+            .set(posts::deleted_at.eq(now - chrono::Duration::days(40)))
+            .execute(&mut conn)
+            .expect("Failed to trash test post");
+        diesel::update(posts::table.filter(posts::post_id.eq("trash_post_3")))
+            .set(posts::deleted_at.eq(now))
+            .execute(&mut conn)
+            .expect("Failed to trash test post");
+        assert_eq!(purge_expired_trash(&mut conn, chrono::Duration::days(30)).unwrap(), 1);
+        let remaining: Vec<String> =
+            posts::table
+                .filter(posts::post_id.like("trash_post_%"))
+                .select(posts::post_id)
+                .load(&mut conn)
+                .unwrap();
+        assert_eq!(remaining, vec!["trash_post_3".to_string()]);
+
+        // Worklog goes through the same trash
+        diesel::insert_into(worklog::table)
+            .values(&NewWorklog {
+                worklog_id: "trash_worklog".to_string(),
+                work_title: "Trashed work".to_string(),
+                body: "Oops.".to_string(),
+                created_at: now,
+                updated_at: now,
+                author_id: None,
+            })
+            .execute(&mut conn)
+            .expect("Failed to insert test worklog");
+        let resp = test::call_service(&app, test::TestRequest::delete().uri("/blog/worklog/single/trash_worklog").to_request()).await;
+        assert!(resp.status().is_success());
+        let resp = test::call_service(&app, as_editor(test::TestRequest::post().uri("/trash/worklog/trash_worklog/restore"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let live_worklog =
+            worklog::table
+                .filter(worklog::worklog_id.eq("trash_worklog"))
+                .filter(worklog::deleted_at.is_null())
+                .count()
+                .get_result::<i64>(&mut conn)
+                .unwrap();
+        diesel::delete(worklog::table.filter(worklog::worklog_id.eq("trash_worklog")))
+            .execute(&mut conn)
+            .expect("Failed to delete test worklog");
+        assert_eq!(live_worklog, 1);
+
+        let resp = test::call_service(&app, as_editor(test::TestRequest::post().uri("/trash/comments/1/restore"))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn test_post_if_match() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(get_by_post_id)
+                    .service(update_post)
+                    .service(delete_post),
+            )
+                .await;
+
+        let created_at = NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let _guard = TestGuard::new(pool.clone(), vec![NewPost {
+            post_id: "etag_post".to_string(),
+            slug: "etag_post".to_string(),
+            title: "Versioned".to_string(),
+            body: "First version.".to_string(),
+            created_at,
+            updated_at: created_at,
+            status: PostStatus::Published,
+            publish_at: None,
+            author_id: None,
+        }]);
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/blog/post/retrieve/post-id/etag_post").to_request()).await;
+        let original_etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+
+        let update = |if_match: &str, body: &str| {
+            test::TestRequest::put()
+                .uri("/blog/posts/update/etag_post")
+                .insert_header(("If-Match", if_match))
+                .set_json(json!({
+                    "id": 9100,
+                    "post_id": "etag_post",
+                    "title": "Versioned",
+                    "body": body,
+                    "created_at": "2024-08-29T14:00:00Z",
+                    "updated_at": "2024-08-29T14:00:00Z"
+                }))
+                .to_request()
+        };
+
+        // The first editor's write goes through and bumps updated_at, which changes the ETag
+        let resp = test::call_service(&app, update(&original_etag, "Second version.")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let new_etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        assert_ne!(new_etag, original_etag);
+
+        let json_body: Value =
+            test::call_and_read_body_json(&app, test::TestRequest::get().uri("/blog/post/retrieve/post-id/etag_post").to_request()).await;
+        assert_eq!(json_body["body"], "Second version.");
+        assert!(json_body["updated_at"].as_str().unwrap() > "2024-08-29T14:00:00");
+
+        // A second editor still holding the original version is turned away
+        let resp = test::call_service(&app, update(&original_etag, "Conflicting version.")).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(resp.headers().get("etag").unwrap().to_str().unwrap(), new_etag);
+
+        let delete = |if_match: &str| {
+            test::TestRequest::delete()
+                .uri("/blog/post/single/etag_post")
+                .insert_header(("If-Match", if_match))
+                .to_request()
+        };
+        let resp = test::call_service(&app, delete(&original_etag)).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        let resp = test::call_service(&app, delete(&new_etag)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    async fn test_import_export() {
+        use crate::controllers::import_export_controller::{export_stream, import_records, parse_ndjson, TransferFormat};
+        use futures::StreamExt;
+
+        let pool = web::Data::new(establish_connection());
+        let mut conn = pool.get().unwrap();
+
+        let line = |title: &str, updated_at: &str| {
+            json!({
+                "type": "post",
+                "post_id": "imported_post",
+                "title": title,
+                "slug": "imported-post",
+                "status": "draft",
+                "tags": ["Imported"],
+                "created_at": "2024-08-29T14:00:00Z",
+                "updated_at": updated_at,
+                "body": "Brought in from elsewhere."
+            }).to_string()
+        };
+        let records = parse_ndjson(line("Imported Post", "2024-08-29T14:00:00Z").as_bytes()).unwrap();
+
+        // A dry run reports the create without writing anything
+        let report = import_records(&mut conn, &records, None, true).unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(report.created[0].id, "imported_post");
+        assert_eq!(posts::table.filter(posts::post_id.eq("imported_post")).count().get_result::<i64>(&mut conn).unwrap(), 0);
+
+        let report = import_records(&mut conn, &records, None, false).unwrap();
+        assert!(report.applied);
+        let post = posts::table.filter(posts::post_id.eq("imported_post")).select(Post::as_select()).first(&mut conn).unwrap();
+        assert_eq!(post.slug, "imported-post");
+        assert_eq!(post.status, PostStatus::Draft);
+
+        // The same post twice, or an older copy than the stored one, conflicts and nothing is written
+        let twice = parse_ndjson(format!("{}\n{}", line("Newer", "2024-08-30T14:00:00Z"), line("Newer", "2024-08-30T14:00:00Z")).as_bytes()).unwrap();
+        let report = import_records(&mut conn, &twice, None, false).unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.updated.len(), 1);
+        assert_eq!(report.conflicts[0].reason, "It appears more than once in the import");
+        let older = parse_ndjson(line("Older", "2024-08-28T14:00:00Z").as_bytes()).unwrap();
+        assert_eq!(import_records(&mut conn, &older, None, false).unwrap().conflicts.len(), 1);
+        assert_eq!(posts::table.filter(posts::post_id.eq("imported_post")).select(posts::title).first::<String>(&mut conn).unwrap(), "Imported Post");
+
+        // A newer copy updates the post, and its slug follows the new title
+        let newer = parse_ndjson(line("Renamed Import", "2024-08-30T14:00:00Z").as_bytes()).unwrap();
+        let report = import_records(&mut conn, &newer, None, false).unwrap();
+        assert!(report.applied);
+        assert_eq!(report.updated.len(), 1);
+        let post = posts::table.filter(posts::post_id.eq("imported_post")).select(Post::as_select()).first(&mut conn).unwrap();
+        assert_eq!(post.title, "Renamed Import");
+        assert_eq!(post.slug, "renamed-import");
+
+        // Status and publish time go through the same checks as the API
+        let published = |post_id: &str, status: &str| {
+            json!({
+                "type": "post",
+                "post_id": post_id,
+                "title": post_id,
+                "status": status,
+                "created_at": "2024-08-29T14:00:00Z",
+                "updated_at": "2024-08-29T14:00:00Z",
+                "body": "No publish time given."
+            }).to_string()
+        };
+        let scheduled = parse_ndjson(published("imported_scheduled", "scheduled").as_bytes()).unwrap();
+        let report = import_records(&mut conn, &scheduled, None, false).unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.conflicts[0].reason, "Scheduled posts need a publish_at timestamp");
+        let imported_at = chrono::Utc::now().naive_utc();
+        let records = parse_ndjson(published("imported_published", "published").as_bytes()).unwrap();
+        assert!(import_records(&mut conn, &records, None, false).unwrap().applied);
+        let publish_at =
+            posts::table.filter(posts::post_id.eq("imported_published")).select(posts::publish_at).first::<Option<NaiveDateTime>>(&mut conn).unwrap();
+        diesel::update(posts::table.filter(posts::post_id.eq("imported_published")))
+            .set(posts::deleted_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(&mut conn)
+            .unwrap();
+        purge_trash(&mut conn, TrashKind::Posts, Some("imported_published"), None).unwrap();
+        assert!(publish_at.is_some_and(|publish_at| publish_at >= imported_at));
+
+        // The export carries the post with its tags, and reads back in as the same record
+        let chunks: Vec<_> = export_stream(pool.get_ref().clone(), TransferFormat::Ndjson).collect().await;
+        let export: Vec<u8> = chunks.into_iter().flat_map(|chunk| chunk.unwrap().to_vec()).collect();
+        let exported = parse_ndjson(&export).unwrap();
+        let imported = exported.iter().find(|record| serde_json::to_value(record).unwrap()["post_id"] == "imported_post").unwrap();
+        let imported = serde_json::to_value(imported).unwrap();
+        assert_eq!(imported["tags"], json!(["Imported"]));
+        assert_eq!(imported["slug"], "renamed-import");
+
+        diesel::update(posts::table.filter(posts::post_id.eq("imported_post")))
+            .set(posts::deleted_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(&mut conn)
+            .unwrap();
+        purge_trash(&mut conn, TrashKind::Posts, Some("imported_post"), None).unwrap();
+        diesel::delete(tags::table.filter(tags::slug.eq("imported"))).execute(&mut conn).unwrap();
+    }
+
+    async fn test_post_conditional_get() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(get_by_post_id)
+                    .service(get_all_posts),
+            )
+                .await;
+
+        let created_at = NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let _guard = TestGuard::new(pool.clone(), vec![NewPost {
+            post_id: "cached_post".to_string(),
+            slug: "cached_post".to_string(),
+            title: "Cached".to_string(),
+            body: "Fetched once.".to_string(),
+            created_at,
+            updated_at: created_at,
+            status: PostStatus::Published,
+            publish_at: None,
+            author_id: None,
+        }]);
+
+        let get = |uri: &str, header: (&str, &str)| test::TestRequest::get().uri(uri).insert_header(header).to_request();
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/blog/post/retrieve/post-id/cached_post").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        // Posts are tagged by content, updated_at misses changes like reactions, so there is no Last-Modified to go by
+        assert!(resp.headers().get("last-modified").is_none());
+
+        let resp = test::call_service(&app, get("/blog/post/retrieve/post-id/cached_post", ("If-None-Match", &etag))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+
+        // The list is tagged by its content, so it stays cached until a post in it changes
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/blog/post/get/all").to_request()).await;
+        let list_etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        let resp = test::call_service(&app, get("/blog/post/get/all", ("If-None-Match", &list_etag))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        let body: Bytes = to_bytes(resp.into_body()).await.unwrap();
+        assert!(body.is_empty());
+
+        let mut conn = pool.get().unwrap();
+        diesel::update(posts::table.filter(posts::post_id.eq("cached_post")))
+            .set((posts::body.eq("Fetched twice."), posts::updated_at.eq(chrono::Utc::now().naive_utc())))
+            .execute(&mut conn)
+            .unwrap();
+
+        let resp = test::call_service(&app, get("/blog/post/get/all", ("If-None-Match", &list_etag))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, get("/blog/post/retrieve/post-id/cached_post", ("If-None-Match", &etag))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    async fn test_feeds() {
+        let pool = web::Data::new(establish_connection());
+
+        let app =
+            test::init_service(
+                App::new()
+                    .app_data(pool.clone())
+                    .service(rss_feed)
+                    .service(atom_feed)
+                    .service(json_feed),
+            )
+                .await;
+
+        let posts_to_insert = vec![
+            NewPost {
+                post_id: "feed_published".to_string(),
+                slug: "feed_published".to_string(),
+                title: "Feed Published".to_string(),
+                body: "In the feed.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+            NewPost {
+                post_id: "feed_draft".to_string(),
+                slug: "feed_draft".to_string(),
+                title: "Feed Draft".to_string(),
+                body: "Not in the feed.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                status: PostStatus::Draft,
+                publish_at: None,
+                author_id: None,
+            },
+        ];
+
+        let _guard = TestGuard::new(pool.clone(), posts_to_insert);
+
+        for (uri, content_type) in [
+            ("/feed.xml", "application/rss+xml; charset=utf-8"),
+            ("/atom.xml", "application/atom+xml; charset=utf-8"),
+            ("/feed.json", "application/feed+json; charset=utf-8"),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.headers().get("content-type").unwrap(), content_type);
+            let last_modified = resp.headers().get("last-modified").unwrap().clone();
+
+            let body = test::read_body(resp).await;
+            let body = std::str::from_utf8(&body).unwrap();
+            assert!(body.contains("Feed Published"));
+            assert!(!body.contains("Feed Draft"));
+
+            // A reader that already has this version gets nothing back
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("If-Modified-Since", last_modified))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/feed.xml")
+            .insert_header(("If-Modified-Since", "Sat, 01 Jan 2000 00:00:00 GMT"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    fn test_resolve_publication() {
+        let now = NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(resolve_publication(None, None, now), Ok((PostStatus::Published, Some(now))));
+        assert_eq!(
+            resolve_publication(None, Some("2024-09-01T09:00:00Z"), now).map(|(status, _)| status),
+            Ok(PostStatus::Scheduled)
+        );
+        assert_eq!(resolve_publication(Some(PostStatus::Draft), None, now), Ok((PostStatus::Draft, None)));
+        assert!(resolve_publication(Some(PostStatus::Scheduled), None, now).is_err());
+        assert!(resolve_publication(None, Some("tomorrow"), now).is_err());
     }
 
     async fn test_delete_post() {
@@ -598,18 +2836,26 @@ mod blog_controller_spec {
         let posts_to_insert = vec![
             NewPost {
                 post_id: "abc200".to_string(),
+                slug: "abc200".to_string(),
                 title: "Test Post 1".to_string(),
                 body: "This is the first test post.".to_string(),
 
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
             NewPost {
                 post_id: "def456".to_string(),
+                slug: "def456".to_string(),
                 title: "Test Post 2".to_string(),
                 body: "This is the second test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
         ];
 
@@ -626,16 +2872,20 @@ mod blog_controller_spec {
             pool.get().expect("Failed to get connection from pool");
 
         let deleted_post = posts::table
+            .filter(posts::deleted_at.is_null())
             .filter(posts::post_id.eq("abc200"))
-            .first::<Post>(&mut conn)
+            .select(Post::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted post");
 
         assert!(deleted_post.is_none());
 
         let deleted_post = posts::table
+            .filter(posts::deleted_at.is_null())
             .filter(posts::post_id.eq("def456"))
-            .first::<Post>(&mut conn)
+            .select(Post::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted post");
 
@@ -659,17 +2909,25 @@ mod blog_controller_spec {
         let posts_to_insert = vec![
             NewPost {
                 post_id: "fake_id_1".to_string(),
+                slug: "fake_id_1".to_string(),
                 title: "Test Post 1".to_string(),
                 body: "This is the first test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
             NewPost {
                 post_id: "fake_id_2".to_string(),
+                slug: "fake_id_2".to_string(),
                 title: "Test Post 2".to_string(),
                 body: "This is the second test post.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
             },
         ];
 
@@ -689,16 +2947,20 @@ mod blog_controller_spec {
             pool.get().expect("Failed to get connection from pool");
 
         let deleted_post = posts::table
+            .filter(posts::deleted_at.is_null())
             .filter(posts::post_id.eq("fake_id_1"))
-            .first::<Post>(&mut conn)
+            .select(Post::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted post");
 
         assert!(deleted_post.is_none());
 
         let deleted_post = posts::table
+            .filter(posts::deleted_at.is_null())
             .filter(posts::post_id.eq("fake_id_2"))
-            .first::<Post>(&mut conn)
+            .select(Post::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted post");
 
@@ -0,0 +1,18 @@
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use serde_json::json;
+
+use crate::connectors::content_cache::ContentCache;
+use crate::controllers::login_controller::session_from_request;
+
+// Hit and miss counts of the content cache since the server started, for admins and editors
+#[get("/cache/stats")]
+async fn get_cache_stats(
+    cache: web::Data<ContentCache>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    match session_from_request(&req).await {
+        Some(session) if session.is_editor_or_admin() => Ok(HttpResponse::Ok().json(cache.stats())),
+        Some(_) => Ok(HttpResponse::Forbidden().json(json!({ "error": "Only admins and editors can view cache stats" }))),
+        None => Ok(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+}
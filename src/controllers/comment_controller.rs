@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use actix_web::{get, post, put, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::login_controller::{get_user_by_user_id, session_from_request};
+use crate::models::CommentStatus::CommentStatus;
+use crate::models::PostStatus::PostStatus;
+use crate::models::SessionData::SessionData;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::comment_schema::comments;
+use crate::table_models::comment_models::{Comment, NewComment};
+
+const MAX_AUTHOR_NAME_LENGTH: usize = 100;
+const MAX_COMMENT_LENGTH: usize = 5000;
+const DEFAULT_QUEUE_LIMIT: i64 = 50;
+const MAX_QUEUE_LIMIT: i64 = 200;
+
+// Guests have to give a name, logged in users are named after their account
+#[derive(Serialize, Deserialize)]
+pub struct CommentInput {
+    pub body: String,
+    pub author_name: Option<String>,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModerationInput {
+    pub status: CommentStatus,
+}
+
+// Query string for the moderation queue, e.g. ?status=spam&limit=20
+#[derive(Debug, Deserialize)]
+pub struct ModerationQueueQuery {
+    pub status: Option<CommentStatus>,
+    pub limit: Option<i64>,
+}
+
+// An approved comment as shown publicly, with its approved replies nested below it
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CommentNode {
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub author_name: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub replies: Vec<CommentNode>,
+}
+
+// Nests comments under their parents, oldest first at every level.
+// Replies whose parent is not in `comments` are left out along with the parent.
+pub fn build_comment_tree(mut comments: Vec<Comment>) -> Vec<CommentNode> {
+    comments.sort_by_key(|comment| (comment.created_at, comment.id));
+
+    let mut children: HashMap<Option<i32>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        children.entry(comment.parent_id).or_default().push(comment);
+    }
+
+    fn nest(parent_id: Option<i32>, children: &mut HashMap<Option<i32>, Vec<Comment>>) -> Vec<CommentNode> {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|comment| CommentNode {
+                replies: nest(Some(comment.id), children),
+                id: comment.id,
+                parent_id: comment.parent_id,
+                author_name: comment.author_name,
+                body: comment.body,
+                created_at: comment.created_at,
+            })
+            .collect()
+    }
+
+    nest(None, &mut children)
+}
+
+// Records a moderation decision, None when there is no comment with that id
+pub fn moderate_comment(
+    conn: &mut PgConnection,
+    comment_id: i32,
+    status: CommentStatus,
+    moderator_id: &str,
+) -> QueryResult<Option<Comment>> {
+    let now = chrono::Utc::now().naive_utc();
+
+    diesel::update(comments::table.filter(comments::id.eq(comment_id)))
+        .set((
+            comments::status.eq(status),
+            comments::moderated_by.eq(moderator_id),
+            comments::moderated_at.eq(now),
+            comments::updated_at.eq(now),
+        ))
+        .returning(Comment::as_returning())
+        .get_result(conn)
+        .optional()
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({ "error": message }))
+}
+
+// Moderation needs a logged in admin or editor
+async fn require_moderator(req: &HttpRequest) -> Result<SessionData, HttpResponse> {
+    match session_from_request(req).await {
+        Some(session) if session.is_editor_or_admin() => Ok(session),
+        Some(_) => Err(HttpResponse::Forbidden().json(json!({ "error": "Only admins and editors can moderate comments" }))),
+        None => Err(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+}
+
+#[post("/blog/post/{post_id}/comments")]
+async fn create_comment(
+    path: web::Path<String>,
+    comment: web::Json<CommentInput>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+    let comment_input = comment.into_inner();
+    let session = session_from_request(&req).await;
+
+    let body = comment_input.body.trim().to_string();
+    if body.is_empty() || body.chars().count() > MAX_COMMENT_LENGTH {
+        return Ok(bad_request(format!("Comment body must be between 1 and {} characters", MAX_COMMENT_LENGTH)));
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    // Only published posts take comments
+    let post_exists =
+        posts::table
+            .filter(posts::post_id.eq(&post_id))
+            .filter(posts::status.eq(PostStatus::Published))
+            .filter(posts::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e)))?
+            > 0;
+    if !post_exists {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("Blog post with ID '{}' not found", post_id)
+        })));
+    }
+
+    if let Some(parent_id) = comment_input.parent_id {
+        let parent_post_id =
+            comments::table
+                .filter(comments::id.eq(parent_id))
+                .select(comments::post_id)
+                .first::<String>(&mut conn)
+                .optional()
+                .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving comment: {}", e)))?;
+        if parent_post_id.as_deref() != Some(post_id.as_str()) {
+            return Ok(bad_request(format!("Comment {} is not on blog post '{}'", parent_id, post_id)));
+        }
+    }
+
+    let (author_id, author_name) =
+        match &session {
+            Some(session) => {
+                let user =
+                    get_user_by_user_id(&mut conn, &session.user_id)
+                        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving user: {}", e)))?;
+                match user {
+                    Some(user) => (Some(user.user_id), user.username),
+                    None => return Ok(HttpResponse::Unauthorized().json(json!({ "error": "User not found" }))),
+                }
+            }
+            None => {
+                let author_name = comment_input.author_name.as_deref().unwrap_or("").trim().to_string();
+                if author_name.is_empty() || author_name.chars().count() > MAX_AUTHOR_NAME_LENGTH {
+                    return Ok(bad_request(format!("Guests need an author_name of 1 to {} characters", MAX_AUTHOR_NAME_LENGTH)));
+                }
+                (None, author_name)
+            }
+        };
+
+    // Comments from admins and editors skip the queue
+    let status =
+        match &session {
+            Some(session) if session.is_editor_or_admin() => CommentStatus::Approved,
+            _ => CommentStatus::Pending,
+        };
+
+    let now = chrono::Utc::now().naive_utc();
+    let new_comment =
+        NewComment {
+            post_id,
+            parent_id: comment_input.parent_id,
+            author_id,
+            author_name,
+            body,
+            status,
+            created_at: now,
+            updated_at: now,
+        };
+
+    match diesel::insert_into(comments::table)
+        .values(&new_comment)
+        .returning(Comment::as_returning())
+        .get_result(&mut conn)
+    {
+        Ok(comment) => {
+            publish_live(&req, LiveKind::Comment, LiveAction::Created, &comment.id.to_string()).await;
+            Ok(HttpResponse::Created().json(comment))
+        }
+        Err(e) => {
+            eprintln!("Error inserting new comment: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/blog/post/{post_id}/comments")]
+async fn get_post_comments(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match comments::table
+        .filter(comments::post_id.eq(&post_id))
+        .filter(comments::status.eq(CommentStatus::Approved))
+        .select(Comment::as_select())
+        .load(&mut conn)
+    {
+        Ok(comments) => Ok(HttpResponse::Ok().json(build_comment_tree(comments))),
+        Err(e) => {
+            eprintln!("Error loading comments: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Oldest first so comments are worked through in the order they arrived
+#[get("/blog/comments/moderation")]
+async fn get_moderation_queue(
+    query: web::Query<ModerationQueueQuery>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = require_moderator(&req).await {
+        return Ok(response);
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_QUEUE_LIMIT);
+    if !(1..=MAX_QUEUE_LIMIT).contains(&limit) {
+        return Ok(bad_request(format!("limit must be between 1 and {}", MAX_QUEUE_LIMIT)));
+    }
+    let status = query.status.unwrap_or(CommentStatus::Pending);
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match comments::table
+        .filter(comments::status.eq(status))
+        .order((comments::created_at.asc(), comments::id.asc()))
+        .limit(limit)
+        .select(Comment::as_select())
+        .load(&mut conn)
+    {
+        Ok(comments) => Ok(HttpResponse::Ok().json(comments)),
+        Err(e) => {
+            eprintln!("Error loading moderation queue: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[put("/blog/comments/{comment_id}/moderate")]
+async fn moderate(
+    path: web::Path<i32>,
+    moderation: web::Json<ModerationInput>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let comment_id = path.into_inner();
+    let moderator = match require_moderator(&req).await {
+        Ok(session) => session,
+        Err(response) => return Ok(response),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match moderate_comment(&mut conn, comment_id, moderation.status, &moderator.user_id) {
+        Ok(Some(comment)) => {
+            publish_live(&req, LiveKind::Comment, LiveAction::Updated, &comment.id.to_string()).await;
+            Ok(HttpResponse::Ok().json(comment))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("Comment {} not found", comment_id)
+        }))),
+        Err(e) => {
+            eprintln!("Error moderating comment: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(id: i32, parent_id: Option<i32>, minute: u32) -> Comment {
+        let created_at =
+            NaiveDateTime::parse_from_str(&format!("2024-08-29 14:{:02}:00", minute), "%Y-%m-%d %H:%M:%S").unwrap();
+        Comment {
+            id,
+            post_id: "post".to_string(),
+            parent_id,
+            author_id: None,
+            author_name: format!("author {}", id),
+            body: format!("comment {}", id),
+            status: CommentStatus::Approved,
+            moderated_by: None,
+            moderated_at: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    #[test]
+    fn test_build_comment_tree_nests_replies_and_drops_orphans() {
+        let tree = build_comment_tree(vec![
+            comment(3, Some(1), 3),
+            comment(1, None, 1),
+            comment(2, None, 2),
+            comment(4, Some(3), 4),
+            // Its parent was not approved, so it isn't shown
+            comment(5, Some(99), 5),
+        ]);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].id, 1);
+        assert_eq!(tree[0].replies[0].id, 3);
+        assert_eq!(tree[0].replies[0].replies[0].id, 4);
+        assert_eq!(tree[1].id, 2);
+        assert!(tree[1].replies.is_empty());
+    }
+}
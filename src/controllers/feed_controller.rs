@@ -0,0 +1,318 @@
+use std::env;
+use std::time::SystemTime;
+
+use actix_web::http::header::{self, HttpDate, IfModifiedSince, LastModified};
+use actix_web::{get, web, Error, HttpMessage, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::{json, Value};
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::tag_controller::attach_tags;
+use crate::models::PostResponse::PostResponse;
+use crate::models::PostStatus::PostStatus;
+use crate::schemas::blog_schema::posts;
+use crate::table_models::blog_models::Post;
+use crate::utils::etag::{is_not_modified, to_system_time};
+use crate::utils::markdown::render_markdown;
+
+const DEFAULT_FEED_SIZE: i64 = 20;
+
+// Site details shared by all three feeds, read from SITE_TITLE, SITE_DESCRIPTION, SITE_BASE_URL and FEED_SIZE
+pub struct FeedConfig {
+    pub title: String,
+    pub description: String,
+    pub base_url: String,
+    pub size: i64,
+}
+
+impl FeedConfig {
+    pub fn from_env() -> Self {
+        FeedConfig {
+            title: env::var("SITE_TITLE").unwrap_or_else(|_| "tarnish".to_string()),
+            description: env::var("SITE_DESCRIPTION").unwrap_or_else(|_| "Latest blog posts".to_string()),
+            base_url: env::var("SITE_BASE_URL")
+                .unwrap_or_else(|_| "http://localhost:3000".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            size: env::var("FEED_SIZE")
+                .ok()
+                .and_then(|size| size.parse::<i64>().ok())
+                .filter(|size| *size > 0)
+                .unwrap_or(DEFAULT_FEED_SIZE),
+        }
+    }
+
+    // Where readers land when they open an entry
+    pub fn post_url(&self, post: &Post) -> String {
+        format!("{}/blog/{}", self.base_url, post.slug)
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn rfc3339(timestamp: NaiveDateTime) -> String {
+    timestamp.and_utc().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+pub fn render_rss(config: &FeedConfig, entries: &[PostResponse], now: NaiveDateTime) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+    xml.push_str(&format!("<title>{}</title>\n", xml_escape(&config.title)));
+    xml.push_str(&format!("<link>{}</link>\n", xml_escape(&config.base_url)));
+    xml.push_str(&format!("<description>{}</description>\n", xml_escape(&config.description)));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}/feed.xml\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        xml_escape(&config.base_url)
+    ));
+    xml.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", now.and_utc().to_rfc2822()));
+
+    for entry in entries {
+        let post = &entry.post;
+        let url = xml_escape(&config.post_url(post));
+        xml.push_str("<item>\n");
+        xml.push_str(&format!("<title>{}</title>\n", xml_escape(&post.title)));
+        xml.push_str(&format!("<link>{}</link>\n", url));
+        xml.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", url));
+        xml.push_str(&format!("<pubDate>{}</pubDate>\n", post.created_at.and_utc().to_rfc2822()));
+        for tag in &entry.tags {
+            xml.push_str(&format!("<category>{}</category>\n", xml_escape(&tag.name)));
+        }
+        xml.push_str(&format!("<description>{}</description>\n", xml_escape(&render_markdown(&post.body).body_html)));
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+pub fn render_atom(config: &FeedConfig, entries: &[PostResponse], now: NaiveDateTime) -> String {
+    let updated = entries.iter().map(|entry| entry.post.updated_at).max().unwrap_or(now);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("<title>{}</title>\n", xml_escape(&config.title)));
+    xml.push_str(&format!("<subtitle>{}</subtitle>\n", xml_escape(&config.description)));
+    xml.push_str(&format!("<id>{}/</id>\n", xml_escape(&config.base_url)));
+    xml.push_str(&format!("<link href=\"{}\"/>\n", xml_escape(&config.base_url)));
+    xml.push_str(&format!("<link rel=\"self\" href=\"{}/atom.xml\"/>\n", xml_escape(&config.base_url)));
+    xml.push_str(&format!("<updated>{}</updated>\n", rfc3339(updated)));
+
+    for entry in entries {
+        let post = &entry.post;
+        let url = xml_escape(&config.post_url(post));
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<title>{}</title>\n", xml_escape(&post.title)));
+        xml.push_str(&format!("<id>{}</id>\n", url));
+        xml.push_str(&format!("<link href=\"{}\"/>\n", url));
+        xml.push_str(&format!("<published>{}</published>\n", rfc3339(post.created_at)));
+        xml.push_str(&format!("<updated>{}</updated>\n", rfc3339(post.updated_at)));
+        if let Some(author) = &entry.author {
+            xml.push_str(&format!("<author><name>{}</name></author>\n", xml_escape(&author.username)));
+        }
+        for tag in &entry.tags {
+            xml.push_str(&format!("<category term=\"{}\" label=\"{}\"/>\n", xml_escape(&tag.slug), xml_escape(&tag.name)));
+        }
+        xml.push_str(&format!(
+            "<content type=\"html\">{}</content>\n",
+            xml_escape(&render_markdown(&post.body).body_html)
+        ));
+        xml.push_str("</entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+// JSON Feed 1.1, https://www.jsonfeed.org/version/1.1/
+pub fn render_json_feed(config: &FeedConfig, entries: &[PostResponse]) -> Value {
+    let items: Vec<Value> =
+        entries
+            .iter()
+            .map(|entry| {
+                let post = &entry.post;
+                let authors: Vec<Value> = entry.author.iter().map(|author| json!({ "name": author.username })).collect();
+                json!({
+                    "id": config.post_url(post),
+                    "url": config.post_url(post),
+                    "title": post.title,
+                    "content_html": render_markdown(&post.body).body_html,
+                    "date_published": rfc3339(post.created_at),
+                    "date_modified": rfc3339(post.updated_at),
+                    "tags": entry.tags.iter().map(|tag| tag.name.clone()).collect::<Vec<String>>(),
+                    "authors": authors,
+                })
+            })
+            .collect();
+
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": config.title,
+        "description": config.description,
+        "home_page_url": config.base_url,
+        "feed_url": format!("{}/feed.json", config.base_url),
+        "items": items,
+    })
+}
+
+// The newest published posts, plus the last time anything in the posts table changed.
+// Any post counts for the timestamp so unpublishing or trashing a post also invalidates cached feeds.
+pub fn load_feed(conn: &mut PgConnection, size: i64) -> QueryResult<(Vec<PostResponse>, Option<NaiveDateTime>)> {
+    let latest_posts =
+        posts::table
+            .filter(posts::status.eq(PostStatus::Published))
+            .filter(posts::deleted_at.is_null())
+            .order((posts::created_at.desc(), posts::id.desc()))
+            .limit(size)
+            .select(Post::as_select())
+            .load(conn)?;
+
+    let (last_updated, last_deleted) =
+        posts::table
+            .select((diesel::dsl::max(posts::updated_at), diesel::dsl::max(posts::deleted_at)))
+            .first::<(Option<NaiveDateTime>, Option<NaiveDateTime>)>(conn)?;
+    let last_modified = last_updated.max(last_deleted);
+
+    Ok((attach_tags(conn, latest_posts)?, last_modified))
+}
+
+enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+async fn feed_response(pool: web::Data<DbPool>, req: HttpRequest, format: FeedFormat) -> Result<HttpResponse, Error> {
+    let config = FeedConfig::from_env();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let (entries, last_modified) =
+        match load_feed(&mut conn, config.size) {
+            Ok(feed) => feed,
+            Err(e) => {
+                eprintln!("Error loading feed: {:?}", e);
+                return Ok(HttpResponse::InternalServerError().finish());
+            }
+        };
+
+    let if_modified_since = req.get_header::<IfModifiedSince>().map(|header| SystemTime::from(header.0));
+    if is_not_modified(if_modified_since, last_modified) {
+        return Ok(HttpResponse::NotModified().finish());
+    }
+
+    let mut response = HttpResponse::Ok();
+    if let Some(last_modified) = last_modified {
+        response.insert_header(LastModified(HttpDate::from(to_system_time(last_modified))));
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    Ok(match format {
+        FeedFormat::Rss => response
+            .insert_header((header::CONTENT_TYPE, "application/rss+xml; charset=utf-8"))
+            .body(render_rss(&config, &entries, now)),
+        FeedFormat::Atom => response
+            .insert_header((header::CONTENT_TYPE, "application/atom+xml; charset=utf-8"))
+            .body(render_atom(&config, &entries, now)),
+        FeedFormat::Json => response
+            .insert_header((header::CONTENT_TYPE, "application/feed+json; charset=utf-8"))
+            .body(render_json_feed(&config, &entries).to_string()),
+    })
+}
+
+#[get("/feed.xml")]
+async fn rss_feed(pool: web::Data<DbPool>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    feed_response(pool, req, FeedFormat::Rss).await
+}
+
+#[get("/atom.xml")]
+async fn atom_feed(pool: web::Data<DbPool>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    feed_response(pool, req, FeedFormat::Atom).await
+}
+
+#[get("/feed.json")]
+async fn json_feed(pool: web::Data<DbPool>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    feed_response(pool, req, FeedFormat::Json).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Author::Author;
+    use crate::models::Reaction::ReactionCounts;
+    use crate::table_models::tag_models::TagLabel;
+
+    fn entry() -> PostResponse {
+        let created_at = NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        PostResponse {
+            post: Post {
+                id: 1,
+                post_id: "fish-and-chips".to_string(),
+                title: "Fish & Chips <3".to_string(),
+                body: "Some **bold** text".to_string(),
+                created_at,
+                updated_at: created_at,
+                status: PostStatus::Published,
+                publish_at: Some(created_at),
+                slug: "fish-and-chips".to_string(),
+                deleted_at: None,
+                author_id: Some("user_mikey".to_string()),
+                series_id: None,
+                series_position: None,
+                like_count: 0,
+                love_count: 0,
+                insightful_count: 0,
+            },
+            tags: vec![TagLabel { name: "Food".to_string(), slug: "food".to_string() }],
+            author: Some(Author { user_id: "user_mikey".to_string(), username: "mikey".to_string() }),
+            series: None,
+            reactions: ReactionCounts::default(),
+            locale: None,
+        }
+    }
+
+    fn config() -> FeedConfig {
+        FeedConfig {
+            title: "My Blog".to_string(),
+            description: "Posts".to_string(),
+            base_url: "https://example.com".to_string(),
+            size: 20,
+        }
+    }
+
+    #[test]
+    fn test_render_rss_escapes_and_links_entries() {
+        let rss = render_rss(&config(), &[entry()], entry().post.created_at);
+
+        assert!(rss.contains("<title>Fish &amp; Chips &lt;3</title>"));
+        assert!(rss.contains("<link>https://example.com/blog/fish-and-chips</link>"));
+        assert!(rss.contains("<pubDate>Thu, 29 Aug 2024 14:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<category>Food</category>"));
+        assert!(rss.contains("&lt;strong&gt;bold&lt;/strong&gt;"));
+    }
+
+    #[test]
+    fn test_render_atom_and_json_feed() {
+        let atom = render_atom(&config(), &[entry()], entry().post.created_at);
+        assert!(atom.contains("<updated>2024-08-29T14:00:00Z</updated>"));
+        assert!(atom.contains("<id>https://example.com/blog/fish-and-chips</id>"));
+        assert!(atom.contains("<author><name>mikey</name></author>"));
+
+        let feed = render_json_feed(&config(), &[entry()]);
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(feed["items"][0]["title"], "Fish & Chips <3");
+        assert_eq!(feed["items"][0]["tags"], json!(["Food"]));
+        assert_eq!(feed["items"][0]["date_published"], "2024-08-29T14:00:00Z");
+        assert_eq!(feed["items"][0]["authors"], json!([{ "name": "mikey" }]));
+    }
+}
@@ -0,0 +1,843 @@
+use std::collections::HashSet;
+use std::env;
+
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+use chrono::{DateTime, NaiveDateTime};
+use diesel::prelude::*;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::{enqueue_post_event, resolve_publication_at};
+use crate::controllers::login_controller::session_from_request;
+use crate::controllers::post_revision_controller::snapshot_post;
+use crate::controllers::slug_controller::{refresh_post_slug, slug_taken, unique_slug};
+use crate::controllers::skills_controller::enqueue_skill_event;
+use crate::controllers::tag_controller::{attach_tags, normalize_tag_names, set_post_tags};
+use crate::controllers::worklog_controller::enqueue_worklog_event;
+use crate::models::PostStatus::PostStatus;
+use crate::models::SessionData::SessionData;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::skills_schema::skills;
+use crate::schemas::worklog_schema::worklog;
+use crate::table_models::blog_models::{NewPost, Post};
+use crate::table_models::skills_models::{NewSkill, Skill};
+use crate::table_models::worklog_models::{NewWorklog, Worklog};
+use crate::utils::front_matter::{parse_front_matter, render_front_matter};
+use crate::utils::slug::slugify;
+use crate::utils::tar::{read_tar, tar_end, tar_entry};
+
+const EXPORT_BATCH_SIZE: i64 = 200;
+const DEFAULT_MAX_IMPORT_BYTES: usize = 50 * 1024 * 1024;
+
+// Front matter fields come in this order, anything else follows
+const FRONT_MATTER_ORDER: [&str; 12] = [
+    "post_id", "skill_id", "worklog_id", "title", "skill_name", "work_title",
+    "slug", "status", "publish_at", "tags", "created_at", "updated_at",
+];
+
+// Accepts RFC 3339 timestamps as well as the zone-less ones the export writes
+fn parse_timestamp(raw: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|timestamp| timestamp.naive_utc())
+        .ok()
+        .or_else(|| raw.parse::<NaiveDateTime>().ok())
+}
+
+fn timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    parse_timestamp(&raw).ok_or_else(|| serde::de::Error::custom(format!("'{}' is not a valid timestamp", raw)))
+}
+
+fn optional_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(raw) => parse_timestamp(&raw)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("'{}' is not a valid timestamp", raw))),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostRecord {
+    pub post_id: String,
+    pub title: String,
+    // Only used when the import creates the post, otherwise the slug follows the title
+    #[serde(default)]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub status: Option<PostStatus>,
+    #[serde(default, deserialize_with = "optional_timestamp")]
+    pub publish_at: Option<NaiveDateTime>,
+    // Left out, an update keeps the post's tags
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(deserialize_with = "timestamp")]
+    pub created_at: NaiveDateTime,
+    #[serde(deserialize_with = "timestamp")]
+    pub updated_at: NaiveDateTime,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkillRecord {
+    pub skill_id: String,
+    pub skill_name: String,
+    #[serde(deserialize_with = "timestamp")]
+    pub created_at: NaiveDateTime,
+    #[serde(deserialize_with = "timestamp")]
+    pub updated_at: NaiveDateTime,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorklogRecord {
+    pub worklog_id: String,
+    pub work_title: String,
+    #[serde(deserialize_with = "timestamp")]
+    pub created_at: NaiveDateTime,
+    #[serde(deserialize_with = "timestamp")]
+    pub updated_at: NaiveDateTime,
+    pub body: String,
+}
+
+// One line of an NDJSON export, or one Markdown file of a tar export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ContentRecord {
+    Post(PostRecord),
+    Skill(SkillRecord),
+    Worklog(WorklogRecord),
+}
+
+impl ContentRecord {
+    fn kind(&self) -> &'static str {
+        match self {
+            ContentRecord::Post(_) => "post",
+            ContentRecord::Skill(_) => "skill",
+            ContentRecord::Worklog(_) => "worklog",
+        }
+    }
+
+    fn id(&self) -> &str {
+        match self {
+            ContentRecord::Post(post) => &post.post_id,
+            ContentRecord::Skill(skill) => &skill.skill_id,
+            ContentRecord::Worklog(entry) => &entry.worklog_id,
+        }
+    }
+
+    fn body(&self) -> &str {
+        match self {
+            ContentRecord::Post(post) => &post.body,
+            ContentRecord::Skill(skill) => &skill.body,
+            ContentRecord::Worklog(entry) => &entry.body,
+        }
+    }
+
+    fn updated_at(&self) -> NaiveDateTime {
+        match self {
+            ContentRecord::Post(post) => post.updated_at,
+            ContentRecord::Skill(skill) => skill.updated_at,
+            ContentRecord::Worklog(entry) => entry.updated_at,
+        }
+    }
+
+    // Where the record goes in a tar export
+    fn directory(&self) -> &'static str {
+        match self {
+            ContentRecord::Post(_) => "posts",
+            ContentRecord::Skill(_) => "skills",
+            ContentRecord::Worklog(_) => "worklog",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+    #[default]
+    Ndjson,
+    // A tar of Markdown files with YAML front matter
+    Markdown,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: TransferFormat,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: TransferFormat,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// Exports go through posts, then skills, then worklog, each in id order
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportStage {
+    Posts,
+    Skills,
+    Worklog,
+    Done,
+}
+
+impl ExportStage {
+    fn next(self) -> ExportStage {
+        match self {
+            ExportStage::Posts => ExportStage::Skills,
+            ExportStage::Skills => ExportStage::Worklog,
+            ExportStage::Worklog | ExportStage::Done => ExportStage::Done,
+        }
+    }
+}
+
+// The next batch of live content of one kind after `after_id`, paired with each row's id
+fn load_export_batch(conn: &mut PgConnection, stage: ExportStage, after_id: i32) -> QueryResult<Vec<(i32, ContentRecord)>> {
+    match stage {
+        ExportStage::Posts => {
+            let rows =
+                posts::table
+                    .filter(posts::deleted_at.is_null())
+                    .filter(posts::id.gt(after_id))
+                    .order(posts::id.asc())
+                    .limit(EXPORT_BATCH_SIZE)
+                    .select(Post::as_select())
+                    .load(conn)?;
+            Ok(attach_tags(conn, rows)?
+                .into_iter()
+                .map(|response| {
+                    let post = response.post;
+                    (post.id, ContentRecord::Post(PostRecord {
+                        post_id: post.post_id,
+                        title: post.title,
+                        slug: Some(post.slug),
+                        status: Some(post.status),
+                        publish_at: post.publish_at,
+                        tags: Some(response.tags.into_iter().map(|tag| tag.name).collect()),
+                        created_at: post.created_at,
+                        updated_at: post.updated_at,
+                        body: post.body,
+                    }))
+                })
+                .collect())
+        }
+        ExportStage::Skills => Ok(
+            skills::table
+                .filter(skills::deleted_at.is_null())
+                .filter(skills::id.gt(after_id))
+                .order(skills::id.asc())
+                .limit(EXPORT_BATCH_SIZE)
+                .select(Skill::as_select())
+                .load(conn)?
+                .into_iter()
+                .map(|skill| (skill.id, ContentRecord::Skill(SkillRecord {
+                    skill_id: skill.skill_id,
+                    skill_name: skill.skill_name,
+                    created_at: skill.created_at,
+                    updated_at: skill.updated_at,
+                    body: skill.body,
+                })))
+                .collect()
+        ),
+        ExportStage::Worklog => Ok(
+            worklog::table
+                .filter(worklog::deleted_at.is_null())
+                .filter(worklog::id.gt(after_id))
+                .order(worklog::id.asc())
+                .limit(EXPORT_BATCH_SIZE)
+                .select(Worklog::as_select())
+                .load(conn)?
+                .into_iter()
+                .map(|entry| (entry.id, ContentRecord::Worklog(WorklogRecord {
+                    worklog_id: entry.worklog_id,
+                    work_title: entry.work_title,
+                    created_at: entry.created_at,
+                    updated_at: entry.updated_at,
+                    body: entry.body,
+                })))
+                .collect()
+        ),
+        ExportStage::Done => Ok(Vec::new()),
+    }
+}
+
+// The record's fields minus its type and body, in front matter order
+fn front_matter_fields(record: &ContentRecord) -> Vec<(String, Value)> {
+    let mut fields = match serde_json::to_value(record) {
+        Ok(Value::Object(fields)) => fields,
+        _ => return Vec::new(),
+    };
+    fields.remove("type");
+    fields.remove("body");
+
+    let mut ordered: Vec<(String, Value)> =
+        FRONT_MATTER_ORDER
+            .iter()
+            .filter_map(|key| fields.remove(*key).map(|value| (key.to_string(), value)))
+            .collect();
+    ordered.extend(fields);
+    ordered
+}
+
+// A file name for the record that is unique within the export, e.g. posts/hello-world.md
+fn markdown_file_name(record: &ContentRecord, taken: &mut HashSet<String>) -> String {
+    let stem = match slugify(record.id()) {
+        stem if stem.is_empty() => record.kind().to_string(),
+        stem => stem[..stem.len().min(80)].trim_end_matches('-').to_string(),
+    };
+
+    let mut name = format!("{}/{}.md", record.directory(), stem);
+    let mut suffix = 1;
+    while !taken.insert(name.clone()) {
+        suffix += 1;
+        name = format!("{}/{}-{}.md", record.directory(), stem, suffix);
+    }
+    name
+}
+
+pub fn record_to_markdown(record: &ContentRecord) -> String {
+    render_front_matter(&front_matter_fields(record), record.body())
+}
+
+fn encode_batch(records: &[(i32, ContentRecord)], format: TransferFormat, file_names: &mut HashSet<String>) -> Result<Vec<u8>, serde_json::Error> {
+    let mut encoded = Vec::new();
+    for (_, record) in records {
+        match format {
+            TransferFormat::Ndjson => {
+                serde_json::to_writer(&mut encoded, record)?;
+                encoded.push(b'\n');
+            }
+            TransferFormat::Markdown => {
+                let name = markdown_file_name(record, file_names);
+                encoded.extend(tar_entry(&name, record_to_markdown(record).as_bytes(), record.updated_at().and_utc().timestamp()));
+            }
+        }
+    }
+    Ok(encoded)
+}
+
+struct ExportState {
+    stage: ExportStage,
+    after_id: i32,
+    file_names: HashSet<String>,
+}
+
+// Streams the export one batch at a time, so the whole site never has to be held in memory
+pub fn export_stream(pool: DbPool, format: TransferFormat) -> impl Stream<Item=Result<web::Bytes, Error>> {
+    let start = ExportState { stage: ExportStage::Posts, after_id: 0, file_names: HashSet::new() };
+
+    futures::stream::unfold(Some(start), move |state| {
+        let pool = pool.clone();
+        async move {
+            let mut state = state?;
+
+            loop {
+                if state.stage == ExportStage::Done {
+                    return match format {
+                        TransferFormat::Markdown => Some((Ok(web::Bytes::from(tar_end())), None)),
+                        TransferFormat::Ndjson => None,
+                    };
+                }
+
+                let (stage, after_id) = (state.stage, state.after_id);
+                let pool = pool.clone();
+                let batch =
+                    web::block(move || {
+                        let mut conn = pool.get().map_err(|e| e.to_string())?;
+                        load_export_batch(&mut conn, stage, after_id).map_err(|e| e.to_string())
+                    })
+                        .await;
+
+                match batch {
+                    Ok(Ok(batch)) if batch.is_empty() => {
+                        state.stage = stage.next();
+                        state.after_id = 0;
+                    }
+                    Ok(Ok(batch)) => {
+                        state.after_id = batch[batch.len() - 1].0;
+                        return match encode_batch(&batch, format, &mut state.file_names) {
+                            Ok(encoded) => Some((Ok(web::Bytes::from(encoded)), Some(state))),
+                            Err(e) => Some((Err(actix_web::error::ErrorInternalServerError(format!("Error encoding export: {}", e))), None)),
+                        };
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("Error loading export: {:?}", e);
+                        return Some((Err(actix_web::error::ErrorInternalServerError("Error loading export")), None));
+                    }
+                    Err(e) => {
+                        eprintln!("Export task failed: {:?}", e);
+                        return Some((Err(actix_web::error::ErrorInternalServerError("Error loading export")), None));
+                    }
+                }
+            }
+        }
+    })
+}
+
+pub fn parse_ndjson(data: &[u8]) -> Result<Vec<ContentRecord>, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "The import is not valid UTF-8".to_string())?;
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| serde_json::from_str(line).map_err(|e| format!("Line {}: {}", index + 1, e)))
+        .collect()
+}
+
+// Reads the Markdown files of a tar export. Each file's type comes from the posts/, skills/ or worklog/ folder it is in,
+// anything that isn't a .md file is ignored.
+pub fn parse_markdown_tar(data: &[u8]) -> Result<Vec<ContentRecord>, String> {
+    let mut records = Vec::new();
+
+    for (name, contents) in read_tar(data)? {
+        if !name.ends_with(".md") {
+            continue;
+        }
+        let kind =
+            match name.rsplit('/').nth(1) {
+                Some("posts") => "post",
+                Some("skills") => "skill",
+                Some("worklog") => "worklog",
+                _ => return Err(format!("{}: Markdown files have to be in a posts/, skills/ or worklog/ folder", name)),
+            };
+
+        let document = String::from_utf8(contents).map_err(|_| format!("{}: not valid UTF-8", name))?;
+        let (mut fields, body) = parse_front_matter(&document).map_err(|e| format!("{}: {}", name, e))?;
+        fields.insert("type".to_string(), json!(kind));
+        fields.insert("body".to_string(), json!(body));
+
+        records.push(serde_json::from_value(Value::Object(fields)).map_err(|e| format!("{}: {}", name, e))?);
+    }
+
+    Ok(records)
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportItem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportConflict {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    pub reason: String,
+}
+
+// What an import did, or with dry_run would do. Nothing is written when there are conflicts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub applied: bool,
+    pub created: Vec<ImportItem>,
+    pub updated: Vec<ImportItem>,
+    pub conflicts: Vec<ImportConflict>,
+}
+
+enum ImportAction {
+    Create,
+    Update,
+    Conflict(String),
+}
+
+// An existing item is only overwritten by a copy at least as new as itself
+fn update_or_conflict(existing_updated_at: Option<NaiveDateTime>, record: &ContentRecord) -> ImportAction {
+    match existing_updated_at {
+        None => ImportAction::Create,
+        Some(existing) if existing > record.updated_at() => ImportAction::Conflict(format!(
+            "The stored copy was updated at {}, after the imported one ({})",
+            existing, record.updated_at()
+        )),
+        Some(_) => ImportAction::Update,
+    }
+}
+
+// The status and publish time an imported post is stored with, checked and filled in the way the API does it.
+// None when an update leaves both as they are.
+fn post_publication(post: &PostRecord, create: bool) -> Result<Option<(PostStatus, Option<NaiveDateTime>)>, String> {
+    if !create && post.status.is_none() && post.publish_at.is_none() {
+        return Ok(None);
+    }
+    resolve_publication_at(post.status, post.publish_at, chrono::Utc::now().naive_utc()).map(Some)
+}
+
+fn plan_record(conn: &mut PgConnection, record: &ContentRecord, claimed_slugs: &mut HashSet<String>) -> QueryResult<ImportAction> {
+    match record {
+        ContentRecord::Post(post) => {
+            let existing =
+                posts::table
+                    .filter(posts::post_id.eq(&post.post_id))
+                    .filter(posts::deleted_at.is_null())
+                    .select(posts::updated_at)
+                    .first::<NaiveDateTime>(conn)
+                    .optional()?;
+            if let Some(tags) = &post.tags {
+                if let Err(message) = normalize_tag_names(tags) {
+                    return Ok(ImportAction::Conflict(message));
+                }
+            }
+
+            let action = update_or_conflict(existing, record);
+            if let ImportAction::Create | ImportAction::Update = action {
+                if let Err(message) = post_publication(post, matches!(action, ImportAction::Create)) {
+                    return Ok(ImportAction::Conflict(message));
+                }
+            }
+            // A slug is only taken over when the post is created
+            if let (ImportAction::Create, Some(slug)) = (&action, &post.slug) {
+                if slugify(slug) != *slug || slug.is_empty() {
+                    return Ok(ImportAction::Conflict(format!("'{}' is not a valid slug", slug)));
+                }
+                if slug_taken(conn, slug, Some(&post.post_id))? || !claimed_slugs.insert(slug.clone()) {
+                    return Ok(ImportAction::Conflict(format!("The slug '{}' is used by another post", slug)));
+                }
+            }
+            Ok(action)
+        }
+        ContentRecord::Skill(skill) => {
+            let existing =
+                skills::table
+                    .filter(skills::skill_id.eq(&skill.skill_id))
+                    .filter(skills::deleted_at.is_null())
+                    .select(skills::updated_at)
+                    .first::<NaiveDateTime>(conn)
+                    .optional()?;
+            Ok(update_or_conflict(existing, record))
+        }
+        ContentRecord::Worklog(entry) => {
+            let existing =
+                worklog::table
+                    .filter(worklog::worklog_id.eq(&entry.worklog_id))
+                    .filter(worklog::deleted_at.is_null())
+                    .select(worklog::updated_at)
+                    .first::<NaiveDateTime>(conn)
+                    .optional()?;
+            Ok(update_or_conflict(existing, record))
+        }
+    }
+}
+
+fn apply_post(conn: &mut PgConnection, post: &PostRecord, author_id: Option<&str>, create: bool) -> QueryResult<()> {
+    // Already checked while planning
+    let publication = post_publication(post, create).unwrap_or_default();
+    if create {
+        let (status, publish_at) = publication.unwrap_or((PostStatus::Draft, None));
+        let slug = match &post.slug {
+            Some(slug) => slug.clone(),
+            None => unique_slug(conn, &post.title, None)?,
+        };
+        diesel::insert_into(posts::table)
+            .values(&NewPost {
+                post_id: post.post_id.clone(),
+                title: post.title.clone(),
+                body: post.body.clone(),
+                created_at: post.created_at,
+                updated_at: post.updated_at,
+                status,
+                publish_at,
+                slug,
+                author_id: author_id.map(str::to_string),
+            })
+            .execute(conn)?;
+    } else {
+        let live = || posts::table.filter(posts::post_id.eq(&post.post_id)).filter(posts::deleted_at.is_null());
+        diesel::update(live())
+            .set((
+                posts::title.eq(&post.title),
+                posts::body.eq(&post.body),
+                posts::created_at.eq(post.created_at),
+                posts::updated_at.eq(post.updated_at),
+            ))
+            .execute(conn)?;
+        // Like an update through the API, status and publish time are only touched when the record has them
+        if let Some((status, publish_at)) = publication {
+            diesel::update(live()).set((posts::status.eq(status), posts::publish_at.eq(publish_at))).execute(conn)?;
+        }
+        refresh_post_slug(conn, &post.post_id)?;
+    }
+
+    if let Some(tags) = &post.tags {
+        // Already checked while planning
+        let labels = normalize_tag_names(tags).unwrap_or_default();
+        set_post_tags(conn, &post.post_id, &labels)?;
+    }
+    snapshot_post(conn, &post.post_id, author_id.map(str::to_string))?;
+    Ok(())
+}
+
+fn apply_record(conn: &mut PgConnection, record: &ContentRecord, author_id: Option<&str>, create: bool) -> QueryResult<()> {
+    match record {
+        ContentRecord::Post(post) => apply_post(conn, post, author_id, create),
+        ContentRecord::Skill(skill) if create => diesel::insert_into(skills::table)
+            .values(&NewSkill {
+                skill_id: skill.skill_id.clone(),
+                skill_name: skill.skill_name.clone(),
+                body: skill.body.clone(),
+                created_at: skill.created_at,
+                updated_at: skill.updated_at,
+                author_id: author_id.map(str::to_string),
+            })
+            .execute(conn)
+            .map(|_| ()),
+        ContentRecord::Skill(skill) => diesel::update(skills::table.filter(skills::skill_id.eq(&skill.skill_id)).filter(skills::deleted_at.is_null()))
+            .set((
+                skills::skill_name.eq(&skill.skill_name),
+                skills::body.eq(&skill.body),
+                skills::created_at.eq(skill.created_at),
+                skills::updated_at.eq(skill.updated_at),
+            ))
+            .execute(conn)
+            .map(|_| ()),
+        ContentRecord::Worklog(entry) if create => diesel::insert_into(worklog::table)
+            .values(&NewWorklog {
+                worklog_id: entry.worklog_id.clone(),
+                work_title: entry.work_title.clone(),
+                body: entry.body.clone(),
+                created_at: entry.created_at,
+                updated_at: entry.updated_at,
+                author_id: author_id.map(str::to_string),
+            })
+            .execute(conn)
+            .map(|_| ()),
+        ContentRecord::Worklog(entry) => diesel::update(worklog::table.filter(worklog::worklog_id.eq(&entry.worklog_id)).filter(worklog::deleted_at.is_null()))
+            .set((
+                worklog::work_title.eq(&entry.work_title),
+                worklog::body.eq(&entry.body),
+                worklog::created_at.eq(entry.created_at),
+                worklog::updated_at.eq(entry.updated_at),
+            ))
+            .execute(conn)
+            .map(|_| ()),
+    }
+}
+
+// Imported items reach webhooks like any other write
+fn enqueue_record_event(conn: &mut PgConnection, record: &ContentRecord, create: bool) -> QueryResult<i32> {
+    match (record, create) {
+        (ContentRecord::Post(post), true) => enqueue_post_event(conn, WebhookEvent::PostCreated, &post.post_id),
+        (ContentRecord::Post(post), false) => enqueue_post_event(conn, WebhookEvent::PostUpdated, &post.post_id),
+        (ContentRecord::Skill(skill), true) => enqueue_skill_event(conn, WebhookEvent::SkillCreated, &skill.skill_id),
+        (ContentRecord::Skill(skill), false) => enqueue_skill_event(conn, WebhookEvent::SkillUpdated, &skill.skill_id),
+        (ContentRecord::Worklog(entry), true) => enqueue_worklog_event(conn, WebhookEvent::WorklogCreated, &entry.worklog_id),
+        (ContentRecord::Worklog(entry), false) => enqueue_worklog_event(conn, WebhookEvent::WorklogUpdated, &entry.worklog_id),
+    }
+}
+
+// Upserts every record by its post_id, skill_id or worklog_id in a single transaction. Created items are credited to `author_id`.
+// With `dry_run`, or when any record conflicts, nothing is written and the report says what would have happened.
+pub fn import_records(conn: &mut PgConnection, records: &[ContentRecord], author_id: Option<&str>, dry_run: bool) -> QueryResult<ImportReport> {
+    let mut report = ImportReport { dry_run, ..Default::default() };
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let mut seen: HashSet<(&str, &str)> = HashSet::new();
+        let mut claimed_slugs: HashSet<String> = HashSet::new();
+
+        for record in records {
+            let item = ImportItem { kind: record.kind().to_string(), id: record.id().to_string() };
+
+            let action =
+                if seen.insert((record.kind(), record.id())) {
+                    plan_record(conn, record, &mut claimed_slugs)?
+                } else {
+                    ImportAction::Conflict("It appears more than once in the import".to_string())
+                };
+
+            // After the first conflict the rest is only planned, the transaction is rolled back anyway
+            let apply = !dry_run && report.conflicts.is_empty();
+            match action {
+                ImportAction::Create => {
+                    if apply {
+                        apply_record(conn, record, author_id, true)?;
+                        enqueue_record_event(conn, record, true)?;
+                    }
+                    report.created.push(item);
+                }
+                ImportAction::Update => {
+                    if apply {
+                        apply_record(conn, record, author_id, false)?;
+                        enqueue_record_event(conn, record, false)?;
+                    }
+                    report.updated.push(item);
+                }
+                ImportAction::Conflict(reason) => report.conflicts.push(ImportConflict { kind: item.kind, id: item.id, reason }),
+            }
+        }
+
+        if dry_run || !report.conflicts.is_empty() {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => {
+            report.applied = true;
+            Ok(report)
+        }
+        Err(diesel::result::Error::RollbackTransaction) => Ok(report),
+        Err(e) => Err(e),
+    }
+}
+
+fn max_import_bytes() -> usize {
+    env::var("IMPORT_MAX_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_IMPORT_BYTES)
+}
+
+// Exports and imports cover drafts and rewrite content, so they need a logged in admin or editor
+async fn require_editor(req: &HttpRequest) -> Result<SessionData, HttpResponse> {
+    match session_from_request(req).await {
+        Some(session) if session.is_editor_or_admin() => Ok(session),
+        Some(_) => Err(HttpResponse::Forbidden().json(json!({ "error": "Only admins and editors can import and export content" }))),
+        None => Err(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+}
+
+#[get("/export")]
+async fn export_content(
+    pool: web::Data<DbPool>,
+    query: web::Query<ExportQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = require_editor(&req).await {
+        return Ok(response);
+    }
+
+    let (content_type, file_name) =
+        match query.format {
+            TransferFormat::Ndjson => ("application/x-ndjson", "tarnish-export.ndjson"),
+            TransferFormat::Markdown => ("application/x-tar", "tarnish-export.tar"),
+        };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, content_type))
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name.to_string())],
+        })
+        .streaming(export_stream(pool.get_ref().clone(), query.format)))
+}
+
+#[post("/import")]
+async fn import_content(
+    pool: web::Data<DbPool>,
+    query: web::Query<ImportQuery>,
+    mut payload: web::Payload,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let session = match require_editor(&req).await {
+        Ok(session) => session,
+        Err(response) => return Ok(response),
+    };
+
+    let max_bytes = max_import_bytes();
+    let mut data = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if data.len() + chunk.len() > max_bytes {
+            return Ok(HttpResponse::PayloadTooLarge().json(json!({
+                "error": format!("Imports are limited to {} bytes", max_bytes)
+            })));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let records =
+        match query.format {
+            TransferFormat::Ndjson => parse_ndjson(&data),
+            TransferFormat::Markdown => parse_markdown_tar(&data),
+        };
+    let records = match records {
+        Ok(records) => records,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match import_records(&mut conn, &records, Some(&session.user_id), query.dry_run) {
+        Ok(report) if report.applied => {
+            for kind in [CacheKind::Posts, CacheKind::Skills, CacheKind::Worklog] {
+                invalidate_cached(&req, kind).await;
+            }
+            for (items, action) in [(&report.created, LiveAction::Created), (&report.updated, LiveAction::Updated)] {
+                for item in items {
+                    let kind =
+                        match item.kind.as_str() {
+                            "post" => LiveKind::Post,
+                            "skill" => LiveKind::Skill,
+                            _ => LiveKind::Worklog,
+                        };
+                    publish_live(&req, kind, action, &item.id).await;
+                }
+            }
+            Ok(HttpResponse::Ok().json(report))
+        }
+        Ok(report) if report.conflicts.is_empty() => Ok(HttpResponse::Ok().json(report)),
+        Ok(report) => Ok(HttpResponse::Conflict().json(report)),
+        Err(e) => {
+            eprintln!("Error importing content: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skill_record() -> ContentRecord {
+        let created_at = NaiveDateTime::parse_from_str("2024-08-29 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        ContentRecord::Skill(SkillRecord {
+            skill_id: "Rust / Actix".to_string(),
+            skill_name: "Rust".to_string(),
+            created_at,
+            updated_at: created_at,
+            body: "Fast and *safe*.\n".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_parse_ndjson() {
+        let records = parse_ndjson(b"{\"type\":\"worklog\",\"worklog_id\":\"w1\",\"work_title\":\"Ship\",\"created_at\":\"2024-08-29T14:00:00Z\",\"updated_at\":\"2024-08-29T14:00:00\",\"body\":\"\"}\n\n").unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id(), "w1");
+
+        let error = parse_ndjson(b"{\"type\":\"worklog\"}\n{\"type\":\"comment\"}").unwrap_err();
+        assert!(error.starts_with("Line 1:"));
+    }
+
+    #[test]
+    fn test_markdown_tar_round_trip() {
+        let record = skill_record();
+        let mut file_names = HashSet::new();
+        let mut archive = encode_batch(&[(1, record.clone()), (2, record.clone())], TransferFormat::Markdown, &mut file_names).unwrap();
+        archive.extend(tar_end());
+
+        // Ids are turned into safe, unique file names
+        let names: Vec<String> = read_tar(&archive).unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["skills/rust-actix.md", "skills/rust-actix-2.md"]);
+
+        assert!(record_to_markdown(&record).starts_with("---\nskill_id: Rust / Actix\nskill_name: Rust\n"));
+        assert_eq!(parse_markdown_tar(&archive).unwrap(), vec![record.clone(), record]);
+
+        let mut stray = tar_entry("notes/readme.md", b"---\ntitle: x\n---\n", 0);
+        stray.extend(tar_end());
+        assert!(parse_markdown_tar(&stray).unwrap_err().starts_with("notes/readme.md"));
+    }
+}
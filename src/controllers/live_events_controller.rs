@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use futures::{Stream, StreamExt};
+use serde_json::json;
+
+use crate::connectors::live_events::{last_event_id, LiveEvents};
+use crate::controllers::login_controller::session_from_request;
+
+// A comment line every so often keeps proxies and load balancers from closing a quiet stream
+const KEEP_ALIVE_SECONDS: u64 = 15;
+// How long browsers wait before reconnecting after the stream drops
+const RETRY_MILLISECONDS: u64 = 3000;
+
+// Interleaves `frames` with a keep-alive comment every `period`. It ends along with `frames`, so when the
+// Redis subscription drops the response ends too and the browser reconnects, rather than getting keep-alives forever.
+pub fn with_keep_alive(frames: impl Stream<Item = String>, period: Duration) -> impl Stream<Item = String> {
+    let frames = frames.map(Some).chain(futures::stream::once(async { None }));
+    let keep_alive =
+        futures::stream::unfold(
+            tokio::time::interval_at(tokio::time::Instant::now() + period, period),
+            |mut ticker| async move {
+                ticker.tick().await;
+                Some((Some(": keep-alive\n\n".to_string()), ticker))
+            },
+        );
+
+    futures::stream::select(frames, keep_alive)
+        .take_while(|frame| std::future::ready(frame.is_some()))
+        .filter_map(std::future::ready)
+}
+
+// Server-Sent Events for every change to posts, comments, skills and worklog, for the admin dashboard.
+// Reconnecting clients send Last-Event-ID and get what they missed first.
+#[get("/events")]
+async fn get_live_events(
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    match session_from_request(&req).await {
+        Some(session) if session.is_editor_or_admin() => {}
+        Some(_) => return Ok(HttpResponse::Forbidden().json(json!({ "error": "Only admins and editors can follow live events" }))),
+        None => return Ok(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+
+    let Some(live_events) = req.app_data::<web::Data<LiveEvents>>() else {
+        return Ok(HttpResponse::ServiceUnavailable().json(json!({ "error": "Live events are not set up" })));
+    };
+
+    let events = match live_events.subscribe(last_event_id(&req)).await {
+        Ok(events) => events,
+        Err(e) => {
+            log::warn!("Failed to subscribe to live events: {:?}", e);
+            return Ok(HttpResponse::ServiceUnavailable().json(json!({ "error": "Live events are unavailable" })));
+        }
+    };
+
+    let frames = events.map(|event| event.to_frame());
+    let stream =
+        futures::stream::once(async { format!("retry: {}\n\n", RETRY_MILLISECONDS) })
+            .chain(with_keep_alive(frames, Duration::from_secs(KEEP_ALIVE_SECONDS)))
+            .map(|frame| Ok::<_, Error>(web::Bytes::from(frame)));
+
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        // Sets its own Cache-Control, so the caching middleware leaves the stream alone
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_with_keep_alive_ends_with_the_events() {
+        let frames = futures::stream::iter(vec!["one".to_string(), "two".to_string()]);
+        let sent: Vec<String> = with_keep_alive(frames, Duration::from_secs(60)).collect().await;
+        assert_eq!(sent, vec!["one", "two"]);
+
+        // A quiet stream gets keep-alives until its events run out
+        let quiet = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(120)).await;
+            "late".to_string()
+        });
+        let sent: Vec<String> = with_keep_alive(quiet, Duration::from_millis(50)).collect().await;
+        let (last, earlier) = sent.split_last().unwrap();
+        assert_eq!(last, "late");
+        assert!(!earlier.is_empty() && earlier.iter().all(|frame| frame == ": keep-alive\n\n"));
+    }
+}
@@ -111,6 +111,29 @@ async fn check_user_session(
     }
 }
 
+// Looks up the logged in user behind a request, anonymous requests and expired sessions give None
+pub async fn session_from_request(req: &HttpRequest) -> Option<SessionData> {
+    let session_id = req.cookie("session_id")?.value().to_string();
+    #[cfg(test)]
+    if let Some(sessions) = req.app_data::<web::Data<TestSessions>>() {
+        return sessions.lookup(&session_id);
+    }
+    let redis_client = req.app_data::<web::Data<redis::Client>>()?.clone();
+
+    check_user_session(redis_client, &session_id).await.ok()
+}
+
+// Sessions for handler tests, which run without Redis: session_id -> role
+#[cfg(test)]
+pub struct TestSessions(pub std::collections::HashMap<String, String>);
+
+#[cfg(test)]
+impl TestSessions {
+    pub fn lookup(&self, session_id: &str) -> Option<SessionData> {
+        self.0.get(session_id).map(|role| SessionData { user_id: session_id.to_string(), role: role.clone() })
+    }
+}
+
 use crate::connectors::postgres_connector::DbPool;
 use crate::models::LoginRequest::LoginRequest;
 use crate::models::LogoutResponse::LogoutResponse;
@@ -0,0 +1,424 @@
+use std::env;
+
+use actix_multipart::Multipart;
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use futures_util::TryStreamExt;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::connectors::media_store::{MediaStore, MediaStoreError};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::login_controller::session_from_request;
+use crate::schemas::media_schema::media;
+use crate::table_models::media_models::{Media, NewMedia};
+
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 100 * 1024 * 1024;
+
+// Which part of a file a download asks for, from the Range header
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    Full,
+    // Inclusive on both ends, like the header itself
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+// Supports a single `bytes=start-end`, `bytes=start-` or `bytes=-suffix` range.
+// Anything else is ignored and the whole file is served, which the spec allows.
+pub fn parse_range(range_header: Option<&str>, size: u64) -> ByteRange {
+    let spec = match range_header.and_then(|value| value.trim().strip_prefix("bytes=")) {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        Some(bounds) => bounds,
+        None => return ByteRange::Full,
+    };
+
+    if start.is_empty() {
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial { start: size.saturating_sub(suffix), end: size - 1 },
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let start = match start.parse::<u64>() {
+        Ok(start) => start,
+        Err(_) => return ByteRange::Full,
+    };
+    let end = match end {
+        "" => None,
+        end => match end.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end: end.unwrap_or(size - 1).min(size - 1) }
+}
+
+// Only images, video and audio are shown in the browser. Anything else, SVG and HTML included, could run script
+// on our origin, so it is downloaded instead.
+pub fn disposition_for(content_type: &str) -> DispositionType {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    let inline =
+        match essence.split_once('/') {
+            Some(("image", subtype)) => !subtype.starts_with("svg"),
+            Some(("video", _)) | Some(("audio", _)) => true,
+            _ => false,
+        };
+    if inline { DispositionType::Inline } else { DispositionType::Attachment }
+}
+
+fn max_upload_bytes() -> usize {
+    env::var("MEDIA_MAX_UPLOAD_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES)
+}
+
+// Writes the file to the store and records it in the media table.
+// If the row can't be written the stored file is removed again.
+pub fn save_media(
+    conn: &mut PgConnection,
+    store: &dyn MediaStore,
+    file_name: &str,
+    content_type: &str,
+    data: &[u8],
+    owner_id: Option<String>,
+) -> Result<Media, Box<dyn std::error::Error + Send + Sync>> {
+    let new_media =
+        NewMedia {
+            media_id: Uuid::new_v4().to_string(),
+            file_name: file_name.to_string(),
+            content_type: content_type.to_string(),
+            size_bytes: data.len() as i64,
+            checksum_sha256: format!("{:x}", Sha256::digest(data)),
+            owner_id,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+    store.put(&new_media.media_id, data)?;
+
+    diesel::insert_into(media::table)
+        .values(&new_media)
+        .returning(Media::as_returning())
+        .get_result(conn)
+        .map_err(|e| {
+            if let Err(cleanup_error) = store.delete(&new_media.media_id) {
+                eprintln!("Error removing stored media after failed insert: {:?}", cleanup_error);
+            }
+            e.into()
+        })
+}
+
+// Expects a multipart form with the upload in a field named "file"
+#[post("/media/upload")]
+async fn upload_media(
+    mut payload: Multipart,
+    pool: web::Data<DbPool>,
+    store: web::Data<dyn MediaStore>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let owner_id =
+        match session_from_request(&req).await {
+            Some(session) if session.is_editor_or_admin() => session.user_id,
+            Some(_) => return Ok(HttpResponse::Forbidden().json(json!({ "error": "Only admins and editors can upload media" }))),
+            None => return Ok(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+        };
+
+    let max_bytes = max_upload_bytes();
+    let mut upload: Option<(String, String, Vec<u8>)> = None;
+
+    while let Some(mut field) = payload.try_next().await? {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let file_name =
+            field
+                .content_disposition()
+                .and_then(|disposition| disposition.get_filename())
+                .unwrap_or("upload")
+                .to_string();
+        let content_type =
+            field
+                .content_type()
+                .map(|mime| mime.to_string())
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await? {
+            if data.len() + chunk.len() > max_bytes {
+                return Ok(HttpResponse::PayloadTooLarge().json(json!({
+                    "error": format!("Uploads are limited to {} bytes", max_bytes)
+                })));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        upload = Some((file_name, content_type, data));
+    }
+
+    let (file_name, content_type, data) = match upload {
+        Some(upload) => upload,
+        None => return Ok(HttpResponse::BadRequest().json(json!({ "error": "Expected a multipart field named 'file'" }))),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let saved =
+        web::block(move || save_media(&mut conn, store.get_ref(), &file_name, &content_type, &data, Some(owner_id)))
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Upload failed: {}", e)))?;
+
+    match saved {
+        Ok(media) => Ok(HttpResponse::Created().json(media)),
+        Err(e) => {
+            eprintln!("Error saving media: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Serves a stored file, honouring a Range header so video players can seek
+#[get("/media/{media_id}")]
+async fn download_media(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    store: web::Data<dyn MediaStore>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let media_id = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let stored_media =
+        media::table
+            .filter(media::media_id.eq(&media_id))
+            .select(Media::as_select())
+            .first(&mut conn)
+            .optional()
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving media: {}", e)))?;
+
+    let stored_media = match stored_media {
+        Some(stored_media) => stored_media,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": format!("Media '{}' not found", media_id)
+            })))
+        }
+    };
+
+    let size = stored_media.size_bytes as u64;
+    let range_header = req.headers().get(header::RANGE).and_then(|value| value.to_str().ok());
+
+    let (offset, length, content_range) =
+        match parse_range(range_header, size) {
+            ByteRange::Full => (0, size, None),
+            ByteRange::Partial { start, end } => (start, end - start + 1, Some(format!("bytes {}-{}/{}", start, end, size))),
+            ByteRange::Unsatisfiable => {
+                return Ok(HttpResponse::RangeNotSatisfiable()
+                    .insert_header((header::CONTENT_RANGE, format!("bytes */{}", size)))
+                    .finish())
+            }
+        };
+
+    let key = stored_media.media_id.clone();
+    let data =
+        web::block(move || store.get_range(&key, offset, length))
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Download failed: {}", e)))?;
+
+    let data = match data {
+        Ok(data) => data,
+        Err(MediaStoreError::NotFound) => {
+            eprintln!("Media '{}' is in the database but missing from the store", media_id);
+            return Ok(HttpResponse::NotFound().finish());
+        }
+        Err(e) => {
+            eprintln!("Error reading media: {:?}", e);
+            return Ok(HttpResponse::InternalServerError().finish());
+        }
+    };
+
+    let mut response =
+        match &content_range {
+            Some(_) => HttpResponse::PartialContent(),
+            None => HttpResponse::Ok(),
+        };
+    response
+        .content_type(stored_media.content_type.as_str())
+        .insert_header((header::ACCEPT_RANGES, "bytes"))
+        .insert_header((header::ETAG, format!("\"{}\"", stored_media.checksum_sha256)))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header(ContentDisposition {
+            disposition: disposition_for(&stored_media.content_type),
+            parameters: vec![DispositionParam::Filename(stored_media.file_name.clone())],
+        });
+    if let Some(content_range) = content_range {
+        response.insert_header((header::CONTENT_RANGE, content_range));
+    }
+
+    Ok(response.body(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), ByteRange::Partial { start: 0, end: 9 });
+        assert_eq!(parse_range(Some("bytes=90-"), 100), ByteRange::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=-10"), 100), ByteRange::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), ByteRange::Partial { start: 50, end: 99 });
+        assert_eq!(parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        // Multiple ranges, other units and nonsense fall back to the whole file
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-2"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_disposition_for() {
+        assert_eq!(disposition_for("image/png"), DispositionType::Inline);
+        assert_eq!(disposition_for("video/mp4"), DispositionType::Inline);
+        assert_eq!(disposition_for("Audio/MPEG"), DispositionType::Inline);
+        assert_eq!(disposition_for("image/svg+xml"), DispositionType::Attachment);
+        assert_eq!(disposition_for("text/html; charset=utf-8"), DispositionType::Attachment);
+        assert_eq!(disposition_for("application/octet-stream"), DispositionType::Attachment);
+        assert_eq!(disposition_for(""), DispositionType::Attachment);
+    }
+}
+
+#[cfg(test)]
+mod media_controller_spec {
+    use std::sync::Arc;
+
+    use crate::connectors::media_store::{InMemoryMediaStore, MediaStore, MediaStoreError, MockMediaStore};
+    use crate::connectors::postgres_connector::DbPool;
+    use crate::controllers::media_controller::*;
+    use actix_web::{http::StatusCode, test, web, App};
+    use diesel::r2d2::ConnectionManager;
+    use diesel::{r2d2, PgConnection};
+    use dotenv::dotenv;
+
+    pub fn establish_connection() -> DbPool {
+        dotenv().ok();
+        let database_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        r2d2::Pool::builder().build(manager).expect("Failed to create pool.")
+    }
+
+    #[actix_rt::test]
+    async fn test_download_media_with_ranges() {
+        let pool = web::Data::new(establish_connection());
+        let store: Arc<dyn MediaStore> = Arc::new(InMemoryMediaStore::default());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .app_data(web::Data::from(store.clone()))
+                .service(upload_media)
+                .service(download_media),
+        )
+            .await;
+
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+        let saved =
+            save_media(&mut conn, store.as_ref(), "clip.mp4", "video/mp4", b"0123456789", Some("media_spec".to_string()))
+                .expect("Failed to save media");
+        assert_eq!(saved.size_bytes, 10);
+        assert_eq!(saved.checksum_sha256, "84d89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f7882");
+
+        let req = test::TestRequest::get().uri(&format!("/media/{}", saved.media_id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "video/mp4");
+        assert_eq!(resp.headers().get("accept-ranges").unwrap(), "bytes");
+        assert_eq!(resp.headers().get("x-content-type-options").unwrap(), "nosniff");
+        assert!(resp.headers().get("content-disposition").unwrap().to_str().unwrap().starts_with("inline"));
+        assert_eq!(test::read_body(resp).await, "0123456789");
+
+        // Uploaded HTML could run script on our origin, so it is only ever downloaded
+        let page =
+            save_media(&mut conn, store.as_ref(), "page.html", "text/html", b"<script>alert(1)</script>", Some("media_spec".to_string()))
+                .expect("Failed to save media");
+        let req = test::TestRequest::get().uri(&format!("/media/{}", page.media_id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-content-type-options").unwrap(), "nosniff");
+        assert!(resp.headers().get("content-disposition").unwrap().to_str().unwrap().starts_with("attachment"));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/media/{}", saved.media_id))
+            .insert_header(("Range", "bytes=2-5"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers().get("content-range").unwrap(), "bytes 2-5/10");
+        assert_eq!(test::read_body(resp).await, "2345");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/media/{}", saved.media_id))
+            .insert_header(("Range", "bytes=10-"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers().get("content-range").unwrap(), "bytes */10");
+
+        let req = test::TestRequest::get().uri("/media/does-not-exist").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Uploading needs a logged in admin or editor
+        let req = test::TestRequest::post()
+            .uri("/media/upload")
+            .insert_header(("Content-Type", "multipart/form-data; boundary=spec"))
+            .set_payload("--spec--\r\n")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        diesel::delete(media::table.filter(media::owner_id.eq("media_spec")))
+            .execute(&mut conn)
+            .expect("Failed to delete test media");
+    }
+
+    #[actix_rt::test]
+    async fn test_save_media_skips_the_row_when_the_store_fails() {
+        let pool = establish_connection();
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+
+        let mut store = MockMediaStore::new();
+        store
+            .expect_put()
+            .returning(|_, _| Err(MediaStoreError::Io(std::io::Error::other("disk full"))));
+
+        let result = save_media(&mut conn, &store, "broken.png", "image/png", b"png", Some("media_spec_failure".to_string()));
+        assert!(result.is_err());
+
+        let saved_rows =
+            media::table
+                .filter(media::owner_id.eq("media_spec_failure"))
+                .count()
+                .get_result::<i64>(&mut conn)
+                .expect("Failed to count media");
+        assert_eq!(saved_rows, 0);
+    }
+}
@@ -1,7 +1,26 @@
+pub mod analytics_controller;
 pub mod auth_handler;
+pub mod author_controller;
 pub mod blog_controller;
+pub mod cache_controller;
+pub mod comment_controller;
+pub mod feed_controller;
+pub mod import_export_controller;
+pub mod live_events_controller;
 pub mod login_controller;
+pub mod media_controller;
+pub mod post_revision_controller;
+pub mod reaction_controller;
+pub mod related_controller;
 pub mod skills_controller;
+pub mod slug_controller;
+pub mod stats_controller;
+pub mod tag_controller;
+pub mod trash_controller;
+pub mod translation_controller;
+pub mod webhook_controller;
 pub mod worklog_controller;
 pub mod register_user_controller;
+pub mod search_controller;
+pub mod series_controller;
 pub mod validate_user_controller;
@@ -0,0 +1,270 @@
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use similar::{ChangeTag, TextDiff};
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::enqueue_post_event;
+use crate::controllers::login_controller::session_from_request;
+use crate::controllers::slug_controller::refresh_post_slug;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::post_revision_schema::post_revisions;
+use crate::table_models::post_revision_models::{NewPostRevision, PostRevision};
+
+// Revision metadata returned when listing, the full content is fetched one revision at a time
+#[derive(Queryable, Serialize, Deserialize)]
+pub struct RevisionSummary {
+    pub revision_number: i32,
+    pub title: String,
+    pub author_id: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+// Query string for the diff endpoint, e.g. ?from=1&to=3
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DiffLine {
+    pub tag: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub content: String,
+}
+
+// Line by line diff of two bodies, tags are "equal", "insert" or "delete"
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            tag: match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            }
+                .to_string(),
+            old_line: change.old_index().map(|index| index + 1),
+            new_line: change.new_index().map(|index| index + 1),
+            content: change.value().trim_end_matches('\n').to_string(),
+        })
+        .collect()
+}
+
+// Copies the current title and body of a post into a new revision row.
+// Callers run this inside the same transaction as the write that changed the post.
+pub fn snapshot_post(
+    conn: &mut PgConnection,
+    post_id: &str,
+    author_id: Option<String>,
+) -> QueryResult<PostRevision> {
+    let (title, body) =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .filter(posts::deleted_at.is_null())
+            .select((posts::title, posts::body))
+            .first::<(String, String)>(conn)?;
+
+    let latest_revision =
+        post_revisions::table
+            .filter(post_revisions::post_id.eq(post_id))
+            .select(diesel::dsl::max(post_revisions::revision_number))
+            .first::<Option<i32>>(conn)?;
+
+    let new_revision =
+        NewPostRevision {
+            post_id: post_id.to_string(),
+            revision_number: latest_revision.unwrap_or(0) + 1,
+            title,
+            body,
+            author_id,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+    diesel::insert_into(post_revisions::table)
+        .values(&new_revision)
+        .returning(PostRevision::as_returning())
+        .get_result(conn)
+}
+
+// Posts written before revisions existed have no history yet, so keep their current text before it is overwritten
+pub fn ensure_initial_revision(conn: &mut PgConnection, post_id: &str) -> QueryResult<()> {
+    let revision_count =
+        post_revisions::table
+            .filter(post_revisions::post_id.eq(post_id))
+            .count()
+            .get_result::<i64>(conn)?;
+
+    if revision_count == 0 {
+        snapshot_post(conn, post_id, None)?;
+    }
+    Ok(())
+}
+
+fn find_revision(conn: &mut PgConnection, post_id: &str, revision_number: i32) -> QueryResult<Option<PostRevision>> {
+    post_revisions::table
+        .filter(post_revisions::post_id.eq(post_id))
+        .filter(post_revisions::revision_number.eq(revision_number))
+        .select(PostRevision::as_select())
+        .first(conn)
+        .optional()
+}
+
+fn revision_not_found(post_id: &str, revision_number: i32) -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("application/json")
+        .json(json!({
+            "error": format!("Revision {} of blog post '{}' not found", revision_number, post_id)
+        }))
+}
+
+#[get("/blog/post/{post_id}/revisions")]
+async fn get_post_revisions(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match post_revisions::table
+        .filter(post_revisions::post_id.eq(&post_id))
+        .order(post_revisions::revision_number.desc())
+        .select((
+            post_revisions::revision_number,
+            post_revisions::title,
+            post_revisions::author_id,
+            post_revisions::created_at,
+        ))
+        .load::<RevisionSummary>(&mut conn)
+    {
+        Ok(revisions) => Ok(HttpResponse::Ok().json(revisions)),
+        Err(e) => {
+            eprintln!("Error loading revisions: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Registered before get_post_revision so "diff" is not read as a revision number
+#[get("/blog/post/{post_id}/revisions/diff")]
+async fn diff_post_revisions(
+    path: web::Path<String>,
+    query: web::Query<DiffQuery>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let from = find_revision(&mut conn, &post_id, query.from)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving revision: {}", e)))?;
+    let to = find_revision(&mut conn, &post_id, query.to)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving revision: {}", e)))?;
+
+    match (from, to) {
+        (Some(from), Some(to)) => {
+            let response_body = json!({
+                "post_id": post_id,
+                "from": from.revision_number,
+                "to": to.revision_number,
+                "title": { "from": from.title, "to": to.title },
+                "lines": diff_lines(&from.body, &to.body),
+            });
+            Ok(HttpResponse::Ok().json(response_body))
+        }
+        (None, _) => Ok(revision_not_found(&post_id, query.from)),
+        (_, None) => Ok(revision_not_found(&post_id, query.to)),
+    }
+}
+
+#[get("/blog/post/{post_id}/revisions/{revision_number}")]
+async fn get_post_revision(
+    path: web::Path<(String, i32)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (post_id, revision_number) = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match find_revision(&mut conn, &post_id, revision_number) {
+        Ok(Some(revision)) => Ok(HttpResponse::Ok().json(revision)),
+        Ok(None) => Ok(revision_not_found(&post_id, revision_number)),
+        Err(e) => {
+            eprintln!("Error retrieving revision: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[post("/blog/post/{post_id}/revisions/{revision_number}/restore")]
+async fn restore_post_revision(
+    path: web::Path<(String, i32)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (post_id, revision_number) = path.into_inner();
+    let author_id = session_from_request(&req).await.map(|session| session.user_id);
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let revision =
+        find_revision(&mut conn, &post_id, revision_number)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving revision: {}", e)))?;
+
+    let revision = match revision {
+        Some(revision) => revision,
+        None => return Ok(revision_not_found(&post_id, revision_number)),
+    };
+
+    // Restoring is an update like any other, so it gets its own revision on top of the history
+    let new_revision =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(posts::table.filter(posts::post_id.eq(&post_id)).filter(posts::deleted_at.is_null()))
+                .set((
+                    posts::title.eq(&revision.title),
+                    posts::body.eq(&revision.body),
+                    posts::updated_at.eq(chrono::Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+
+            refresh_post_slug(conn, &post_id)?;
+            enqueue_post_event(conn, WebhookEvent::PostUpdated, &post_id)?;
+            snapshot_post(conn, &post_id, author_id)
+        }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))?;
+
+    invalidate_cached(&req, CacheKind::Posts).await;
+    publish_live(&req, LiveKind::Post, LiveAction::Updated, &post_id).await;
+    Ok(HttpResponse::Ok().json(json!({
+        "message": format!("Blog post '{}' has been restored to revision {}", post_id, revision_number),
+        "revision_number": new_revision.revision_number,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines_marks_inserts_and_deletes() {
+        let diff = diff_lines("first\nsecond\nthird\n", "first\nchanged\nthird\nfourth\n");
+
+        let tags: Vec<&str> = diff.iter().map(|line| line.tag.as_str()).collect();
+        assert_eq!(tags, vec!["equal", "delete", "insert", "equal", "insert"]);
+
+        assert_eq!(diff[1], DiffLine { tag: "delete".to_string(), old_line: Some(2), new_line: None, content: "second".to_string() });
+        assert_eq!(diff[2], DiffLine { tag: "insert".to_string(), old_line: None, new_line: Some(2), content: "changed".to_string() });
+        assert_eq!(diff[4].new_line, Some(4));
+    }
+}
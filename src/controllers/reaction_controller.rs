@@ -0,0 +1,328 @@
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header;
+use actix_web::{delete, get, put, web, Error, HttpRequest, HttpResponse, HttpResponseBuilder};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::login_controller::session_from_request;
+use crate::models::PostStatus::PostStatus;
+use crate::models::Reaction::{Reaction, ReactionCounts};
+use crate::schemas::blog_schema::posts;
+use crate::schemas::post_reaction_schema::post_reactions;
+use crate::table_models::post_reaction_models::NewPostReaction;
+
+// Anonymous readers are told apart by this cookie, handed out with their first reaction
+const READER_COOKIE: &str = "reader_token";
+const MAX_READER_TOKEN_LENGTH: usize = 64;
+const READER_COOKIE_DAYS: i64 = 365;
+
+// Whoever is reacting, a signed-in user or an anonymous reader
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reader {
+    User(String),
+    Token(String),
+}
+
+// Reaction totals of a post, with the reactions the reader asking has left on it
+#[derive(Serialize, Deserialize)]
+pub struct ReactionSummary {
+    pub post_id: String,
+    pub reactions: ReactionCounts,
+    pub mine: Vec<Reaction>,
+}
+
+fn reader_token(req: &HttpRequest) -> Option<String> {
+    req.cookie(READER_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|token| !token.is_empty() && token.len() <= MAX_READER_TOKEN_LENGTH)
+}
+
+async fn reader_from_request(req: &HttpRequest) -> Option<Reader> {
+    match session_from_request(req).await {
+        Some(session) => Some(Reader::User(session.user_id)),
+        None => reader_token(req).map(Reader::Token),
+    }
+}
+
+fn parse_reaction(raw: &str) -> Result<Reaction, HttpResponse> {
+    Reaction::parse(raw.as_bytes()).ok_or_else(|| {
+        HttpResponse::BadRequest().json(json!({
+            "error": format!("Unknown reaction '{}', expected like, love or insightful", raw)
+        }))
+    })
+}
+
+fn post_not_found(post_id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "error": format!("Blog post with ID '{}' not found", post_id)
+    }))
+}
+
+// Moves the post's total for `reaction` by `delta`. The increment happens in the UPDATE itself,
+// so readers reacting at the same time don't overwrite each other's counts.
+fn adjust_reaction_count(conn: &mut PgConnection, post_id: &str, reaction: Reaction, delta: i32) -> QueryResult<usize> {
+    let live_post = posts::table.filter(posts::post_id.eq(post_id)).filter(posts::deleted_at.is_null());
+    match reaction {
+        Reaction::Like => diesel::update(live_post).set(posts::like_count.eq(posts::like_count + delta)).execute(conn),
+        Reaction::Love => diesel::update(live_post).set(posts::love_count.eq(posts::love_count + delta)).execute(conn),
+        Reaction::Insightful => {
+            diesel::update(live_post).set(posts::insightful_count.eq(posts::insightful_count + delta)).execute(conn)
+        }
+    }
+}
+
+// Locks the live post's row until the transaction ends, so it can't be trashed between a reaction
+// changing and its count following. Returns false when there is no live post.
+fn lock_live_post(conn: &mut PgConnection, post_id: &str) -> QueryResult<bool> {
+    let live_post =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .filter(posts::deleted_at.is_null())
+            .select(posts::id)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?;
+    Ok(live_post.is_some())
+}
+
+// Leaves `reaction` on a post for `reader`. Returns false when they had already left it,
+// or the post has gone from under it.
+pub fn add_reaction(conn: &mut PgConnection, post_id: &str, reaction: Reaction, reader: &Reader) -> QueryResult<bool> {
+    let (user_id, reader_token) = match reader {
+        Reader::User(user_id) => (Some(user_id.clone()), None),
+        Reader::Token(token) => (None, Some(token.clone())),
+    };
+    let new_reaction = NewPostReaction {
+        post_id: post_id.to_string(),
+        reaction,
+        user_id,
+        reader_token,
+        created_at: chrono::Utc::now().naive_utc(),
+    };
+
+    conn.transaction(|conn| {
+        if !lock_live_post(conn, post_id)? {
+            return Ok(false);
+        }
+
+        // The unique indexes on post_reactions turn a repeat into a no-op
+        let inserted =
+            diesel::insert_into(post_reactions::table)
+                .values(&new_reaction)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        // A reaction whose count didn't move would leave the two apart, so it doesn't stay either
+        if inserted > 0 && adjust_reaction_count(conn, post_id, reaction, 1)? != 1 {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(inserted > 0)
+    })
+}
+
+// Takes `reader`'s `reaction` off a post. Returns false when they hadn't left it.
+pub fn remove_reaction(conn: &mut PgConnection, post_id: &str, reaction: Reaction, reader: &Reader) -> QueryResult<bool> {
+    conn.transaction(|conn| {
+        // A trashed post's counts are left alone, so its reactions have to stay too or the two drift apart
+        if !lock_live_post(conn, post_id)? {
+            return Ok(false);
+        }
+
+        let left =
+            post_reactions::table
+                .filter(post_reactions::post_id.eq(post_id))
+                .filter(post_reactions::reaction.eq(reaction));
+        let removed = match reader {
+            Reader::User(user_id) => diesel::delete(left.filter(post_reactions::user_id.eq(user_id))).execute(conn)?,
+            Reader::Token(token) => diesel::delete(left.filter(post_reactions::reader_token.eq(token))).execute(conn)?,
+        };
+        if removed > 0 && adjust_reaction_count(conn, post_id, reaction, -1)? != 1 {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(removed > 0)
+    })
+}
+
+// Totals of a published post, or None when there is no such post for readers
+pub fn load_reaction_summary(conn: &mut PgConnection, post_id: &str, reader: Option<&Reader>) -> QueryResult<Option<ReactionSummary>> {
+    let counts =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .filter(posts::status.eq(PostStatus::Published))
+            .filter(posts::deleted_at.is_null())
+            .select((posts::like_count, posts::love_count, posts::insightful_count))
+            .first::<(i32, i32, i32)>(conn)
+            .optional()?;
+    let Some((like, love, insightful)) = counts else { return Ok(None) };
+
+    let mine = match reader {
+        Some(reader) => {
+            let mut query = post_reactions::table.filter(post_reactions::post_id.eq(post_id)).into_boxed();
+            query = match reader {
+                Reader::User(user_id) => query.filter(post_reactions::user_id.eq(user_id)),
+                Reader::Token(token) => query.filter(post_reactions::reader_token.eq(token)),
+            };
+            query.order(post_reactions::reaction.asc()).select(post_reactions::reaction).load::<Reaction>(conn)?
+        }
+        None => Vec::new(),
+    };
+
+    Ok(Some(ReactionSummary {
+        post_id: post_id.to_string(),
+        reactions: ReactionCounts { like, love, insightful },
+        mine,
+    }))
+}
+
+fn published_post_exists(conn: &mut PgConnection, post_id: &str) -> QueryResult<bool> {
+    posts::table
+        .filter(posts::post_id.eq(post_id))
+        .filter(posts::status.eq(PostStatus::Published))
+        .filter(posts::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .map(|count| count > 0)
+}
+
+// The summary differs per reader, so it is never stored by shared caches
+fn summary_response() -> HttpResponseBuilder {
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "private, no-store"));
+    response
+}
+
+#[get("/blog/post/{post_id}/reactions")]
+async fn get_post_reactions(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+    let reader = reader_from_request(&req).await;
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match load_reaction_summary(&mut conn, &post_id, reader.as_ref()) {
+        Ok(Some(summary)) => Ok(summary_response().json(summary)),
+        Ok(None) => Ok(post_not_found(&post_id)),
+        Err(e) => {
+            eprintln!("Error loading reactions: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[put("/blog/post/{post_id}/reactions/{reaction}")]
+async fn add_post_reaction(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (post_id, raw_reaction) = path.into_inner();
+    let reaction = match parse_reaction(&raw_reaction) {
+        Ok(reaction) => reaction,
+        Err(response) => return Ok(response),
+    };
+
+    // Anonymous readers without a token get one with this response
+    let (reader, new_token) = match reader_from_request(&req).await {
+        Some(reader) => (reader, None),
+        None => {
+            let token = Uuid::new_v4().to_string();
+            (Reader::Token(token.clone()), Some(token))
+        }
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    // Only published posts take reactions
+    match published_post_exists(&mut conn, &post_id) {
+        Ok(true) => {}
+        Ok(false) => return Ok(post_not_found(&post_id)),
+        Err(e) => return Err(actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e))),
+    }
+
+    let added = match add_reaction(&mut conn, &post_id, reaction, &reader) {
+        Ok(added) => added,
+        Err(e) => {
+            eprintln!("Error adding reaction: {:?}", e);
+            return Ok(HttpResponse::InternalServerError().finish());
+        }
+    };
+    if added {
+        invalidate_cached(&req, CacheKind::Posts).await;
+    }
+
+    match load_reaction_summary(&mut conn, &post_id, Some(&reader)) {
+        Ok(Some(summary)) => {
+            let mut response = summary_response();
+            if let Some(token) = new_token {
+                response.cookie(
+                    Cookie::build(READER_COOKIE, token)
+                        .path("/")
+                        .http_only(true)
+                        .same_site(SameSite::Lax)
+                        .max_age(time::Duration::days(READER_COOKIE_DAYS))
+                        .finish(),
+                );
+            }
+            Ok(response.json(summary))
+        }
+        Ok(None) => Ok(post_not_found(&post_id)),
+        Err(e) => {
+            eprintln!("Error loading reactions: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[delete("/blog/post/{post_id}/reactions/{reaction}")]
+async fn remove_post_reaction(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (post_id, raw_reaction) = path.into_inner();
+    let reaction = match parse_reaction(&raw_reaction) {
+        Ok(reaction) => reaction,
+        Err(response) => return Ok(response),
+    };
+    let reader = reader_from_request(&req).await;
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match published_post_exists(&mut conn, &post_id) {
+        Ok(true) => {}
+        Ok(false) => return Ok(post_not_found(&post_id)),
+        Err(e) => return Err(actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e))),
+    }
+
+    // A reader without a token has nothing to take back
+    if let Some(reader) = &reader {
+        match remove_reaction(&mut conn, &post_id, reaction, reader) {
+            Ok(true) => invalidate_cached(&req, CacheKind::Posts).await,
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Error removing reaction: {:?}", e);
+                return Ok(HttpResponse::InternalServerError().finish());
+            }
+        }
+    }
+
+    match load_reaction_summary(&mut conn, &post_id, reader.as_ref()) {
+        Ok(Some(summary)) => Ok(summary_response().json(summary)),
+        Ok(None) => Ok(post_not_found(&post_id)),
+        Err(e) => {
+            eprintln!("Error loading reactions: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
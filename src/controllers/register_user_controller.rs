@@ -3,23 +3,21 @@ use crate::table_models::users::{NewUsers, Users};
 
 use actix_web::{post, web, Error, HttpResponse};
 use bcrypt::{hash, DEFAULT_COST};
-use chrono::DateTime;
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::result::Error as DieselError;
 use log::{debug, error, info}; // Import log macros
 
 use serde::Deserialize;
+use uuid::Uuid;
 
+// The user_id and timestamps are the server's to set, clients that still send them have them ignored
 #[derive(Debug, Deserialize)]
 pub struct CreateUserInput {
-    pub user_id: String,
     pub username: String,
     pub password: String,
     pub email: String,
     pub user_type: String, // admin, editor, viewer
-    pub created_at: String,
-    pub updated_at: String,
 }
 
 // Define the database connection pool type
@@ -54,18 +52,15 @@ async fn create_user(
     debug!("Password successfully hashed for user: {}", user_input.username);
 
     // Create a new user struct
+    let now = chrono::Utc::now().naive_utc();
     let new_user = NewUsers {
         username: user_input.username.clone(),
         password_hash: hashed_password,
         email: user_input.email.clone(),
-        user_id: user_input.user_id.clone(),
+        user_id: Uuid::new_v4().to_string(),
         user_type: user_input.user_type.clone(),
-        created_at: DateTime::parse_from_rfc3339(&user_input.created_at)
-            .unwrap()
-            .naive_utc(), // Convert to NaiveDateTime
-        updated_at: DateTime::parse_from_rfc3339(&user_input.updated_at)
-            .unwrap()
-            .naive_utc(), // Convert to NaiveDateTime
+        created_at: now,
+        updated_at: now,
     };
 
     // Log user struct creation
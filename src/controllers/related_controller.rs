@@ -0,0 +1,140 @@
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Float4, Integer, Text};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::content_cache::{CacheKind, CacheLookup};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::can_view_unpublished;
+use crate::models::PostStatus::PostStatus;
+use crate::schemas::blog_schema::posts;
+
+const DEFAULT_RELATED_LIMIT: i32 = 5;
+const MAX_RELATED_LIMIT: i32 = 20;
+
+// How many of the source post's most frequent words go into the text match
+const MAX_SOURCE_TERMS: i32 = 64;
+
+// Query string for /blog/post/{post_id}/related, e.g. ?limit=3
+#[derive(Debug, Deserialize)]
+pub struct RelatedQuery {
+    pub limit: Option<i32>,
+}
+
+// A post ranked against the one being read. `score` is the number of shared tags
+// plus a text similarity between 0 and 1, so a shared tag outweighs any amount of wording.
+#[derive(Debug, QueryableByName, Serialize, Deserialize)]
+pub struct RelatedPost {
+    #[diesel(sql_type = Text)]
+    pub post_id: String,
+    #[diesel(sql_type = Text)]
+    pub slug: String,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = BigInt)]
+    pub shared_tags: i64,
+    #[diesel(sql_type = Float4)]
+    pub text_rank: f32,
+    #[diesel(sql_type = Float4)]
+    pub score: f32,
+}
+
+// Ranks published posts against `post_id` by the tags they share with it and by how well they
+// match its most frequent words in the search_vector. Posts sharing neither are left out,
+// and so is anything unpublished or in the trash.
+pub fn load_related_posts(conn: &mut PgConnection, post_id: &str, limit: i32) -> QueryResult<Vec<RelatedPost>> {
+    diesel::sql_query(
+        "WITH source AS (
+             SELECT p.post_id,
+                    (SELECT to_tsquery('simple', string_agg(quote_literal(terms.lexeme), ' | '))
+                     FROM (SELECT lexeme FROM unnest(p.search_vector)
+                           ORDER BY array_length(positions, 1) DESC NULLS LAST, lexeme
+                           LIMIT $2) terms) AS query
+             FROM posts p
+             WHERE p.post_id = $1
+         ),
+         shared AS (
+             SELECT pt.post_id, COUNT(*) AS shared_tags
+             FROM post_tags pt
+             JOIN post_tags source_tags ON source_tags.tag_id = pt.tag_id AND source_tags.post_id = $1
+             WHERE pt.post_id <> $1
+             GROUP BY pt.post_id
+         ),
+         ranked AS (
+             SELECT p.post_id, p.slug, p.title, p.created_at,
+                    COALESCE(shared.shared_tags, 0) AS shared_tags,
+                    COALESCE(ts_rank(p.search_vector, source.query, 32), 0)::REAL AS text_rank
+             FROM source
+             JOIN posts p ON p.post_id <> source.post_id
+             LEFT JOIN shared ON shared.post_id = p.post_id
+             WHERE p.status = 'published' AND p.deleted_at IS NULL
+               AND (shared.post_id IS NOT NULL OR p.search_vector @@ source.query)
+         )
+         SELECT post_id, slug, title, shared_tags, text_rank, (shared_tags + text_rank)::REAL AS score
+         FROM ranked
+         ORDER BY score DESC, created_at DESC
+         LIMIT $3",
+    )
+        .bind::<Text, _>(post_id)
+        .bind::<Integer, _>(MAX_SOURCE_TERMS)
+        .bind::<Integer, _>(limit)
+        .load::<RelatedPost>(conn)
+}
+
+#[get("/blog/post/{post_id}/related")]
+async fn get_related_posts(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    query: web::Query<RelatedQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+
+    let limit = query.limit.unwrap_or(DEFAULT_RELATED_LIMIT);
+    if !(1..=MAX_RELATED_LIMIT).contains(&limit) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": format!("limit must be between 1 and {}", MAX_RELATED_LIMIT)
+        })));
+    }
+
+    // Staff may look up related posts for a draft, the results are published posts either way
+    let show_unpublished = can_view_unpublished(&req).await;
+    let mut cache = CacheLookup::new(&req, CacheKind::Posts, !show_unpublished).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let mut source = posts::table.filter(posts::post_id.eq(&post_id)).filter(posts::deleted_at.is_null()).into_boxed();
+    if !show_unpublished {
+        source = source.filter(posts::status.eq(PostStatus::Published));
+    }
+    let source_exists =
+        source
+            .count()
+            .get_result::<i64>(&mut conn)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e)))?
+            > 0;
+    if !source_exists {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("Blog post with ID '{}' not found", post_id)
+        })));
+    }
+
+    let related =
+        web::block(move || load_related_posts(&mut conn, &post_id, limit))
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Loading related posts failed: {}", e)))?;
+
+    match related {
+        Ok(related) => Ok(cache.respond(&req, None, &related).await),
+        Err(e) => {
+            eprintln!("Error loading related posts: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use actix_web::{get, web, Error, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{Float4, Integer, Nullable, Text, Timestamp};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::postgres_connector::DbPool;
+
+const DEFAULT_SEARCH_LIMIT: i32 = 20;
+const MAX_SEARCH_LIMIT: i32 = 100;
+
+// Options handed to ts_headline, matches are wrapped in <mark> for the frontend to style
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=30, MinWords=10";
+
+// Query string for /search, e.g. ?q=rust%20async&type=post&limit=10
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(rename = "type")]
+    pub result_type: Option<String>,
+    pub limit: Option<i32>,
+}
+
+// A single ranked hit, `result_type` is one of post, skill or worklog
+#[derive(Debug, QueryableByName, Serialize, Deserialize)]
+pub struct SearchResult {
+    #[diesel(sql_type = Text)]
+    pub result_type: String,
+    #[diesel(sql_type = Text)]
+    pub result_id: String,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Text)]
+    pub snippet: String,
+    #[diesel(sql_type = Float4)]
+    pub rank: f32,
+    #[diesel(sql_type = Timestamp)]
+    pub created_at: NaiveDateTime,
+}
+
+// Ranks posts, skills and worklog against a single websearch-style query using the generated
+// search_vector columns. Titles are weighted above bodies when the vectors are built.
+// Only published posts are searchable, and nothing in the trash.
+pub fn search_content(
+    conn: &mut PgConnection,
+    query_text: &str,
+    result_type: Option<&str>,
+    limit: i32,
+) -> QueryResult<Vec<SearchResult>> {
+    diesel::sql_query(
+        "WITH search AS (SELECT websearch_to_tsquery('english', $1) AS query)
+         SELECT * FROM (
+             SELECT 'post' AS result_type, p.post_id AS result_id, p.title AS title,
+                    ts_headline('english', p.body, search.query, $2) AS snippet,
+                    ts_rank(p.search_vector, search.query) AS rank, p.created_at AS created_at
+             FROM posts p, search
+             WHERE p.search_vector @@ search.query AND p.status = 'published' AND p.deleted_at IS NULL
+             UNION ALL
+             SELECT 'skill', s.skill_id, s.skill_name,
+                    ts_headline('english', s.body, search.query, $2),
+                    ts_rank(s.search_vector, search.query), s.created_at
+             FROM skills s, search
+             WHERE s.search_vector @@ search.query AND s.deleted_at IS NULL
+             UNION ALL
+             SELECT 'worklog', w.worklog_id, w.work_title,
+                    ts_headline('english', w.body, search.query, $2),
+                    ts_rank(w.search_vector, search.query), w.created_at
+             FROM worklog w, search
+             WHERE w.search_vector @@ search.query AND w.deleted_at IS NULL
+         ) results
+         WHERE $3::text IS NULL OR results.result_type = $3
+         ORDER BY rank DESC, created_at DESC
+         LIMIT $4",
+    )
+        .bind::<Text, _>(query_text)
+        .bind::<Text, _>(HEADLINE_OPTIONS)
+        .bind::<Nullable<Text>, _>(result_type)
+        .bind::<Integer, _>(limit)
+        .load::<SearchResult>(conn)
+}
+
+#[get("/search")]
+async fn search(
+    pool: web::Data<DbPool>,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, Error> {
+    let search_query = query.into_inner();
+
+    let query_text = search_query.q.trim().to_string();
+    if query_text.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({ "error": "Query parameter 'q' must not be empty" })));
+    }
+
+    if let Some(result_type) = &search_query.result_type {
+        if !["post", "skill", "worklog"].contains(&result_type.as_str()) {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "error": format!("Unknown type '{}', expected post, skill or worklog", result_type)
+            })));
+        }
+    }
+
+    let limit = search_query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": format!("limit must be between 1 and {}", MAX_SEARCH_LIMIT)
+        })));
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let results =
+        web::block(move || search_content(&mut conn, &query_text, search_query.result_type.as_deref(), limit))
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Search failed: {}", e)))?;
+
+    match results {
+        Ok(results) => Ok(HttpResponse::Ok().json(results)),
+        Err(e) => {
+            eprintln!("Error searching content: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod search_controller_spec {
+    use crate::connectors::postgres_connector::DbPool;
+    use crate::controllers::search_controller::search;
+    use crate::schemas::worklog_schema::worklog;
+    use crate::table_models::worklog_models::NewWorklog;
+    use actix_web::{http::StatusCode, test, web, App};
+    use diesel::prelude::*;
+    use diesel::r2d2::{ConnectionManager, PooledConnection};
+    use diesel::{r2d2, PgConnection};
+    use dotenv::dotenv;
+    use serde_json::Value;
+    use std::env;
+
+    pub fn establish_connection() -> DbPool {
+        dotenv().ok();
+        let database_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        r2d2::Pool::builder().build(manager).expect("Failed to create pool.")
+    }
+
+    #[actix_rt::test]
+    async fn test_search_ranks_and_highlights_worklog() {
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(search),
+        )
+            .await;
+
+        let mut conn: PooledConnection<ConnectionManager<PgConnection>> =
+            pool.get().expect("Failed to get connection from pool");
+
+        let entries = vec![
+            NewWorklog {
+                worklog_id: "search_worklog_1".to_string(),
+                work_title: "Migrating the zeppelin fleet".to_string(),
+                body: "Moved every zeppelin onto the new hangar scheduler.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                author_id: None,
+            },
+            NewWorklog {
+                worklog_id: "search_worklog_2".to_string(),
+                work_title: "Hangar maintenance".to_string(),
+                body: "Routine checks, one zeppelin needed new fabric.".to_string(),
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+                author_id: None,
+            },
+        ];
+
+        diesel::insert_into(worklog::table)
+            .values(&entries)
+            .execute(&mut conn)
+            .expect("Failed to insert test worklog");
+
+        let req = test::TestRequest::get()
+            .uri("/search?q=zeppelin&type=worklog")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let json_body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        let results = json_body.as_array().expect("Expected an array of results");
+
+        diesel::delete(worklog::table.filter(worklog::worklog_id.like("search_worklog_%")))
+            .execute(&mut conn)
+            .expect("Failed to delete test worklog");
+
+        assert_eq!(results.len(), 2);
+        // The title match is weighted above a body-only match
+        assert_eq!(results[0]["result_id"], "search_worklog_1");
+        assert_eq!(results[0]["result_type"], "worklog");
+        assert!(results[0]["snippet"].as_str().unwrap().contains("<mark>zeppelin</mark>"));
+        assert_eq!(results[1]["result_id"], "search_worklog_2");
+
+        let req = test::TestRequest::get()
+            .uri("/search?q=zeppelin&type=comment")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::{can_view_unpublished, enqueue_post_event};
+use crate::controllers::tag_controller::attach_tags;
+use crate::models::PostStatus::PostStatus;
+use crate::models::SeriesNavigation::{SeriesNavigation, SeriesPostLink};
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::series_schema::series;
+use crate::table_models::blog_models::Post;
+use crate::table_models::series_models::{NewSeries, Series};
+use crate::utils::slug::slugify;
+
+const MAX_TITLE_LENGTH: usize = 255;
+
+#[derive(Serialize, Deserialize)]
+pub struct SeriesInput {
+    pub title: String,
+    pub description: Option<String>,
+}
+
+// Where to put a post in a series, at the end when no position is given
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SeriesPlacementInput {
+    pub position: Option<i32>,
+}
+
+// The part of a series one published post fills, as loaded for navigation. `position` is the stored one
+// for ordering, the link carries the part number readers see.
+struct SeriesMember {
+    id: i32,
+    position: i32,
+    link: SeriesPostLink,
+}
+
+// Where each series post sits among the published posts of its series, keyed by the post's id.
+// Posts outside a series are left out.
+pub fn load_series_navigation(conn: &mut PgConnection, posts: &[Post]) -> QueryResult<HashMap<i32, SeriesNavigation>> {
+    let mut series_ids: Vec<i32> = posts.iter().filter_map(|post| post.series_id).collect();
+    series_ids.sort();
+    series_ids.dedup();
+    if series_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let series_by_id: HashMap<i32, (String, String)> =
+        series::table
+            .filter(series::id.eq_any(&series_ids))
+            .select((series::id, series::slug, series::title))
+            .load::<(i32, String, String)>(conn)?
+            .into_iter()
+            .map(|(id, slug, title)| (id, (slug, title)))
+            .collect();
+
+    let rows =
+        posts::table
+            .filter(posts::series_id.eq_any(&series_ids))
+            .filter(posts::series_position.is_not_null())
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::status.eq(PostStatus::Published))
+            .order((posts::series_position.asc(), posts::id.asc()))
+            .select((
+                posts::series_id.assume_not_null(),
+                posts::id,
+                posts::post_id,
+                posts::slug,
+                posts::title,
+                posts::series_position.assume_not_null(),
+            ))
+            .load::<(i32, i32, String, String, String, i32)>(conn)?;
+
+    // Stored positions count drafts and trashed parts too, readers are told which published part it is
+    let mut members_by_series: HashMap<i32, Vec<SeriesMember>> = HashMap::new();
+    for (series_id, id, post_id, slug, title, position) in rows {
+        let members = members_by_series.entry(series_id).or_default();
+        let part = members.len() as i32 + 1;
+        members.push(SeriesMember {
+            id,
+            position,
+            link: SeriesPostLink { post_id, slug, title, position: part },
+        });
+    }
+
+    let mut navigation = HashMap::new();
+    for post in posts {
+        let (Some(series_id), Some(position)) = (post.series_id, post.series_position) else { continue };
+        let Some((slug, title)) = series_by_id.get(&series_id) else { continue };
+        let members = members_by_series.get(&series_id).map(Vec::as_slice).unwrap_or_default();
+
+        // Ties on position go by id, the same order the series endpoint lists posts in
+        let key = (position, post.id);
+        let before = members.iter().filter(|member| (member.position, member.id) < key).count();
+        // A draft shown to an editor counts itself, in the place it will take once published
+        let published = members.iter().any(|member| member.id == post.id);
+        navigation.insert(post.id, SeriesNavigation {
+            slug: slug.clone(),
+            title: title.clone(),
+            position: before as i32 + 1,
+            post_count: members.len() + usize::from(!published),
+            previous: members.iter().rev().find(|member| (member.position, member.id) < key).map(|member| member.link.clone()),
+            next: members.iter().find(|member| (member.position, member.id) > key).map(|member| member.link.clone()),
+        });
+    }
+    Ok(navigation)
+}
+
+// Takes a post out of its series, closing the gap it leaves. Returns the series it was in.
+pub fn detach_from_series(conn: &mut PgConnection, post_id: &str) -> QueryResult<Option<i32>> {
+    let current =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .filter(posts::deleted_at.is_null())
+            .select((posts::series_id, posts::series_position))
+            .first::<(Option<i32>, Option<i32>)>(conn)
+            .optional()?;
+
+    let Some((Some(series_id), position)) = current else { return Ok(None) };
+    // Closing the gap renumbers the series the post leaves, which may not be the one the caller locked
+    series::table.find(series_id).select(series::id).for_update().execute(conn)?;
+
+    diesel::update(posts::table.filter(posts::post_id.eq(post_id)).filter(posts::deleted_at.is_null()))
+        .set((posts::series_id.eq(None::<i32>), posts::series_position.eq(None::<i32>)))
+        .execute(conn)?;
+    if let Some(position) = position {
+        diesel::update(posts::table.filter(posts::series_id.eq(series_id)).filter(posts::series_position.gt(position)))
+            .set(posts::series_position.eq(posts::series_position - 1))
+            .execute(conn)?;
+    }
+    Ok(Some(series_id))
+}
+
+// Puts a post into a series at `position`, moving the posts from there on back by one.
+// Positions past the end, or none at all, append the post. Returns the position it ended up at,
+// or None when there is no such post.
+pub fn place_post_in_series(conn: &mut PgConnection, series_id: i32, post_id: &str, position: Option<i32>) -> QueryResult<Option<i32>> {
+    let exists =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .filter(posts::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?
+            > 0;
+    if !exists {
+        return Ok(None);
+    }
+
+    // Moving within the same series starts from the order without the post
+    detach_from_series(conn, post_id)?;
+
+    let last =
+        posts::table
+            .filter(posts::series_id.eq(series_id))
+            .select(diesel::dsl::max(posts::series_position))
+            .first::<Option<i32>>(conn)?
+            .unwrap_or(0);
+    let position = position.unwrap_or(last + 1).clamp(1, last + 1);
+
+    diesel::update(posts::table.filter(posts::series_id.eq(series_id)).filter(posts::series_position.ge(position)))
+        .set(posts::series_position.eq(posts::series_position + 1))
+        .execute(conn)?;
+    diesel::update(posts::table.filter(posts::post_id.eq(post_id)).filter(posts::deleted_at.is_null()))
+        .set((posts::series_id.eq(series_id), posts::series_position.eq(position)))
+        .execute(conn)?;
+    Ok(Some(position))
+}
+
+fn series_not_found(slug: &str) -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("application/json")
+        .json(json!({
+            "error": format!("Series '{}' not found", slug)
+        }))
+}
+
+fn series_conflict(slug: &str) -> HttpResponse {
+    HttpResponse::Conflict()
+        .content_type("application/json")
+        .json(json!({
+            "error": format!("A series with slug '{}' already exists", slug)
+        }))
+}
+
+// The trimmed title and the slug it gives
+fn series_title(input: &SeriesInput) -> Result<(String, String), HttpResponse> {
+    let title = input.title.trim().to_string();
+    let slug = slugify(&title);
+    if slug.is_empty() || title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(HttpResponse::BadRequest().json(json!({
+            "error": format!("A series needs a title of up to {} characters with at least one letter or digit", MAX_TITLE_LENGTH)
+        })));
+    }
+    Ok((title, slug))
+}
+
+// Also locks the series until the transaction ends, so two changes to it can't both number
+// their post from the same last position
+fn find_series_id(conn: &mut PgConnection, slug: &str) -> QueryResult<Option<i32>> {
+    series::table
+        .filter(series::slug.eq(slug))
+        .select(series::id)
+        .for_update()
+        .first::<i32>(conn)
+        .optional()
+}
+
+#[post("/blog/series/create")]
+async fn create_series(
+    pool: web::Data<DbPool>,
+    series_input: web::Json<SeriesInput>,
+) -> Result<HttpResponse, Error> {
+    let (title, slug) = match series_title(&series_input) {
+        Ok(title) => title,
+        Err(response) => return Ok(response),
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    let new_series =
+        NewSeries {
+            title,
+            slug,
+            description: series_input.description.clone().unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match diesel::insert_into(series::table)
+        .values(&new_series)
+        .returning(Series::as_returning())
+        .get_result(&mut conn)
+    {
+        Ok(series) => Ok(HttpResponse::Created().json(series)),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(series_conflict(&new_series.slug)),
+        Err(e) => {
+            eprintln!("Error inserting new series: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/blog/series/get/all")]
+async fn get_all_series(
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match series::table
+        .order(series::title.asc())
+        .select(Series::as_select())
+        .load(&mut conn)
+    {
+        Ok(series) => Ok(HttpResponse::Ok().json(series)),
+        Err(e) => {
+            eprintln!("Error loading series: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// A series with its posts in order. Everyone else only sees the published ones.
+#[get("/blog/series/retrieve/{slug}")]
+async fn get_series(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let show_unpublished = can_view_unpublished(&req).await;
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let found =
+        series::table
+            .filter(series::slug.eq(&slug))
+            .select(Series::as_select())
+            .first(&mut conn)
+            .optional();
+
+    let series = match found {
+        Ok(Some(series)) => series,
+        Ok(None) => return Ok(series_not_found(&slug)),
+        Err(e) => {
+            eprintln!("Error retrieving series: {:?}", e);
+            return Ok(HttpResponse::InternalServerError().finish());
+        }
+    };
+
+    let mut query = posts::table.filter(posts::series_id.eq(series.id)).filter(posts::deleted_at.is_null()).into_boxed();
+    if !show_unpublished {
+        query = query.filter(posts::status.eq(PostStatus::Published));
+    }
+
+    match query
+        .order((posts::series_position.asc(), posts::id.asc()))
+        .select(Post::as_select())
+        .load(&mut conn)
+        .and_then(|posts| attach_tags(&mut conn, posts))
+    {
+        Ok(posts) => Ok(HttpResponse::Ok().json(json!({
+            "id": series.id,
+            "title": series.title,
+            "slug": series.slug,
+            "description": series.description,
+            "created_at": series.created_at,
+            "updated_at": series.updated_at,
+            "posts": posts,
+        }))),
+        Err(e) => {
+            eprintln!("Error loading series posts: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Renaming a series also moves it to the slug of the new title
+#[put("/blog/series/update/{slug}")]
+async fn update_series(
+    path: web::Path<String>,
+    series_input: web::Json<SeriesInput>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let (title, new_slug) = match series_title(&series_input) {
+        Ok(title) => title,
+        Err(response) => return Ok(response),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let updated =
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let updated =
+                diesel::update(series::table.filter(series::slug.eq(&slug)))
+                    .set((
+                        series::title.eq(&title),
+                        series::slug.eq(&new_slug),
+                        series::description.eq(series_input.description.clone().unwrap_or_default()),
+                        series::updated_at.eq(chrono::Utc::now().naive_utc()),
+                    ))
+                    .returning(Series::as_returning())
+                    .get_result(conn)
+                    .optional()?;
+
+            // Each post in the series shows its title, so each of them changed for webhooks too
+            let Some(series) = updated else { return Ok(None) };
+            let post_ids: Vec<String> =
+                posts::table
+                    .filter(posts::series_id.eq(series.id))
+                    .filter(posts::deleted_at.is_null())
+                    .select(posts::post_id)
+                    .load(conn)?;
+            for post_id in &post_ids {
+                enqueue_post_event(conn, WebhookEvent::PostUpdated, post_id)?;
+            }
+            Ok(Some((series, post_ids)))
+        });
+
+    match updated {
+        // Posts show their series by title, so cached posts in this one are out of date
+        Ok(Some((series, post_ids))) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            for post_id in &post_ids {
+                publish_live(&req, LiveKind::Post, LiveAction::Updated, post_id).await;
+            }
+            Ok(HttpResponse::Ok().json(series))
+        }
+        Ok(None) => Ok(series_not_found(&slug)),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(series_conflict(&new_slug)),
+        Err(e) => {
+            eprintln!("Error updating series: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Deleting a series takes its posts out of it, the posts themselves are left alone
+#[delete("/blog/series/single/{slug}")]
+async fn delete_series(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let deleted =
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let Some(series_id) = find_series_id(conn, &slug)? else { return Ok(None) };
+
+            let post_ids: Vec<String> =
+                diesel::update(posts::table.filter(posts::series_id.eq(series_id)))
+                    .set((posts::series_id.eq(None::<i32>), posts::series_position.eq(None::<i32>)))
+                    .returning(posts::post_id)
+                    .get_results(conn)?;
+            for post_id in &post_ids {
+                enqueue_post_event(conn, WebhookEvent::PostUpdated, post_id)?;
+            }
+            diesel::delete(series::table.find(series_id))
+                .returning(series::title)
+                .get_result::<String>(conn)
+                .map(|title| Some((title, post_ids)))
+        });
+
+    match deleted {
+        Ok(Some((title, post_ids))) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            for post_id in &post_ids {
+                publish_live(&req, LiveKind::Post, LiveAction::Updated, post_id).await;
+            }
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .json(json!({ "message": format!("Series '{}' has been deleted", title) })))
+        }
+        Ok(None) => Ok(series_not_found(&slug)),
+        Err(e) => {
+            eprintln!("Error deleting series: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Adds a post to a series, or moves it to another position or series
+#[put("/blog/series/{slug}/posts/{post_id}")]
+async fn add_post_to_series(
+    path: web::Path<(String, String)>,
+    placement: web::Json<SeriesPlacementInput>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (slug, post_id) = path.into_inner();
+    if placement.position.is_some_and(|position| position < 1) {
+        return Ok(HttpResponse::BadRequest().json(json!({ "error": "Series positions start at 1" })));
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let placed =
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let Some(series_id) = find_series_id(conn, &slug)? else { return Ok(Err(series_not_found(&slug))) };
+
+            match place_post_in_series(conn, series_id, &post_id, placement.position)? {
+                Some(position) => {
+                    enqueue_post_event(conn, WebhookEvent::PostUpdated, &post_id)?;
+                    Ok(Ok(position))
+                }
+                None => Ok(Err(HttpResponse::NotFound().json(json!({ "error": format!("Post with ID '{}' not found", post_id) })))),
+            }
+        });
+
+    match placed {
+        Ok(Ok(position)) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            publish_live(&req, LiveKind::Post, LiveAction::Updated, &post_id).await;
+            Ok(HttpResponse::Ok().json(json!({
+                "message": format!("Post '{}' is now part {} of series '{}'", post_id, position, slug),
+                "position": position,
+            })))
+        }
+        Ok(Err(response)) => Ok(response),
+        Err(e) => {
+            eprintln!("Error adding post to series: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[delete("/blog/series/{slug}/posts/{post_id}")]
+async fn remove_post_from_series(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (slug, post_id) = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let removed =
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let Some(series_id) = find_series_id(conn, &slug)? else { return Ok(None) };
+
+            // Only when the post is in this series, rather than some other one
+            let in_series =
+                posts::table
+                    .filter(posts::post_id.eq(&post_id))
+                    .filter(posts::series_id.eq(series_id))
+                    .filter(posts::deleted_at.is_null())
+                    .count()
+                    .get_result::<i64>(conn)?
+                    > 0;
+            if !in_series {
+                return Ok(Some(false));
+            }
+            detach_from_series(conn, &post_id)?;
+            enqueue_post_event(conn, WebhookEvent::PostUpdated, &post_id)?;
+            Ok(Some(true))
+        });
+
+    match removed {
+        Ok(Some(true)) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            publish_live(&req, LiveKind::Post, LiveAction::Updated, &post_id).await;
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .json(json!({ "message": format!("Post '{}' has been removed from series '{}'", post_id, slug) })))
+        }
+        Ok(Some(false)) => Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("Post with ID '{}' is not in series '{}'", post_id, slug)
+        }))),
+        Ok(None) => Ok(series_not_found(&slug)),
+        Err(e) => {
+            eprintln!("Error removing post from series: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
@@ -1,71 +1,98 @@
+use crate::connectors::content_cache::{invalidate_cached, CacheKind, CacheLookup};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::controllers::author_controller::{attach_authors, author_ids_named};
+use crate::controllers::login_controller::session_from_request;
+use crate::controllers::webhook_controller::enqueue_event;
+use crate::models::Author::AuthorQuery;
+use crate::models::Pagination::{CursorKey, ListParams, ListQuery, Page, SortField, SortOrder};
+use crate::models::Rendered::{RenderQuery, Rendered};
+use crate::models::WebhookEvent::WebhookEvent;
 use crate::schemas::skills_schema::skills;
 use crate::table_models::skills_models::{NewSkill, Skill};
-use actix_web::{delete, get, post, put, web, Error, HttpResponse};
-use chrono::DateTime;
+use crate::utils::etag::{etag_for, if_match_version, precondition_failed};
+use actix_web::http::header::ETag;
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use uuid::Uuid;
 
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+// Body of the create and update endpoints. The skill_id, id and timestamps are the server's to set,
+// clients that still send them have them ignored.
 #[derive(Serialize, Deserialize)]
 pub struct SkillInput {
-    pub id: i32,
-    pub skill_id: String,
     pub skill_name: String,
     pub body: String,
-    pub created_at: String,
-    pub updated_at: String,
 }
 
-impl SkillInput {
-    pub fn new(id: i32, skill_id: String, skill_name: String, body: String, created_at: String, updated_at: String) -> Self {
-        SkillInput {
-            id,
-            skill_id,
-            skill_name,
-            body,
-            created_at,
-            updated_at,
-        }
-    }
+// Puts `event` for a skill in the webhook outbox, with the skill as it is now. Call it in the write's transaction.
+// Trashed copies can share the skill_id, so the live skill wins, then the most recently trashed copy.
+pub fn enqueue_skill_event(conn: &mut PgConnection, event: WebhookEvent, skill_id: &str) -> QueryResult<i32> {
+    let id =
+        skills::table
+            .filter(skills::skill_id.eq(skill_id))
+            .order(skills::deleted_at.desc().nulls_first())
+            .select(skills::id)
+            .first::<i32>(conn)?;
+    enqueue_skill_row_event(conn, event, id)
+}
+
+// Same as `enqueue_skill_event`, for one row of the skills table picked by its `id`
+pub fn enqueue_skill_row_event(conn: &mut PgConnection, event: WebhookEvent, id: i32) -> QueryResult<i32> {
+    let (skill_id, skill_name) =
+        skills::table
+            .find(id)
+            .select((skills::skill_id, skills::skill_name))
+            .first::<(String, String)>(conn)?;
+    enqueue_event(conn, event, json!({ "skill_id": skill_id, "skill_name": skill_name }))
 }
 
 #[post("/blog/skill/create")]
 async fn create_skill(
     pool: web::Data<DbPool>,
     skill: web::Json<SkillInput>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let skill_input = skill.into_inner();
+    let author_id = session_from_request(&req).await.map(|session| session.user_id);
 
+    let now = chrono::Utc::now().naive_utc();
     let new_skill =
         NewSkill {
-            skill_id: skill_input.skill_id,
+            skill_id: Uuid::new_v4().to_string(),
             skill_name: skill_input.skill_name,
             body: skill_input.body,
-            created_at: DateTime::parse_from_rfc3339(&skill_input.created_at)
-                .unwrap()
-                .naive_utc(), // Convert to NaiveDateTime
-            updated_at: DateTime::parse_from_rfc3339(&skill_input.updated_at)
-                .unwrap()
-                .naive_utc(), // Convert to NaiveDateTime
+            created_at: now,
+            updated_at: now,
+            author_id,
         };
 
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    conn.transaction::<_, diesel::result::Error, _>(|conn| {
-        // Insert the new skill
-        diesel::insert_into(skills::table)
-            .values(&new_skill)
-            .get_result::<Skill>(conn)
-            .map_err(|e| {
-                eprintln!("Error inserting new skill: {:?}", e);
-                e
-            })
-    }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))
-        .map(|skill| HttpResponse::Created().json(skill))
+    let created =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Insert the new skill
+            diesel::insert_into(skills::table)
+                .values(&new_skill)
+                .returning(Skill::as_returning())
+                .get_result(conn)
+                .map_err(|e| {
+                    eprintln!("Error inserting new skill: {:?}", e);
+                    e
+                })
+                .and_then(|created| {
+                    enqueue_skill_event(conn, WebhookEvent::SkillCreated, &created.skill_id)?;
+                    attach_authors(conn, vec![created], |created| created.author_id.as_ref())
+                })
+        }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))?;
+
+    invalidate_cached(&req, CacheKind::Skills).await;
+    publish_live(&req, LiveKind::Skill, LiveAction::Created, &created[0].item.skill_id).await;
+    Ok(HttpResponse::Created().json(&created[0]))
 }
 
 
@@ -73,14 +100,25 @@ async fn create_skill(
 async fn get_by_skill_id(
     path: web::Path<String>,
     pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let skill_id_path = path.into_inner();
+    let mut cache = CacheLookup::new(&req, CacheKind::Skills, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match skills::table.filter(skills::skill_id.eq(skill_id_path)).first::<Skill>(&mut conn) {
-        Ok(skill) => Ok(HttpResponse::Ok().json(skill)),
+    match skills::table.filter(skills::skill_id.eq(skill_id_path)).filter(skills::deleted_at.is_null()).select(Skill::as_select()).first(&mut conn).and_then(|skill| attach_authors(&mut conn, vec![skill], |skill| skill.author_id.as_ref())) {
+        Ok(mut skills) => {
+            let skill = skills.remove(0);
+            let updated_at = skill.item.updated_at;
+            Ok(cache.respond(&req, Some(updated_at), &Rendered::new(skill, render_query.render)).await)
+        }
         Err(_) => Ok(HttpResponse::NotFound().finish()),
     }
 }
@@ -89,26 +127,96 @@ async fn get_by_skill_id(
 async fn get_skill(
     path: web::Path<i32>,
     pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let id = path.into_inner();
+    let mut cache = CacheLookup::new(&req, CacheKind::Skills, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match skills::table.find(id).first::<Skill>(&mut conn) {
-        Ok(skill) => Ok(HttpResponse::Ok().json(skill)),
+    match skills::table.find(id).filter(skills::deleted_at.is_null()).select(Skill::as_select()).first(&mut conn).and_then(|skill| attach_authors(&mut conn, vec![skill], |skill| skill.author_id.as_ref())) {
+        Ok(mut skills) => {
+            let skill = skills.remove(0);
+            let updated_at = skill.item.updated_at;
+            Ok(cache.respond(&req, Some(updated_at), &Rendered::new(skill, render_query.render)).await)
+        }
         Err(_) => Ok(HttpResponse::NotFound().finish()),
     }
 }
 
+// Loads one keyset page of skills along with the total number of skills matching the filters
+pub fn load_skills_page(conn: &mut PgConnection, params: &ListParams, author: Option<&str>) -> QueryResult<Page<Skill>> {
+    let filtered = || {
+        // Trashed skills only show up in the trash
+        let mut query = skills::table.filter(skills::deleted_at.is_null()).into_boxed();
+        if let Some(from) = params.from {
+            query = query.filter(skills::created_at.ge(from));
+        }
+        if let Some(to) = params.to {
+            query = query.filter(skills::created_at.lt(to));
+        }
+        if let Some(author) = author {
+            query = query.filter(skills::author_id.eq_any(author_ids_named(author)));
+        }
+        query
+    };
+
+    let total_count = filtered().count().get_result::<i64>(conn)?;
+
+    let after = params.after.as_ref();
+    let ordered =
+        match params.sort {
+            SortField::CreatedAt =>
+                crate::keyset_paginate!(filtered(), skills::created_at, skills::id, params.order, after.and_then(|c| c.timestamp().map(|t| (t, c.id)))),
+            SortField::UpdatedAt =>
+                crate::keyset_paginate!(filtered(), skills::updated_at, skills::id, params.order, after.and_then(|c| c.timestamp().map(|t| (t, c.id)))),
+            SortField::Title =>
+                crate::keyset_paginate!(filtered(), skills::skill_name, skills::id, params.order, after.and_then(|c| c.text().map(|t| (t, c.id)))),
+        };
+
+    let rows = ordered.select(Skill::as_select()).limit(params.limit + 1).load(conn)?;
+
+    Ok(Page::from_rows(rows, params, total_count, |skill| {
+        let key = match params.sort {
+            SortField::CreatedAt => CursorKey::Timestamp(skill.created_at),
+            SortField::UpdatedAt => CursorKey::Timestamp(skill.updated_at),
+            SortField::Title => CursorKey::Text(skill.skill_name.clone()),
+        };
+        (key, skill.id)
+    }))
+}
+
 #[get("/blog/skill/get/all")]
-async fn get_all_skills(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+async fn get_all_skills(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+    author_query: web::Query<AuthorQuery>,
+    render_query: web::Query<RenderQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    // Skills have always been listed oldest first
+    let params = match query.into_inner().into_params(SortOrder::Asc) {
+        Ok(params) => params,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+    };
+
+    let mut cache = CacheLookup::new(&req, CacheKind::Skills, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match skills::table.load::<Skill>(&mut conn) {
-        Ok(skills) => Ok(HttpResponse::Ok().json(skills)),
+    match load_skills_page(&mut conn, &params, author_query.author.as_deref()).and_then(|page| page.try_map_items(|items| attach_authors(&mut conn, items, |item| item.author_id.as_ref()))) {
+        Ok(page) => Ok(cache.respond(&req, None, &page.map_items(|items| Rendered::all(items, render_query.render))).await),
         Err(_) => Ok(HttpResponse::InternalServerError().finish()),
     }
 }
@@ -118,6 +226,7 @@ async fn update_skill(
     path: web::Path<String>,
     skill: web::Json<SkillInput>,
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let skill_id_path = path.into_inner();
     let skill_input = skill.into_inner();
@@ -126,37 +235,61 @@ async fn update_skill(
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    // First, retrieve the title of the post before deleting it
+    // First, retrieve the name and version of the skill being updated
     let skill_name =
         skills::table
             .filter(skills::skill_id.eq(&skill_id_path))
-            .select(skills::skill_name)
-            .first::<String>(&mut conn)
+            .filter(skills::deleted_at.is_null())
+            .select((skills::skill_name, skills::updated_at))
+            .first::<(String, chrono::NaiveDateTime)>(&mut conn)
             .optional()
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error retrieving skill name: {}", e))
             })?;
 
     match skill_name {
-        Some(skill) => {
-            match diesel::update(skills::table.filter(skills::skill_id.eq(skill_id_path)))
-                .set((
-                    skills::id.eq(skill_input.id),
-                    skills::skill_id.eq(skill_input.skill_id),
-                    skills::skill_name.eq(skill_input.skill_name),
-                    skills::body.eq(skill_input.body),
-                ))
-                .execute(&mut conn)
-            {
-                Ok(_) => {
+        Some((skill, updated_at)) => {
+            let expected_version = match if_match_version(&req, updated_at) {
+                Ok(expected_version) => expected_version,
+                Err(response) => return Ok(response),
+            };
+
+            let mut update =
+                diesel::update(skills::table.filter(skills::skill_id.eq(&skill_id_path)).filter(skills::deleted_at.is_null()))
+                    .set((
+                        skills::skill_name.eq(skill_input.skill_name),
+                        skills::body.eq(skill_input.body),
+                        skills::updated_at.eq(chrono::Utc::now().naive_utc()),
+                    ))
+                    .returning(skills::updated_at)
+                    .into_boxed();
+            // With If-Match, only overwrite the version that was checked so a concurrent write in between fails the precondition
+            if let Some(expected_version) = expected_version {
+                update = update.filter(skills::updated_at.eq(expected_version));
+            }
+
+            let update_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let new_updated_at = update.get_result::<chrono::NaiveDateTime>(conn).optional()?;
+                if new_updated_at.is_some() {
+                    enqueue_skill_event(conn, WebhookEvent::SkillUpdated, &skill_id_path)?;
+                }
+                Ok(new_updated_at)
+            });
+
+            match update_result {
+                Ok(Some(new_updated_at)) => {
+                    invalidate_cached(&req, CacheKind::Skills).await;
+                    publish_live(&req, LiveKind::Skill, LiveAction::Updated, &skill_id_path).await;
                     let response_body =
                         json!({"message": format!("Skill '{}' has been updated", skill)});
                     Ok(HttpResponse::Ok()
                         .content_type("application/json")
+                        .insert_header(ETag(etag_for(new_updated_at)))
                         .json(response_body))
                 }
+                Ok(None) => Ok(precondition_failed(updated_at)),
                 Err(e) => {
-                    eprintln!("Error updating post: {:?}", e);
+                    eprintln!("Error updating skill: {:?}", e);
                     Ok(HttpResponse::InternalServerError().finish())
                 }
             }
@@ -179,6 +312,7 @@ async fn update_skill(
 async fn delete_skill(
     path: web::Path<String>,  // Changed to String since skill_id is a varchar
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let skill_id_path = path.into_inner();
     let mut conn = pool.get().map_err(|e| {
@@ -189,20 +323,44 @@ async fn delete_skill(
     let skill_name =
         skills::table
             .filter(skills::skill_id.eq(&skill_id_path))
-            .select(skills::skill_name)
-            .first::<String>(&mut conn)
+            .filter(skills::deleted_at.is_null())
+            .select((skills::skill_name, skills::updated_at))
+            .first::<(String, chrono::NaiveDateTime)>(&mut conn)
             .optional()
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error retrieving skill name: {}", e))
             })?;
 
     match skill_name {
-        Some(skill_name) => {
-            // Now delete the skill
-            match diesel::delete(skills::table.filter(skills::skill_id.eq(&skill_id_path))).execute(&mut conn) {
+        Some((skill_name, updated_at)) => {
+            let expected_version = match if_match_version(&req, updated_at) {
+                Ok(expected_version) => expected_version,
+                Err(response) => return Ok(response),
+            };
+
+            // Now move the skill to the trash
+            let trash_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let mut trash =
+                    diesel::update(skills::table.filter(skills::skill_id.eq(&skill_id_path)).filter(skills::deleted_at.is_null()))
+                        .set(skills::deleted_at.eq(chrono::Utc::now().naive_utc()))
+                        .into_boxed();
+                if let Some(expected_version) = expected_version {
+                    trash = trash.filter(skills::updated_at.eq(expected_version));
+                }
+                let trashed = trash.execute(conn)?;
+                if trashed > 0 {
+                    enqueue_skill_event(conn, WebhookEvent::SkillDeleted, &skill_id_path)?;
+                }
+                Ok(trashed)
+            });
+
+            match trash_result {
+                Ok(0) => Ok(precondition_failed(updated_at)),
                 Ok(_) => {
+                    invalidate_cached(&req, CacheKind::Skills).await;
+                    publish_live(&req, LiveKind::Skill, LiveAction::Deleted, &skill_id_path).await;
                     let response_body = json!({
-                        "message": format!("Skill '{}' has been deleted", skill_name)
+                        "message": format!("Skill '{}' has been moved to the trash", skill_name)
                     });
 
                     Ok(HttpResponse::Ok()
@@ -231,15 +389,32 @@ async fn delete_skill(
 #[delete("/blog/skill/all")]
 async fn delete_all_skills(
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match diesel::sql_query("DELETE FROM skills").execute(&mut conn) {
-        Ok(_) => {
+    let trash_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let trashed =
+            diesel::update(skills::table.filter(skills::deleted_at.is_null()))
+                .set(skills::deleted_at.eq(chrono::Utc::now().naive_utc()))
+                .returning(skills::skill_id)
+                .get_results::<String>(conn)?;
+        for skill_id in &trashed {
+            enqueue_skill_event(conn, WebhookEvent::SkillDeleted, skill_id)?;
+        }
+        Ok(trashed)
+    });
+
+    match trash_result {
+        Ok(trashed) => {
+            invalidate_cached(&req, CacheKind::Skills).await;
+            for skill_id in &trashed {
+                publish_live(&req, LiveKind::Skill, LiveAction::Deleted, skill_id).await;
+            }
             let response_body = json!({
-                "message": "All skills have been deleted."
+                "message": "All skills have been moved to the trash."
             });
 
             Ok(HttpResponse::Ok()
@@ -353,6 +528,7 @@ mod skills_controller_spec {
                 body: "Some content about the skill".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
             NewSkill {
                 skill_id: "skill2".to_string(),
@@ -360,6 +536,7 @@ mod skills_controller_spec {
                 body: "Some content about the skill 2".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
         ];
 
@@ -387,8 +564,14 @@ mod skills_controller_spec {
         let body_str = std::str::from_utf8(&body).unwrap();
         let json_body: Value = serde_json::from_str(body_str).unwrap();
 
+        // The server picks the skill_id, the one sent is ignored
         let skill_id = json_body.get("skill_id").unwrap().as_str().unwrap();
-        assert_eq!(skill_id, "skill3");
+        assert!(uuid::Uuid::parse_str(skill_id).is_ok());
+
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+        diesel::delete(skills::table.filter(skills::skill_id.eq(skill_id)))
+            .execute(&mut conn)
+            .expect("Failed to delete test skill");
     }
 
     async fn test_get_by_skill_id() {
@@ -412,6 +595,7 @@ mod skills_controller_spec {
                 body: "This is the first skill.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
             NewSkill {
                 skill_id: "skill5".to_string(),
@@ -419,6 +603,7 @@ mod skills_controller_spec {
                 body: "This is the second skill.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
         ];
 
@@ -461,6 +646,7 @@ mod skills_controller_spec {
                 body: "Some content 1".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
             NewSkill {
                 skill_id: "skill11".to_string(),
@@ -468,6 +654,7 @@ mod skills_controller_spec {
                 body: "Some content 2".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
             NewSkill {
                 skill_id: "skill12".to_string(),
@@ -475,6 +662,7 @@ mod skills_controller_spec {
                 body: "Some content 3".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
         ];
 
@@ -490,8 +678,10 @@ mod skills_controller_spec {
         let body: Bytes = to_bytes(resp.into_body()).await.unwrap();
         let json_body: Value = serde_json::from_slice(&body).unwrap();
 
-        // Assuming the response is an array of skills
-        let skill_json_array = json_body.as_array().expect("Expected an array of skills");
+        // The response is a page with the skills under "items"
+        let skill_json_array = json_body["items"].as_array().expect("Expected an array of skills");
+        assert_eq!(json_body["total_count"], 3);
+        assert!(json_body["next_cursor"].is_null());
 
         // Assert the length of the array
         assert_eq!(skill_json_array.len(), 3);
@@ -531,6 +721,7 @@ mod skills_controller_spec {
                 body: "Fake content".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
         ];
 
@@ -585,6 +776,7 @@ mod skills_controller_spec {
                 body: "This is the first test skill.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
             NewSkill {
                 skill_id: "def456".to_string(),
@@ -592,6 +784,7 @@ mod skills_controller_spec {
                 body: "This is the second test skill.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
         ];
 
@@ -609,8 +802,10 @@ mod skills_controller_spec {
 
         let deleted_skill_1 =
             skills::table
+                .filter(skills::deleted_at.is_null())
                 .filter(skills::skill_id.eq("abc200"))
-                .first::<Skill>(&mut conn)
+                .select(Skill::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted skill");
 
@@ -618,8 +813,10 @@ mod skills_controller_spec {
 
         let deleted_skill_2 =
             skills::table
+                .filter(skills::deleted_at.is_null())
                 .filter(skills::skill_id.eq("def456"))
-                .first::<Skill>(&mut conn)
+                .select(Skill::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted skill");
 
@@ -647,6 +844,7 @@ mod skills_controller_spec {
                 body: "This is the first skill.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
             NewSkill {
                 skill_id: "fake_id_2".to_string(),
@@ -654,6 +852,7 @@ mod skills_controller_spec {
                 body: "This is the second skill.".to_string(),
                 created_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
                 updated_at: chrono::Utc::now().naive_utc(), // Current time in ISO 8601 format
+                author_id: None,
             },
         ];
 
@@ -674,8 +873,10 @@ mod skills_controller_spec {
 
         let deleted_skill_1 =
             skills::table
+                .filter(skills::deleted_at.is_null())
                 .filter(skills::skill_id.eq("fake_id_1"))
-                .first::<Skill>(&mut conn)
+                .select(Skill::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted skill");
 
@@ -683,8 +884,10 @@ mod skills_controller_spec {
 
         let deleted_skill_2 =
             skills::table
+                .filter(skills::deleted_at.is_null())
                 .filter(skills::skill_id.eq("fake_id_2"))
-                .first::<Skill>(&mut conn)
+                .select(Skill::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted skill");
 
@@ -0,0 +1,197 @@
+use actix_web::http::header;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::connectors::view_counter::count_view;
+use crate::controllers::blog_controller::can_view_unpublished;
+use crate::controllers::tag_controller::attach_tags;
+use crate::controllers::translation_controller::{preferred_locales, translate_posts, vary_on_language, LangQuery};
+use crate::models::PostStatus::PostStatus;
+use crate::models::Rendered::{RenderQuery, Rendered};
+use crate::schemas::blog_schema::posts;
+use crate::schemas::slug_history_schema::slug_history;
+use crate::table_models::blog_models::Post;
+use crate::table_models::slug_history_models::NewSlugHistory;
+use crate::utils::etag::{conditional_body, versioned_etag};
+use crate::utils::slug::slugify;
+
+// Leaves room for the -N suffix within the VARCHAR(255) column
+const MAX_SLUG_BASE_LENGTH: usize = 200;
+
+// The slug a title would get before making it unique, titles without any letters or digits become "post"
+pub fn slug_base(title: &str) -> String {
+    let slug = slugify(title);
+    let slug = slug[..slug.len().min(MAX_SLUG_BASE_LENGTH)].trim_end_matches('-');
+
+    if slug.is_empty() { "post".to_string() } else { slug.to_string() }
+}
+
+// Whether a post other than `own_post_id` uses `slug` now or used it before
+pub fn slug_taken(conn: &mut PgConnection, slug: &str, own_post_id: Option<&str>) -> QueryResult<bool> {
+    let mut current = posts::table.filter(posts::slug.eq(slug)).into_boxed();
+    let mut historic = slug_history::table.filter(slug_history::slug.eq(slug)).into_boxed();
+    if let Some(post_id) = own_post_id {
+        current = current.filter(posts::post_id.ne(post_id));
+        historic = historic.filter(slug_history::post_id.ne(post_id));
+    }
+
+    Ok(current.count().get_result::<i64>(conn)? > 0 || historic.count().get_result::<i64>(conn)? > 0)
+}
+
+// A slug for `title` that no other post uses now or used before, e.g. "hello-world", then "hello-world-2", ...
+// A post may take back one of its own old slugs.
+pub fn unique_slug(conn: &mut PgConnection, title: &str, own_post_id: Option<&str>) -> QueryResult<String> {
+    let base = slug_base(title);
+    let mut candidate = base.clone();
+    let mut suffix = 1;
+
+    while slug_taken(conn, &candidate, own_post_id)? {
+        suffix += 1;
+        candidate = format!("{}-{}", base, suffix);
+    }
+    Ok(candidate)
+}
+
+// Re-slugs a post from its current title, keeping the old slug in slug_history so links to it still resolve.
+// Returns the post's slug afterwards, which is unchanged when the title still maps to it.
+pub fn refresh_post_slug(conn: &mut PgConnection, post_id: &str) -> QueryResult<String> {
+    let (title, current_slug) =
+        posts::table
+            .filter(posts::post_id.eq(post_id))
+            .filter(posts::deleted_at.is_null())
+            .select((posts::title, posts::slug))
+            .first::<(String, String)>(conn)?;
+
+    // Nothing to do if the title still produces the slug the post already has
+    let base = slug_base(&title);
+    let still_matches =
+        current_slug == base
+            || current_slug
+                .strip_prefix(&format!("{}-", base))
+                .is_some_and(|suffix| suffix.parse::<u32>().is_ok());
+    if still_matches {
+        return Ok(current_slug);
+    }
+
+    let new_slug = unique_slug(conn, &title, Some(post_id))?;
+
+    // The new slug may be one this post had before, it is current again so it leaves the history
+    diesel::delete(slug_history::table.filter(slug_history::slug.eq(&new_slug))).execute(conn)?;
+    diesel::insert_into(slug_history::table)
+        .values(&NewSlugHistory {
+            post_id: post_id.to_string(),
+            slug: current_slug,
+            created_at: chrono::Utc::now().naive_utc(),
+        })
+        .on_conflict(slug_history::slug)
+        .do_nothing()
+        .execute(conn)?;
+    diesel::update(posts::table.filter(posts::post_id.eq(post_id)).filter(posts::deleted_at.is_null()))
+        .set(posts::slug.eq(&new_slug))
+        .execute(conn)?;
+
+    Ok(new_slug)
+}
+
+#[get("/blog/post/{slug}")]
+async fn get_by_slug(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    lang_query: web::Query<LangQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let preferred = match preferred_locales(&req, lang_query.lang.as_deref()) {
+        Ok(preferred) => preferred,
+        Err(response) => return Ok(response),
+    };
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let show_unpublished = can_view_unpublished(&req).await;
+    let visible = || {
+        let mut query = posts::table.filter(posts::deleted_at.is_null()).into_boxed();
+        if !show_unpublished {
+            query = query.filter(posts::status.eq(PostStatus::Published));
+        }
+        query
+    };
+
+    let post =
+        visible()
+            .filter(posts::slug.eq(&slug))
+            .select(Post::as_select())
+            .first(&mut conn)
+            .optional()
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e)))?;
+
+    if let Some(post) = post {
+        let post =
+            attach_tags(&mut conn, vec![post])
+                .and_then(|mut posts| translate_posts(&mut conn, &mut posts, &preferred).map(|_| posts));
+        return match post {
+            Ok(posts) => {
+                if !show_unpublished {
+                    count_view(&req, &posts[0].post.post_id);
+                }
+                // Reactions, tags, series navigation and translations change the body without touching updated_at
+                let updated_at = posts[0].post.updated_at;
+                match serde_json::to_vec(&Rendered::all(posts, render_query.render).remove(0)) {
+                    Ok(body) => Ok(vary_on_language(conditional_body(&req, Some(versioned_etag(updated_at, &body)), None, body))),
+                    Err(e) => {
+                        eprintln!("Error serializing response: {:?}", e);
+                        Ok(HttpResponse::InternalServerError().finish())
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Error loading post tags: {:?}", e);
+                Ok(HttpResponse::InternalServerError().finish())
+            }
+        };
+    }
+
+    // An old slug sends the client on to the post's current one
+    let current_slug =
+        visible()
+            .filter(
+                posts::post_id.nullable().eq(
+                    slug_history::table
+                        .filter(slug_history::slug.eq(&slug))
+                        .select(slug_history::post_id)
+                        .single_value(),
+                ),
+            )
+            .select(posts::slug)
+            .first::<String>(&mut conn)
+            .optional()
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving slug history: {}", e)))?;
+
+    match current_slug {
+        Some(current_slug) => {
+            let mut location = format!("/blog/post/{}", current_slug);
+            if !req.query_string().is_empty() {
+                location = format!("{}?{}", location, req.query_string());
+            }
+            Ok(HttpResponse::MovedPermanently()
+                .insert_header((header::LOCATION, location))
+                .finish())
+        }
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_base() {
+        assert_eq!(slug_base("Hello, World!"), "hello-world");
+        assert_eq!(slug_base("???"), "post");
+        assert_eq!(slug_base(&"a".repeat(300)).len(), MAX_SLUG_BASE_LENGTH);
+    }
+}
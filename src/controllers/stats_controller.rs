@@ -0,0 +1,179 @@
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Integer, Text, Timestamp};
+use serde::{Deserialize, Serialize};
+
+use crate::connectors::content_cache::{CacheKind, CacheLookup};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::tag_controller::{load_tag_cloud, TagCount};
+
+// How many of everything there is. Posts are the published ones, trashed posts, skills and worklog are left out.
+#[derive(Debug, QueryableByName, Serialize, Deserialize)]
+pub struct ContentTotals {
+    #[diesel(sql_type = BigInt)]
+    pub posts: i64,
+    #[diesel(sql_type = BigInt)]
+    pub skills: i64,
+    #[diesel(sql_type = BigInt)]
+    pub worklog: i64,
+    #[diesel(sql_type = BigInt)]
+    pub users: i64,
+}
+
+// Published posts in one month, counted by publish_at or created_at for posts published straight away
+#[derive(Debug, QueryableByName, Serialize, Deserialize)]
+pub struct MonthCount {
+    #[diesel(sql_type = Integer)]
+    pub year: i32,
+    #[diesel(sql_type = Integer)]
+    pub month: i32,
+    #[diesel(sql_type = BigInt)]
+    pub posts: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentStats {
+    pub totals: ContentTotals,
+    pub posts_per_month: Vec<MonthCount>,
+    pub posts_per_tag: Vec<TagCount>,
+}
+
+// One month of the archive as it comes out of Postgres, the arrays line up and go newest first
+#[derive(Debug, QueryableByName)]
+struct ArchiveRow {
+    #[diesel(sql_type = Integer)]
+    year: i32,
+    #[diesel(sql_type = Integer)]
+    month: i32,
+    #[diesel(sql_type = Array<Text>)]
+    post_ids: Vec<String>,
+    #[diesel(sql_type = Array<Text>)]
+    slugs: Vec<String>,
+    #[diesel(sql_type = Array<Text>)]
+    titles: Vec<String>,
+    #[diesel(sql_type = Array<Timestamp>)]
+    published_at: Vec<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivePost {
+    pub post_id: String,
+    pub slug: String,
+    pub title: String,
+    pub published_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveMonth {
+    pub year: i32,
+    pub month: i32,
+    pub posts: Vec<ArchivePost>,
+}
+
+pub fn load_content_totals(conn: &mut PgConnection) -> QueryResult<ContentTotals> {
+    diesel::sql_query(
+        "SELECT (SELECT COUNT(*) FROM posts WHERE status = 'published' AND deleted_at IS NULL) AS posts,
+                (SELECT COUNT(*) FROM skills WHERE deleted_at IS NULL) AS skills,
+                (SELECT COUNT(*) FROM worklog WHERE deleted_at IS NULL) AS worklog,
+                (SELECT COUNT(*) FROM users) AS users",
+    )
+        .get_result::<ContentTotals>(conn)
+}
+
+// Months without posts are left out, newest month first
+pub fn load_posts_per_month(conn: &mut PgConnection) -> QueryResult<Vec<MonthCount>> {
+    diesel::sql_query(
+        "SELECT EXTRACT(YEAR FROM month)::INT AS year, EXTRACT(MONTH FROM month)::INT AS month, posts
+         FROM (SELECT date_trunc('month', COALESCE(publish_at, created_at)) AS month, COUNT(*) AS posts
+               FROM posts
+               WHERE status = 'published' AND deleted_at IS NULL
+               GROUP BY 1) per_month
+         ORDER BY per_month.month DESC",
+    )
+        .load::<MonthCount>(conn)
+}
+
+// Published posts grouped by the month they came out in, newest first throughout
+pub fn load_archive(conn: &mut PgConnection) -> QueryResult<Vec<ArchiveMonth>> {
+    let rows =
+        diesel::sql_query(
+            "SELECT EXTRACT(YEAR FROM month)::INT AS year, EXTRACT(MONTH FROM month)::INT AS month,
+                    array_agg(post_id ORDER BY published_at DESC, id DESC) AS post_ids,
+                    array_agg(slug ORDER BY published_at DESC, id DESC) AS slugs,
+                    array_agg(title ORDER BY published_at DESC, id DESC) AS titles,
+                    array_agg(published_at ORDER BY published_at DESC, id DESC) AS published_at
+             FROM (SELECT id, post_id, slug, title, COALESCE(publish_at, created_at) AS published_at,
+                          date_trunc('month', COALESCE(publish_at, created_at)) AS month
+                   FROM posts
+                   WHERE status = 'published' AND deleted_at IS NULL) published
+             GROUP BY published.month
+             ORDER BY published.month DESC",
+        )
+            .load::<ArchiveRow>(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ArchiveMonth {
+            year: row.year,
+            month: row.month,
+            posts: row.post_ids
+                .into_iter()
+                .zip(row.slugs)
+                .zip(row.titles)
+                .zip(row.published_at)
+                .map(|(((post_id, slug), title), published_at)| ArchivePost { post_id, slug, title, published_at })
+                .collect(),
+        })
+        .collect())
+}
+
+pub fn load_content_stats(conn: &mut PgConnection) -> QueryResult<ContentStats> {
+    Ok(ContentStats {
+        totals: load_content_totals(conn)?,
+        posts_per_month: load_posts_per_month(conn)?,
+        posts_per_tag: load_tag_cloud(conn)?,
+    })
+}
+
+#[get("/blog/stats")]
+async fn get_content_stats(
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match web::block(move || load_content_stats(&mut conn)).await {
+        Ok(Ok(stats)) => Ok(HttpResponse::Ok().json(stats)),
+        Ok(Err(e)) => {
+            eprintln!("Error loading content stats: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+        Err(e) => Err(actix_web::error::ErrorInternalServerError(format!("Loading content stats failed: {}", e))),
+    }
+}
+
+#[get("/blog/archive")]
+async fn get_archive(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut cache = CacheLookup::new(&req, CacheKind::Posts, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match web::block(move || load_archive(&mut conn)).await {
+        Ok(Ok(archive)) => Ok(cache.respond(&req, None, &archive).await),
+        Ok(Err(e)) => {
+            eprintln!("Error loading archive: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+        Err(e) => Err(actix_web::error::ErrorInternalServerError(format!("Loading the archive failed: {}", e))),
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sql_types::{BigInt, Text};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::author_controller::load_authors;
+use crate::controllers::blog_controller::enqueue_post_event;
+use crate::controllers::series_controller::load_series_navigation;
+use crate::models::PostResponse::PostResponse;
+use crate::models::Reaction::ReactionCounts;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::tag_schema::{post_tags, tags};
+use crate::table_models::blog_models::Post;
+use crate::table_models::tag_models::{NewPostTag, NewTag, Tag, TagLabel};
+use crate::utils::slug::slugify;
+
+#[derive(Serialize, Deserialize)]
+pub struct TagInput {
+    pub name: String,
+}
+
+// One entry of the tag cloud, only published posts are counted
+#[derive(Debug, QueryableByName, Serialize, Deserialize)]
+pub struct TagCount {
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = Text)]
+    pub slug: String,
+    #[diesel(sql_type = BigInt)]
+    pub post_count: i64,
+}
+
+// Trims the given tag names and pairs each with its slug, names that slug to the same value are only kept once
+pub fn normalize_tag_names(names: &[String]) -> Result<Vec<TagLabel>, String> {
+    let mut labels: Vec<TagLabel> = Vec::new();
+
+    for name in names {
+        let name = name.trim();
+        let slug = slugify(name);
+        if slug.is_empty() {
+            return Err(format!("Tag '{}' needs at least one letter or digit", name));
+        }
+        if !labels.iter().any(|label| label.slug == slug) {
+            labels.push(TagLabel { name: name.to_string(), slug });
+        }
+    }
+
+    Ok(labels)
+}
+
+// Replaces the tags of a post, tags that don't exist yet are created on the way
+pub fn set_post_tags(conn: &mut PgConnection, post_id: &str, labels: &[TagLabel]) -> QueryResult<()> {
+    diesel::delete(post_tags::table.filter(post_tags::post_id.eq(post_id))).execute(conn)?;
+    if labels.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let new_tags: Vec<NewTag> =
+        labels
+            .iter()
+            .map(|label| NewTag {
+                name: label.name.clone(),
+                slug: label.slug.clone(),
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+
+    diesel::insert_into(tags::table)
+        .values(&new_tags)
+        .on_conflict(tags::slug)
+        .do_nothing()
+        .execute(conn)?;
+
+    let tag_ids =
+        tags::table
+            .filter(tags::slug.eq_any(labels.iter().map(|label| label.slug.clone())))
+            .select(tags::id)
+            .load::<i32>(conn)?;
+
+    let new_post_tags: Vec<NewPostTag> =
+        tag_ids
+            .into_iter()
+            .map(|tag_id| NewPostTag { post_id: post_id.to_string(), tag_id })
+            .collect();
+
+    diesel::insert_into(post_tags::table)
+        .values(&new_post_tags)
+        .execute(conn)?;
+    Ok(())
+}
+
+// Loads the tags of every post in one go, keyed by post_id
+pub fn load_post_tags(conn: &mut PgConnection, post_ids: &[String]) -> QueryResult<HashMap<String, Vec<TagLabel>>> {
+    let rows =
+        post_tags::table
+            .inner_join(tags::table)
+            .filter(post_tags::post_id.eq_any(post_ids))
+            .order(tags::name.asc())
+            .select((post_tags::post_id, (tags::name, tags::slug)))
+            .load::<(String, TagLabel)>(conn)?;
+
+    let mut tags_by_post: HashMap<String, Vec<TagLabel>> = HashMap::new();
+    for (post_id, label) in rows {
+        tags_by_post.entry(post_id).or_default().push(label);
+    }
+    Ok(tags_by_post)
+}
+
+// Pairs posts with their tags, author, place in a series and reaction totals for the response body
+pub fn attach_tags(conn: &mut PgConnection, posts: Vec<Post>) -> QueryResult<Vec<PostResponse>> {
+    let post_ids: Vec<String> = posts.iter().map(|post| post.post_id.clone()).collect();
+    let mut tags_by_post = load_post_tags(conn, &post_ids)?;
+    let author_ids: Vec<String> = posts.iter().filter_map(|post| post.author_id.clone()).collect();
+    let authors = load_authors(conn, &author_ids)?;
+    let mut series_by_post = load_series_navigation(conn, &posts)?;
+
+    Ok(posts
+        .into_iter()
+        .map(|post| {
+            let tags = tags_by_post.remove(&post.post_id).unwrap_or_default();
+            let author = post.author_id.as_ref().and_then(|author_id| authors.get(author_id)).cloned();
+            let series = series_by_post.remove(&post.id);
+            let reactions = ReactionCounts::from(&post);
+            PostResponse { post, tags, author, series, reactions, locale: None }
+        })
+        .collect())
+}
+
+pub fn load_tag_cloud(conn: &mut PgConnection) -> QueryResult<Vec<TagCount>> {
+    diesel::sql_query(
+        "SELECT t.name, t.slug, COUNT(p.id) AS post_count
+         FROM tags t
+         JOIN post_tags pt ON pt.tag_id = t.id
+         JOIN posts p ON p.post_id = pt.post_id AND p.status = 'published' AND p.deleted_at IS NULL
+         GROUP BY t.id
+         ORDER BY post_count DESC, t.name ASC",
+    )
+        .load::<TagCount>(conn)
+}
+
+fn tag_not_found(slug: &str) -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("application/json")
+        .json(json!({
+            "error": format!("Tag '{}' not found", slug)
+        }))
+}
+
+fn tag_conflict(slug: &str) -> HttpResponse {
+    HttpResponse::Conflict()
+        .content_type("application/json")
+        .json(json!({
+            "error": format!("A tag with slug '{}' already exists", slug)
+        }))
+}
+
+fn new_tag_label(name: &str) -> Result<TagLabel, HttpResponse> {
+    normalize_tag_names(&[name.to_string()])
+        .map(|mut labels| labels.remove(0))
+        .map_err(|message| HttpResponse::BadRequest().json(json!({ "error": message })))
+}
+
+#[post("/blog/tag/create")]
+async fn create_tag(
+    pool: web::Data<DbPool>,
+    tag: web::Json<TagInput>,
+) -> Result<HttpResponse, Error> {
+    let label = match new_tag_label(&tag.name) {
+        Ok(label) => label,
+        Err(response) => return Ok(response),
+    };
+
+    let now: NaiveDateTime = chrono::Utc::now().naive_utc();
+    let new_tag =
+        NewTag {
+            name: label.name,
+            slug: label.slug,
+            created_at: now,
+            updated_at: now,
+        };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match diesel::insert_into(tags::table)
+        .values(&new_tag)
+        .returning(Tag::as_returning())
+        .get_result(&mut conn)
+    {
+        Ok(tag) => Ok(HttpResponse::Created().json(tag)),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(tag_conflict(&new_tag.slug)),
+        Err(e) => {
+            eprintln!("Error inserting new tag: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/blog/tag/get/all")]
+async fn get_all_tags(
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match tags::table
+        .order(tags::name.asc())
+        .select(Tag::as_select())
+        .load(&mut conn)
+    {
+        Ok(tags) => Ok(HttpResponse::Ok().json(tags)),
+        Err(e) => {
+            eprintln!("Error loading tags: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/blog/tag/cloud")]
+async fn get_tag_cloud(
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match load_tag_cloud(&mut conn) {
+        Ok(cloud) => Ok(HttpResponse::Ok().json(cloud)),
+        Err(e) => {
+            eprintln!("Error loading tag cloud: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/blog/tag/retrieve/{slug}")]
+async fn get_tag(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match tags::table
+        .filter(tags::slug.eq(&slug))
+        .select(Tag::as_select())
+        .first(&mut conn)
+        .optional()
+    {
+        Ok(Some(tag)) => Ok(HttpResponse::Ok().json(tag)),
+        Ok(None) => Ok(tag_not_found(&slug)),
+        Err(e) => {
+            eprintln!("Error retrieving tag: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Posts show their tags by name, so renaming or deleting a tag changes each live post carrying it for webhooks.
+// Returns the post_ids of those posts. Call it in the write's transaction, before a delete takes the links away.
+fn enqueue_tagged_post_events(conn: &mut PgConnection, tag_id: i32) -> QueryResult<Vec<String>> {
+    let post_ids: Vec<String> =
+        post_tags::table
+            .inner_join(posts::table.on(posts::post_id.eq(post_tags::post_id)))
+            .filter(post_tags::tag_id.eq(tag_id))
+            .filter(posts::deleted_at.is_null())
+            .select(posts::post_id)
+            .load(conn)?;
+    for post_id in &post_ids {
+        enqueue_post_event(conn, WebhookEvent::PostUpdated, post_id)?;
+    }
+    Ok(post_ids)
+}
+
+// Renaming a tag also moves it to the slug of the new name
+#[put("/blog/tag/update/{slug}")]
+async fn update_tag(
+    path: web::Path<String>,
+    tag: web::Json<TagInput>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let label = match new_tag_label(&tag.name) {
+        Ok(label) => label,
+        Err(response) => return Ok(response),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let updated =
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let updated =
+                diesel::update(tags::table.filter(tags::slug.eq(&slug)))
+                    .set((
+                        tags::name.eq(&label.name),
+                        tags::slug.eq(&label.slug),
+                        tags::updated_at.eq(chrono::Utc::now().naive_utc()),
+                    ))
+                    .returning(Tag::as_returning())
+                    .get_result(conn)
+                    .optional()?;
+            let Some(tag) = updated else { return Ok(None) };
+            let post_ids = enqueue_tagged_post_events(conn, tag.id)?;
+            Ok(Some((tag, post_ids)))
+        });
+
+    match updated {
+        // Posts show their tags by name, so cached posts carrying this one are out of date
+        Ok(Some((tag, post_ids))) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            for post_id in &post_ids {
+                publish_live(&req, LiveKind::Post, LiveAction::Updated, post_id).await;
+            }
+            Ok(HttpResponse::Ok().json(tag))
+        }
+        Ok(None) => Ok(tag_not_found(&slug)),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(tag_conflict(&label.slug)),
+        Err(e) => {
+            eprintln!("Error updating tag: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Deleting a tag detaches it from every post, the posts themselves are left alone
+#[delete("/blog/tag/single/{slug}")]
+async fn delete_tag(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let slug = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let deleted =
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let tag =
+                tags::table
+                    .filter(tags::slug.eq(&slug))
+                    .select((tags::id, tags::name))
+                    .first::<(i32, String)>(conn)
+                    .optional()?;
+            let Some((tag_id, name)) = tag else { return Ok(None) };
+
+            let post_ids = enqueue_tagged_post_events(conn, tag_id)?;
+            diesel::delete(tags::table.find(tag_id)).execute(conn)?;
+            Ok(Some((name, post_ids)))
+        });
+
+    match deleted {
+        Ok(Some((name, post_ids))) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            for post_id in &post_ids {
+                publish_live(&req, LiveKind::Post, LiveAction::Updated, post_id).await;
+            }
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .json(json!({ "message": format!("Tag '{}' has been deleted", name) })))
+        }
+        Ok(None) => Ok(tag_not_found(&slug)),
+        Err(e) => {
+            eprintln!("Error deleting tag: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tag_names_dedupes_by_slug() {
+        let names = vec!["Rust".to_string(), " rust ".to_string(), "Web Dev".to_string()];
+        let labels = normalize_tag_names(&names).unwrap();
+
+        assert_eq!(
+            labels,
+            vec![
+                TagLabel { name: "Rust".to_string(), slug: "rust".to_string() },
+                TagLabel { name: "Web Dev".to_string(), slug: "web-dev".to_string() },
+            ]
+        );
+        assert!(normalize_tag_names(&["???".to_string()]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod tag_controller_spec {
+    use crate::connectors::postgres_connector::DbPool;
+    use crate::controllers::tag_controller::*;
+    use crate::models::PostStatus::PostStatus;
+    use crate::schemas::webhook_schema::webhook_events;
+    use crate::table_models::blog_models::NewPost;
+    use actix_web::{http::StatusCode, test, web, App};
+    use diesel::r2d2::ConnectionManager;
+    use diesel::{r2d2, PgConnection};
+    use dotenv::dotenv;
+    use serde_json::{json, Value};
+    use std::env;
+
+    pub fn establish_connection() -> DbPool {
+        dotenv().ok();
+        let database_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        r2d2::Pool::builder().build(manager).expect("Failed to create pool.")
+    }
+
+    #[actix_rt::test]
+    async fn test_tag_crud() {
+        let pool = web::Data::new(establish_connection());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(pool.clone())
+                .service(create_tag)
+                .service(get_all_tags)
+                .service(get_tag)
+                .service(update_tag)
+                .service(delete_tag),
+        )
+            .await;
+
+        let req = test::TestRequest::post()
+            .uri("/blog/tag/create")
+            .set_json(json!({ "name": "Tag Spec Crud" }))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["slug"], "tag-spec-crud");
+
+        // Same slug again is a conflict
+        let req = test::TestRequest::post()
+            .uri("/blog/tag/create")
+            .set_json(json!({ "name": "tag spec crud" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .uri("/blog/tag/create")
+            .set_json(json!({ "name": "  " }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get().uri("/blog/tag/get/all").to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(json_body.as_array().unwrap().iter().any(|tag| tag["slug"] == "tag-spec-crud"));
+
+        // A post carrying the tag changes with it, for webhooks
+        let mut conn = pool.get().expect("Failed to get connection from pool");
+        let started_at = chrono::Utc::now().naive_utc();
+        diesel::insert_into(posts::table)
+            .values(&NewPost {
+                post_id: "tag_spec_post".to_string(),
+                slug: "tag_spec_post".to_string(),
+                title: "Tagged".to_string(),
+                body: "Carries a tag.".to_string(),
+                created_at: started_at,
+                updated_at: started_at,
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            })
+            .execute(&mut conn)
+            .expect("Failed to insert test post");
+        set_post_tags(&mut conn, "tag_spec_post", &normalize_tag_names(&["Tag Spec Crud".to_string()]).unwrap())
+            .expect("Failed to tag test post");
+        let post_updates = |conn: &mut PgConnection| {
+            webhook_events::table
+                .filter(webhook_events::event.eq(WebhookEvent::PostUpdated))
+                .filter(webhook_events::payload.like("%\"post_id\":\"tag_spec_post\"%"))
+                .filter(webhook_events::created_at.ge(started_at))
+                .count()
+                .get_result::<i64>(conn)
+                .unwrap()
+        };
+
+        let req = test::TestRequest::put()
+            .uri("/blog/tag/update/tag-spec-crud")
+            .set_json(json!({ "name": "Tag Spec Renamed" }))
+            .to_request();
+        let json_body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(json_body["name"], "Tag Spec Renamed");
+        assert_eq!(json_body["slug"], "tag-spec-renamed");
+        assert_eq!(post_updates(&mut conn), 1);
+
+        let req = test::TestRequest::get().uri("/blog/tag/retrieve/tag-spec-crud").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::delete().uri("/blog/tag/single/tag-spec-renamed").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let updates = post_updates(&mut conn);
+        diesel::delete(posts::table.filter(posts::post_id.eq("tag_spec_post")))
+            .execute(&mut conn)
+            .expect("Failed to delete test post");
+        assert_eq!(updates, 2);
+
+        let req = test::TestRequest::get().uri("/blog/tag/retrieve/tag-spec-renamed").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
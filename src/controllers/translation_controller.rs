@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{delete, get, put, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::{can_view_unpublished, enqueue_post_event};
+use crate::models::PostResponse::PostResponse;
+use crate::models::PostStatus::PostStatus;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::post_translation_schema::post_translations;
+use crate::table_models::post_translation_models::{NewPostTranslation, PostTranslation};
+use crate::utils::locale::{accept_language, negotiate_translation, normalize_locale, site_locale};
+
+// Query string for the post retrieval endpoints, ?lang=de asks for the German translation
+#[derive(Debug, Default, Deserialize)]
+pub struct LangQuery {
+    pub lang: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TranslationInput {
+    pub title: String,
+    pub body: String,
+}
+
+// The translations a post has, the original is always available besides them
+#[derive(Serialize, Deserialize)]
+pub struct PostLocales {
+    pub post_id: String,
+    pub locales: Vec<String>,
+}
+
+// The locales a reader asked for, most preferred first. ?lang wins over Accept-Language,
+// and an empty list means the original is wanted.
+pub fn preferred_locales(req: &HttpRequest, lang: Option<&str>) -> Result<Vec<String>, HttpResponse> {
+    if let Some(lang) = lang {
+        return normalize_locale(lang).map(|locale| vec![locale]).ok_or_else(|| {
+            HttpResponse::BadRequest().json(json!({ "error": format!("'{}' is not a language tag, e.g. de or pt-BR", lang) }))
+        });
+    }
+
+    Ok(req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(accept_language)
+        .unwrap_or_default())
+}
+
+// Tells caches the body depends on Accept-Language, for responses negotiated through `preferred_locales`
+pub fn vary_on_language(mut response: HttpResponse) -> HttpResponse {
+    response.headers_mut().append(header::VARY, HeaderValue::from_static("Accept-Language"));
+    response
+}
+
+// Swaps in the translated title and body of each post that has a translation matching `preferred` better
+// than the original does, the others stay in the original. Returns whether any post was translated.
+pub fn translate_posts(conn: &mut PgConnection, posts: &mut [PostResponse], preferred: &[String]) -> QueryResult<bool> {
+    if preferred.is_empty() || posts.is_empty() {
+        return Ok(false);
+    }
+    let original = site_locale();
+
+    let post_ids: Vec<&String> = posts.iter().map(|post| &post.post.post_id).collect();
+    let translations =
+        post_translations::table
+            .filter(post_translations::post_id.eq_any(post_ids))
+            .select(PostTranslation::as_select())
+            .load(conn)?;
+
+    let mut by_post: HashMap<String, Vec<PostTranslation>> = HashMap::new();
+    for translation in translations {
+        by_post.entry(translation.post_id.clone()).or_default().push(translation);
+    }
+
+    let mut translated = false;
+    for post in posts.iter_mut() {
+        let Some(translations) = by_post.remove(&post.post.post_id) else { continue };
+        let locales: Vec<String> = translations.iter().map(|translation| translation.locale.clone()).collect();
+        let Some(locale) = negotiate_translation(preferred, &original, &locales) else { continue };
+
+        if let Some(translation) = translations.into_iter().find(|translation| translation.locale == locale) {
+            post.post.title = translation.title;
+            post.post.body = translation.body;
+            post.locale = Some(translation.locale);
+            translated = true;
+        }
+    }
+    Ok(translated)
+}
+
+fn post_exists(conn: &mut PgConnection, post_id: &str, show_unpublished: bool) -> QueryResult<bool> {
+    let mut query = posts::table.filter(posts::post_id.eq(post_id)).filter(posts::deleted_at.is_null()).into_boxed();
+    if !show_unpublished {
+        query = query.filter(posts::status.eq(PostStatus::Published));
+    }
+    query.count().get_result::<i64>(conn).map(|count| count > 0)
+}
+
+fn post_not_found(post_id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "error": format!("Blog post with ID '{}' not found", post_id)
+    }))
+}
+
+fn parse_locale(raw: &str) -> Result<String, HttpResponse> {
+    normalize_locale(raw).ok_or_else(|| {
+        HttpResponse::BadRequest().json(json!({ "error": format!("'{}' is not a language tag, e.g. de or pt-BR", raw) }))
+    })
+}
+
+#[get("/blog/post/{post_id}/locales")]
+async fn get_post_locales(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let post_id = path.into_inner();
+    let show_unpublished = can_view_unpublished(&req).await;
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match post_exists(&mut conn, &post_id, show_unpublished) {
+        Ok(true) => {}
+        Ok(false) => return Ok(post_not_found(&post_id)),
+        Err(e) => return Err(actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e))),
+    }
+
+    match post_translations::table
+        .filter(post_translations::post_id.eq(&post_id))
+        .order(post_translations::locale.asc())
+        .select(post_translations::locale)
+        .load::<String>(&mut conn)
+    {
+        Ok(locales) => Ok(HttpResponse::Ok().json(PostLocales { post_id, locales })),
+        Err(e) => {
+            eprintln!("Error loading post locales: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Creates or replaces the translation of a post into one locale
+#[put("/blog/post/{post_id}/translations/{locale}")]
+async fn put_post_translation(
+    path: web::Path<(String, String)>,
+    translation: web::Json<TranslationInput>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (post_id, raw_locale) = path.into_inner();
+    let locale = match parse_locale(&raw_locale) {
+        Ok(locale) => locale,
+        Err(response) => return Ok(response),
+    };
+    let translation_input = translation.into_inner();
+    if translation_input.title.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({ "error": "Translated title must not be empty" })));
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match post_exists(&mut conn, &post_id, true) {
+        Ok(true) => {}
+        Ok(false) => return Ok(post_not_found(&post_id)),
+        Err(e) => return Err(actix_web::error::ErrorInternalServerError(format!("Error retrieving post: {}", e))),
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let new_translation = NewPostTranslation {
+        post_id,
+        locale,
+        title: translation_input.title,
+        body: translation_input.body,
+        created_at: now,
+        updated_at: now,
+    };
+
+    // A new or changed translation is an update of the post for webhooks
+    let saved =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let translation =
+                diesel::insert_into(post_translations::table)
+                    .values(&new_translation)
+                    .on_conflict((post_translations::post_id, post_translations::locale))
+                    .do_update()
+                    .set((
+                        post_translations::title.eq(&new_translation.title),
+                        post_translations::body.eq(&new_translation.body),
+                        post_translations::updated_at.eq(now),
+                    ))
+                    .returning(PostTranslation::as_returning())
+                    .get_result(conn)?;
+            enqueue_post_event(conn, WebhookEvent::PostUpdated, &new_translation.post_id)?;
+            Ok(translation)
+        });
+
+    match saved {
+        Ok(translation) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            publish_live(&req, LiveKind::Post, LiveAction::Updated, &translation.post_id).await;
+            Ok(HttpResponse::Ok().json(translation))
+        }
+        Err(e) => {
+            eprintln!("Error saving translation: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[delete("/blog/post/{post_id}/translations/{locale}")]
+async fn delete_post_translation(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (post_id, raw_locale) = path.into_inner();
+    let locale = match parse_locale(&raw_locale) {
+        Ok(locale) => locale,
+        Err(response) => return Ok(response),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let deleted =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let deleted =
+                diesel::delete(
+                    post_translations::table
+                        .filter(post_translations::post_id.eq(&post_id))
+                        .filter(post_translations::locale.eq(&locale)),
+                )
+                    .execute(conn)?;
+            if deleted > 0 {
+                enqueue_post_event(conn, WebhookEvent::PostUpdated, &post_id)?;
+            }
+            Ok(deleted)
+        });
+
+    match deleted {
+        Ok(0) => Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("Blog post '{}' has no '{}' translation", post_id, locale)
+        }))),
+        Ok(_) => {
+            invalidate_cached(&req, CacheKind::Posts).await;
+            publish_live(&req, LiveKind::Post, LiveAction::Updated, &post_id).await;
+            Ok(HttpResponse::Ok().json(json!({ "message": format!("The '{}' translation of '{}' has been deleted", locale, post_id) })))
+        }
+        Err(e) => {
+            eprintln!("Error deleting translation: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
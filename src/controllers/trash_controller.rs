@@ -0,0 +1,388 @@
+use actix_web::{delete, get, post, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::connectors::content_cache::{invalidate_cached, CacheKind};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::enqueue_post_row_event;
+use crate::controllers::login_controller::session_from_request;
+use crate::controllers::skills_controller::enqueue_skill_row_event;
+use crate::controllers::worklog_controller::enqueue_worklog_row_event;
+use crate::models::SessionData::SessionData;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+use crate::schemas::comment_schema::comments;
+use crate::schemas::post_revision_schema::post_revisions;
+use crate::schemas::post_translation_schema::post_translations;
+use crate::schemas::post_reaction_schema::post_reactions;
+use crate::schemas::post_view_schema::post_views;
+use crate::schemas::skills_schema::skills;
+use crate::schemas::slug_history_schema::slug_history;
+use crate::schemas::tag_schema::post_tags;
+use crate::schemas::worklog_schema::worklog;
+use crate::table_models::blog_models::Post;
+use crate::table_models::skills_models::Skill;
+use crate::table_models::worklog_models::Worklog;
+
+// The content types that can be trashed, as used in /trash/{kind}/... paths
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashKind {
+    Posts,
+    Skills,
+    Worklog,
+}
+
+impl TrashKind {
+    pub const ALL: [TrashKind; 3] = [TrashKind::Posts, TrashKind::Skills, TrashKind::Worklog];
+
+    pub fn cache_kind(&self) -> CacheKind {
+        match self {
+            TrashKind::Posts => CacheKind::Posts,
+            TrashKind::Skills => CacheKind::Skills,
+            TrashKind::Worklog => CacheKind::Worklog,
+        }
+    }
+
+    pub fn live_kind(&self) -> LiveKind {
+        match self {
+            TrashKind::Posts => LiveKind::Post,
+            TrashKind::Skills => LiveKind::Skill,
+            TrashKind::Worklog => LiveKind::Worklog,
+        }
+    }
+
+    // Restored items come back as created and purged ones go as deleted, for webhooks. `id` is the
+    // row's own id, since a trashed copy can share its public id with a live item.
+    pub fn enqueue_event(&self, conn: &mut PgConnection, restored: bool, id: i32) -> QueryResult<i32> {
+        match (self, restored) {
+            (TrashKind::Posts, true) => enqueue_post_row_event(conn, WebhookEvent::PostCreated, id),
+            (TrashKind::Posts, false) => enqueue_post_row_event(conn, WebhookEvent::PostDeleted, id),
+            (TrashKind::Skills, true) => enqueue_skill_row_event(conn, WebhookEvent::SkillCreated, id),
+            (TrashKind::Skills, false) => enqueue_skill_row_event(conn, WebhookEvent::SkillDeleted, id),
+            (TrashKind::Worklog, true) => enqueue_worklog_row_event(conn, WebhookEvent::WorklogCreated, id),
+            (TrashKind::Worklog, false) => enqueue_worklog_row_event(conn, WebhookEvent::WorklogDeleted, id),
+        }
+    }
+}
+
+// Everything in the trash, most recently deleted first
+#[derive(Serialize, Deserialize)]
+pub struct TrashListing {
+    pub posts: Vec<Post>,
+    pub skills: Vec<Skill>,
+    pub worklog: Vec<Worklog>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RestoreOutcome {
+    Restored,
+    NotInTrash,
+    // A live item already uses the same id
+    Conflict,
+}
+
+pub fn load_trash(conn: &mut PgConnection) -> QueryResult<TrashListing> {
+    Ok(TrashListing {
+        posts:
+            posts::table
+                .filter(posts::deleted_at.is_not_null())
+                .order((posts::deleted_at.desc(), posts::id.desc()))
+                .select(Post::as_select())
+                .load(conn)?,
+        skills:
+            skills::table
+                .filter(skills::deleted_at.is_not_null())
+                .order((skills::deleted_at.desc(), skills::id.desc()))
+                .select(Skill::as_select())
+                .load(conn)?,
+        worklog:
+            worklog::table
+                .filter(worklog::deleted_at.is_not_null())
+                .order((worklog::deleted_at.desc(), worklog::id.desc()))
+                .select(Worklog::as_select())
+                .load(conn)?,
+    })
+}
+
+// Takes an item back out of the trash. Restored posts come back with their tags, comments, revisions and old slugs.
+pub fn restore_item(conn: &mut PgConnection, kind: TrashKind, item_id: &str) -> QueryResult<RestoreOutcome> {
+    conn.transaction(|conn| {
+        let (trashed, live) =
+            match kind {
+                TrashKind::Posts => (
+                    posts::table.filter(posts::post_id.eq(item_id)).filter(posts::deleted_at.is_not_null())
+                        .select(posts::id).first::<i32>(conn).optional()?,
+                    posts::table.filter(posts::post_id.eq(item_id)).filter(posts::deleted_at.is_null())
+                        .count().get_result::<i64>(conn)?,
+                ),
+                TrashKind::Skills => (
+                    skills::table.filter(skills::skill_id.eq(item_id)).filter(skills::deleted_at.is_not_null())
+                        .select(skills::id).first::<i32>(conn).optional()?,
+                    skills::table.filter(skills::skill_id.eq(item_id)).filter(skills::deleted_at.is_null())
+                        .count().get_result::<i64>(conn)?,
+                ),
+                TrashKind::Worklog => (
+                    worklog::table.filter(worklog::worklog_id.eq(item_id)).filter(worklog::deleted_at.is_not_null())
+                        .select(worklog::id).first::<i32>(conn).optional()?,
+                    worklog::table.filter(worklog::worklog_id.eq(item_id)).filter(worklog::deleted_at.is_null())
+                        .count().get_result::<i64>(conn)?,
+                ),
+            };
+
+        let id = match trashed {
+            Some(id) => id,
+            None => return Ok(RestoreOutcome::NotInTrash),
+        };
+        if live > 0 {
+            return Ok(RestoreOutcome::Conflict);
+        }
+
+        // Coming back is a change too, so feeds and conditional reads stop answering 304 with the old version
+        let no_longer_deleted: Option<NaiveDateTime> = None;
+        let now = chrono::Utc::now().naive_utc();
+        match kind {
+            TrashKind::Posts => diesel::update(posts::table.find(id))
+                .set((posts::deleted_at.eq(no_longer_deleted), posts::updated_at.eq(now)))
+                .execute(conn)?,
+            TrashKind::Skills => diesel::update(skills::table.find(id))
+                .set((skills::deleted_at.eq(no_longer_deleted), skills::updated_at.eq(now)))
+                .execute(conn)?,
+            TrashKind::Worklog => diesel::update(worklog::table.find(id))
+                .set((worklog::deleted_at.eq(no_longer_deleted), worklog::updated_at.eq(now)))
+                .execute(conn)?,
+        };
+        kind.enqueue_event(conn, true, id)?;
+        Ok(RestoreOutcome::Restored)
+    })
+}
+
+// Permanently removes trashed posts along with everything hanging off them, returns how many posts went
+fn purge_posts(conn: &mut PgConnection, item_id: Option<&str>, deleted_before: Option<NaiveDateTime>) -> QueryResult<usize> {
+    let mut query = posts::table.filter(posts::deleted_at.is_not_null()).into_boxed();
+    if let Some(item_id) = item_id {
+        query = query.filter(posts::post_id.eq(item_id));
+    }
+    if let Some(deleted_before) = deleted_before {
+        query = query.filter(posts::deleted_at.le(deleted_before));
+    }
+    let purged: Vec<(i32, String)> = query.select((posts::id, posts::post_id)).load(conn)?;
+    if purged.is_empty() {
+        return Ok(0);
+    }
+
+    let post_ids: Vec<&String> = purged.iter().map(|(_, post_id)| post_id).collect();
+
+    // A live post reusing the same post_id keeps its tags, comments, revisions, slugs, view counts, reactions and translations,
+    // and as far as webhooks are concerned it hasn't gone anywhere
+    let live_post_ids: Vec<String> =
+        posts::table
+            .filter(posts::post_id.eq_any(&post_ids))
+            .filter(posts::deleted_at.is_null())
+            .select(posts::post_id)
+            .load(conn)?;
+    let orphaned: Vec<&String> = post_ids.iter().copied().filter(|post_id| !live_post_ids.contains(post_id)).collect();
+    for (id, post_id) in &purged {
+        if !live_post_ids.contains(post_id) {
+            TrashKind::Posts.enqueue_event(conn, false, *id)?;
+        }
+    }
+
+    diesel::delete(post_revisions::table.filter(post_revisions::post_id.eq_any(&orphaned))).execute(conn)?;
+    diesel::delete(post_tags::table.filter(post_tags::post_id.eq_any(&orphaned))).execute(conn)?;
+    diesel::delete(comments::table.filter(comments::post_id.eq_any(&orphaned))).execute(conn)?;
+    diesel::delete(slug_history::table.filter(slug_history::post_id.eq_any(&orphaned))).execute(conn)?;
+    diesel::delete(post_views::table.filter(post_views::post_id.eq_any(&orphaned))).execute(conn)?;
+    diesel::delete(post_reactions::table.filter(post_reactions::post_id.eq_any(&orphaned))).execute(conn)?;
+    diesel::delete(post_translations::table.filter(post_translations::post_id.eq_any(&orphaned))).execute(conn)?;
+    let ids: Vec<i32> = purged.iter().map(|(id, _)| *id).collect();
+    diesel::delete(posts::table.filter(posts::id.eq_any(ids))).execute(conn)
+}
+
+// Permanently removes trashed items of one kind. Narrowed to a single item by `item_id`,
+// or to items deleted at or before `deleted_before`. Returns how many items were removed.
+pub fn purge_trash(
+    conn: &mut PgConnection,
+    kind: TrashKind,
+    item_id: Option<&str>,
+    deleted_before: Option<NaiveDateTime>,
+) -> QueryResult<usize> {
+    conn.transaction(|conn| match kind {
+        TrashKind::Posts => purge_posts(conn, item_id, deleted_before),
+        TrashKind::Skills => {
+            let mut query = skills::table.filter(skills::deleted_at.is_not_null()).into_boxed();
+            if let Some(item_id) = item_id {
+                query = query.filter(skills::skill_id.eq(item_id));
+            }
+            if let Some(deleted_before) = deleted_before {
+                query = query.filter(skills::deleted_at.le(deleted_before));
+            }
+            let purged: Vec<(i32, String)> = query.select((skills::id, skills::skill_id)).load(conn)?;
+            let (ids, skill_ids): (Vec<i32>, Vec<String>) = purged.iter().cloned().unzip();
+            // Nothing is announced as deleted while a live item still goes by the same id
+            let live_skill_ids: Vec<String> =
+                skills::table
+                    .filter(skills::skill_id.eq_any(&skill_ids))
+                    .filter(skills::deleted_at.is_null())
+                    .select(skills::skill_id)
+                    .load(conn)?;
+            for (id, skill_id) in &purged {
+                if !live_skill_ids.contains(skill_id) {
+                    kind.enqueue_event(conn, false, *id)?;
+                }
+            }
+            diesel::delete(skills::table.filter(skills::id.eq_any(ids))).execute(conn)
+        }
+        TrashKind::Worklog => {
+            let mut query = worklog::table.filter(worklog::deleted_at.is_not_null()).into_boxed();
+            if let Some(item_id) = item_id {
+                query = query.filter(worklog::worklog_id.eq(item_id));
+            }
+            if let Some(deleted_before) = deleted_before {
+                query = query.filter(worklog::deleted_at.le(deleted_before));
+            }
+            let purged: Vec<(i32, String)> = query.select((worklog::id, worklog::worklog_id)).load(conn)?;
+            let (ids, worklog_ids): (Vec<i32>, Vec<String>) = purged.iter().cloned().unzip();
+            // Nothing is announced as deleted while a live item still goes by the same id
+            let live_worklog_ids: Vec<String> =
+                worklog::table
+                    .filter(worklog::worklog_id.eq_any(&worklog_ids))
+                    .filter(worklog::deleted_at.is_null())
+                    .select(worklog::worklog_id)
+                    .load(conn)?;
+            for (id, worklog_id) in &purged {
+                if !live_worklog_ids.contains(worklog_id) {
+                    kind.enqueue_event(conn, false, *id)?;
+                }
+            }
+            diesel::delete(worklog::table.filter(worklog::id.eq_any(ids))).execute(conn)
+        }
+    })
+}
+
+// Trashed content isn't public and purging can't be undone, so only admins and editors get at the trash
+pub fn trash_access(session: Option<SessionData>) -> Result<SessionData, HttpResponse> {
+    match session {
+        Some(session) if session.is_editor_or_admin() => Ok(session),
+        Some(_) => Err(HttpResponse::Forbidden().json(json!({ "error": "Only admins and editors can manage the trash" }))),
+        None => Err(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+}
+
+#[get("/trash")]
+async fn get_trash(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = trash_access(session_from_request(&req).await) {
+        return Ok(response);
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match load_trash(&mut conn) {
+        Ok(trash) => Ok(HttpResponse::Ok().json(trash)),
+        Err(e) => {
+            eprintln!("Error loading trash: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[post("/trash/{kind}/{item_id}/restore")]
+async fn restore_from_trash(
+    path: web::Path<(TrashKind, String)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = trash_access(session_from_request(&req).await) {
+        return Ok(response);
+    }
+    let (kind, item_id) = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match restore_item(&mut conn, kind, &item_id) {
+        Ok(RestoreOutcome::Restored) => {
+            invalidate_cached(&req, kind.cache_kind()).await;
+            publish_live(&req, kind.live_kind(), LiveAction::Created, &item_id).await;
+            Ok(HttpResponse::Ok().json(json!({
+                "message": format!("'{}' has been restored from the trash", item_id)
+            })))
+        }
+        Ok(RestoreOutcome::NotInTrash) => Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("'{}' is not in the trash", item_id)
+        }))),
+        Ok(RestoreOutcome::Conflict) => Ok(HttpResponse::Conflict().json(json!({
+            "error": format!("Another item with ID '{}' already exists", item_id)
+        }))),
+        Err(e) => {
+            eprintln!("Error restoring from trash: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[delete("/trash/{kind}/{item_id}")]
+async fn purge_from_trash(
+    path: web::Path<(TrashKind, String)>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = trash_access(session_from_request(&req).await) {
+        return Ok(response);
+    }
+    let (kind, item_id) = path.into_inner();
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match purge_trash(&mut conn, kind, Some(&item_id), None) {
+        Ok(0) => Ok(HttpResponse::NotFound().json(json!({
+            "error": format!("'{}' is not in the trash", item_id)
+        }))),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "message": format!("'{}' has been permanently deleted", item_id)
+        }))),
+        Err(e) => {
+            eprintln!("Error purging from trash: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[delete("/trash")]
+async fn empty_trash(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = trash_access(session_from_request(&req).await) {
+        return Ok(response);
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let mut purged = 0;
+    for kind in TrashKind::ALL {
+        match purge_trash(&mut conn, kind, None, None) {
+            Ok(count) => purged += count,
+            Err(e) => {
+                eprintln!("Error emptying trash: {:?}", e);
+                return Ok(HttpResponse::InternalServerError().finish());
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "The trash has been emptied.",
+        "purged": purged,
+    })))
+}
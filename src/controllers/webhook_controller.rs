@@ -0,0 +1,217 @@
+use actix_web::{delete, get, post, web, Error, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::login_controller::session_from_request;
+use crate::models::SessionData::SessionData;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::webhook_schema::{webhook_deliveries, webhook_events, webhooks};
+use crate::table_models::webhook_models::{NewWebhook, NewWebhookEvent, Webhook, WebhookDelivery};
+
+const MIN_SECRET_LENGTH: usize = 16;
+const MAX_SECRET_LENGTH: usize = 128;
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+// Body of the create endpoint. Without a secret one is generated, without events the webhook gets all of them.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookInput {
+    pub url: String,
+    pub secret: Option<String>,
+    pub events: Option<Vec<String>>,
+}
+
+// The one response that carries the secret, the receiver needs it to check signatures
+#[derive(Serialize, Deserialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DeliveryLogEntry {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub event: WebhookEvent,
+}
+
+// Writes `event` to the outbox. Call it inside the transaction making the change,
+// so the event is stored exactly when the change is and the dispatcher picks it up from there. Returns the event's id.
+pub fn enqueue_event(conn: &mut PgConnection, event: WebhookEvent, data: serde_json::Value) -> QueryResult<i32> {
+    diesel::insert_into(webhook_events::table)
+        .values(&NewWebhookEvent {
+            event,
+            payload: data.to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+        })
+        .returning(webhook_events::id)
+        .get_result(conn)
+}
+
+// Checks the URL, secret and event filter of a new webhook, filling in a secret when there is none
+pub fn new_webhook(input: WebhookInput, now: chrono::NaiveDateTime) -> Result<NewWebhook, String> {
+    let url = input.url.trim().to_string();
+    match reqwest::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host().is_some() => {}
+        _ => return Err(format!("'{}' is not an http or https URL", url)),
+    }
+
+    let secret = match input.secret {
+        Some(secret) if (MIN_SECRET_LENGTH..=MAX_SECRET_LENGTH).contains(&secret.len()) => secret,
+        Some(_) => {
+            return Err(format!("secret has to be between {} and {} characters", MIN_SECRET_LENGTH, MAX_SECRET_LENGTH));
+        }
+        None => format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+    };
+
+    let mut events = input.events.unwrap_or_default();
+    if let Some(unknown) = events.iter().find(|filter| !WebhookEvent::is_valid_filter(filter)) {
+        return Err(format!("Unknown event '{}', expected e.g. post.created or post.*", unknown));
+    }
+    events.sort();
+    events.dedup();
+
+    Ok(NewWebhook { url, secret, events, created_at: now, updated_at: now })
+}
+
+// The deliveries of one webhook, newest first
+pub fn load_delivery_log(conn: &mut PgConnection, webhook_id: i32) -> QueryResult<Vec<DeliveryLogEntry>> {
+    webhook_deliveries::table
+        .inner_join(webhook_events::table)
+        .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+        .order(webhook_deliveries::id.desc())
+        .limit(DELIVERY_LOG_LIMIT)
+        .select((WebhookDelivery::as_select(), webhook_events::event))
+        .load::<(WebhookDelivery, WebhookEvent)>(conn)
+        .map(|rows| rows.into_iter().map(|(delivery, event)| DeliveryLogEntry { delivery, event }).collect())
+}
+
+// Webhooks send content to other systems, so only admins manage them
+async fn require_admin(req: &HttpRequest) -> Result<SessionData, HttpResponse> {
+    match session_from_request(req).await {
+        Some(session) if session.is_admin() => Ok(session),
+        Some(_) => Err(HttpResponse::Forbidden().json(json!({ "error": "Only admins can manage webhooks" }))),
+        None => Err(HttpResponse::Unauthorized().json(json!({ "error": "Login required" }))),
+    }
+}
+
+#[post("/webhooks")]
+async fn create_webhook(
+    pool: web::Data<DbPool>,
+    webhook: web::Json<WebhookInput>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = require_admin(&req).await {
+        return Ok(response);
+    }
+
+    let new_webhook = match new_webhook(webhook.into_inner(), chrono::Utc::now().naive_utc()) {
+        Ok(new_webhook) => new_webhook,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+    };
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match diesel::insert_into(webhooks::table)
+        .values(&new_webhook)
+        .returning(Webhook::as_returning())
+        .get_result(&mut conn)
+    {
+        Ok(webhook) => {
+            let secret = webhook.secret.clone();
+            Ok(HttpResponse::Created().json(CreatedWebhook { webhook, secret }))
+        }
+        Err(e) => {
+            eprintln!("Error inserting webhook: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/webhooks")]
+async fn get_webhooks(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = require_admin(&req).await {
+        return Ok(response);
+    }
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match webhooks::table.order(webhooks::id.asc()).select(Webhook::as_select()).load(&mut conn) {
+        Ok(webhooks) => Ok(HttpResponse::Ok().json(webhooks)),
+        Err(e) => {
+            eprintln!("Error loading webhooks: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// Removes the webhook along with its delivery log
+#[delete("/webhooks/{id}")]
+async fn delete_webhook(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = require_admin(&req).await {
+        return Ok(response);
+    }
+    let id = path.into_inner();
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    match diesel::delete(webhooks::table.find(id)).execute(&mut conn) {
+        Ok(0) => Ok(HttpResponse::NotFound().json(json!({ "error": format!("Webhook {} not found", id) }))),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({ "message": format!("Webhook {} has been deleted", id) }))),
+        Err(e) => {
+            eprintln!("Error deleting webhook: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[get("/webhooks/{id}/deliveries")]
+async fn get_webhook_deliveries(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    if let Err(response) = require_admin(&req).await {
+        return Ok(response);
+    }
+    let id = path.into_inner();
+
+    let mut conn = pool.get().map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
+    })?;
+
+    let exists =
+        webhooks::table
+            .find(id)
+            .count()
+            .get_result::<i64>(&mut conn)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Error retrieving webhook: {}", e)))?
+            > 0;
+    if !exists {
+        return Ok(HttpResponse::NotFound().json(json!({ "error": format!("Webhook {} not found", id) })));
+    }
+
+    match load_delivery_log(&mut conn, id) {
+        Ok(deliveries) => Ok(HttpResponse::Ok().json(deliveries)),
+        Err(e) => {
+            eprintln!("Error loading webhook deliveries: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
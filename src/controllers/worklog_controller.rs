@@ -1,87 +1,122 @@
-use actix_web::{delete, get, post, put, web, Error, HttpResponse};
-use chrono::DateTime;
+use actix_web::http::header::ETag;
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use uuid::Uuid;
 
+use crate::connectors::content_cache::{invalidate_cached, CacheKind, CacheLookup};
+use crate::connectors::live_events::{publish_live, LiveAction, LiveKind};
+use crate::controllers::author_controller::{attach_authors, author_ids_named};
+use crate::controllers::login_controller::session_from_request;
+use crate::controllers::webhook_controller::enqueue_event;
+use crate::models::Author::AuthorQuery;
 use crate::connectors::postgres_connector::DbPool;
+use crate::models::Pagination::{CursorKey, ListParams, ListQuery, Page, SortField, SortOrder};
+use crate::models::Rendered::{RenderQuery, Rendered};
+use crate::models::WebhookEvent::WebhookEvent;
 use crate::schemas::worklog_schema::worklog;
 use crate::table_models::worklog_models::{NewWorklog, Worklog};
+use crate::utils::etag::{etag_for, if_match_version, precondition_failed};
 
+// Body of the create and update endpoints. The worklog_id, id and timestamps are the server's to set,
+// clients that still send them have them ignored.
 #[derive(Serialize, Deserialize)]
 pub struct WorklogInput {
-    pub id: i32,
-    pub worklog_id: String,
     pub work_title: String,
     pub body: String,
-    pub created_at: String,
-    pub updated_at: String,
 }
 
-
-impl WorklogInput {
-    // Constructor method for creating a new WorklogInput
-    pub fn new(id: i32, worklog_id: String, work_title: String, body: String, created_at: String, updated_at: String) -> Self {
-        WorklogInput {
-            id,
-            worklog_id,
-            work_title,
-            body,
-            created_at,
-            updated_at,
-        }
-    }
+// Puts `event` for a worklog in the webhook outbox, with the worklog as it is now. Call it in the write's transaction.
+// Trashed copies can share the worklog_id, so the live worklog wins, then the most recently trashed copy.
+pub fn enqueue_worklog_event(conn: &mut PgConnection, event: WebhookEvent, worklog_id: &str) -> QueryResult<i32> {
+    let id =
+        worklog::table
+            .filter(worklog::worklog_id.eq(worklog_id))
+            .order(worklog::deleted_at.desc().nulls_first())
+            .select(worklog::id)
+            .first::<i32>(conn)?;
+    enqueue_worklog_row_event(conn, event, id)
 }
 
+// Same as `enqueue_worklog_event`, for one row of the worklog table picked by its `id`
+pub fn enqueue_worklog_row_event(conn: &mut PgConnection, event: WebhookEvent, id: i32) -> QueryResult<i32> {
+    let (worklog_id, work_title) =
+        worklog::table
+            .find(id)
+            .select((worklog::worklog_id, worklog::work_title))
+            .first::<(String, String)>(conn)?;
+    enqueue_event(conn, event, json!({ "worklog_id": worklog_id, "work_title": work_title }))
+}
 
 #[post("/blog/worklog/create")]
 async fn create_worklog(
     pool: web::Data<DbPool>,
     worklog: web::Json<WorklogInput>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let worklog_input = worklog.into_inner();
+    let author_id = session_from_request(&req).await.map(|session| session.user_id);
 
+    let now = chrono::Utc::now().naive_utc();
     let new_worklog = NewWorklog {
-        worklog_id: worklog_input.worklog_id,
+        worklog_id: Uuid::new_v4().to_string(),
         work_title: worklog_input.work_title,
         body: worklog_input.body,
-        created_at: DateTime::parse_from_rfc3339(&worklog_input.created_at)
-            .unwrap()
-            .naive_utc(), // Convert to NaiveDateTime
-        updated_at: DateTime::parse_from_rfc3339(&worklog_input.updated_at)
-            .unwrap()
-            .naive_utc(), // Convert to NaiveDateTime
+        created_at: now,
+        updated_at: now,
+        author_id,
     };
 
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    conn.transaction::<_, diesel::result::Error, _>(|conn| {
-        // Insert the new worklog
-        diesel::insert_into(worklog::table)
-            .values(&new_worklog)
-            .get_result::<Worklog>(conn)  // This can return the inserted record with the `id`
-            .map_err(|e| {
-                eprintln!("Error inserting new worklog: {:?}", e);
-                e
-            })
-    }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))
-        .map(|worklog| HttpResponse::Created().json(worklog))
+    let created =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Insert the new worklog
+            diesel::insert_into(worklog::table)
+                .values(&new_worklog)
+                .returning(Worklog::as_returning())
+                .get_result(conn)  // This can return the inserted record with the `id`
+                .map_err(|e| {
+                    eprintln!("Error inserting new worklog: {:?}", e);
+                    e
+                })
+                .and_then(|created| {
+                    enqueue_worklog_event(conn, WebhookEvent::WorklogCreated, &created.worklog_id)?;
+                    attach_authors(conn, vec![created], |created| created.author_id.as_ref())
+                })
+        }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Transaction failed: {}", e)))?;
+
+    invalidate_cached(&req, CacheKind::Worklog).await;
+    publish_live(&req, LiveKind::Worklog, LiveAction::Created, &created[0].item.worklog_id).await;
+    Ok(HttpResponse::Created().json(&created[0]))
 }
 
 #[get("/blog/worklog/retrieve/worklog-id/{worklog_id}")]
 async fn get_by_worklog_id(
     path: web::Path<String>,
     pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let worklog_id = path.into_inner();
+    let mut cache = CacheLookup::new(&req, CacheKind::Worklog, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match worklog::table.filter(worklog::worklog_id.eq(worklog_id)).first::<Worklog>(&mut conn) {
-        Ok(worklog) => Ok(HttpResponse::Ok().json(worklog)),
+    match worklog::table.filter(worklog::worklog_id.eq(worklog_id)).filter(worklog::deleted_at.is_null()).select(Worklog::as_select()).first(&mut conn).and_then(|worklog| attach_authors(&mut conn, vec![worklog], |worklog| worklog.author_id.as_ref())) {
+        Ok(mut worklog) => {
+            let worklog = worklog.remove(0);
+            let updated_at = worklog.item.updated_at;
+            Ok(cache.respond(&req, Some(updated_at), &Rendered::new(worklog, render_query.render)).await)
+        }
         Err(_) => Ok(HttpResponse::NotFound().finish()),
     }
 }
@@ -90,30 +125,99 @@ async fn get_by_worklog_id(
 async fn get_worklog(
     path: web::Path<i32>,
     pool: web::Data<DbPool>,
+    render_query: web::Query<RenderQuery>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let id = path.into_inner();
+    let mut cache = CacheLookup::new(&req, CacheKind::Worklog, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match worklog::table.find(id).first::<Worklog>(&mut conn) {
-        Ok(worklog) => Ok(HttpResponse::Ok().json(worklog)),
+    match worklog::table.find(id).filter(worklog::deleted_at.is_null()).select(Worklog::as_select()).first(&mut conn).and_then(|worklog| attach_authors(&mut conn, vec![worklog], |worklog| worklog.author_id.as_ref())) {
+        Ok(mut worklog) => {
+            let worklog = worklog.remove(0);
+            let updated_at = worklog.item.updated_at;
+            Ok(cache.respond(&req, Some(updated_at), &Rendered::new(worklog, render_query.render)).await)
+        }
         Err(diesel::result::Error::NotFound) => Ok(HttpResponse::NotFound().finish()),
         Err(_) => Ok(HttpResponse::InternalServerError().finish()),
     }
 }
 
 
+// Loads one keyset page of worklog entries along with the total number of worklog entries matching the filters
+pub fn load_worklog_page(conn: &mut PgConnection, params: &ListParams, author: Option<&str>) -> QueryResult<Page<Worklog>> {
+    let filtered = || {
+        // Trashed worklog entries only show up in the trash
+        let mut query = worklog::table.filter(worklog::deleted_at.is_null()).into_boxed();
+        if let Some(from) = params.from {
+            query = query.filter(worklog::created_at.ge(from));
+        }
+        if let Some(to) = params.to {
+            query = query.filter(worklog::created_at.lt(to));
+        }
+        if let Some(author) = author {
+            query = query.filter(worklog::author_id.eq_any(author_ids_named(author)));
+        }
+        query
+    };
+
+    let total_count = filtered().count().get_result::<i64>(conn)?;
+
+    let after = params.after.as_ref();
+    let ordered =
+        match params.sort {
+            SortField::CreatedAt =>
+                crate::keyset_paginate!(filtered(), worklog::created_at, worklog::id, params.order, after.and_then(|c| c.timestamp().map(|t| (t, c.id)))),
+            SortField::UpdatedAt =>
+                crate::keyset_paginate!(filtered(), worklog::updated_at, worklog::id, params.order, after.and_then(|c| c.timestamp().map(|t| (t, c.id)))),
+            SortField::Title =>
+                crate::keyset_paginate!(filtered(), worklog::work_title, worklog::id, params.order, after.and_then(|c| c.text().map(|t| (t, c.id)))),
+        };
+
+    let rows = ordered.select(Worklog::as_select()).limit(params.limit + 1).load(conn)?;
+
+    Ok(Page::from_rows(rows, params, total_count, |entry| {
+        let key = match params.sort {
+            SortField::CreatedAt => CursorKey::Timestamp(entry.created_at),
+            SortField::UpdatedAt => CursorKey::Timestamp(entry.updated_at),
+            SortField::Title => CursorKey::Text(entry.work_title.clone()),
+        };
+        (key, entry.id)
+    }))
+}
+
 #[get("/blog/worklog/get/all")]
-async fn get_all_worklog(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+async fn get_all_worklog(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+    author_query: web::Query<AuthorQuery>,
+    render_query: web::Query<RenderQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    // Worklog has always been listed oldest first
+    let params = match query.into_inner().into_params(SortOrder::Asc) {
+        Ok(params) => params,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(json!({ "error": message }))),
+    };
+
+    let mut cache = CacheLookup::new(&req, CacheKind::Worklog, true).await;
+    if let Some(response) = cache.cached_response(&req) {
+        return Ok(response);
+    }
+
     // Get a connection from the pool
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    // Query all worklog from the database
-    match worklog::table.load::<Worklog>(&mut conn) {
-        Ok(worklog) => Ok(HttpResponse::Ok().json(worklog)),
+    match load_worklog_page(&mut conn, &params, author_query.author.as_deref()).and_then(|page| page.try_map_items(|items| attach_authors(&mut conn, items, |item| item.author_id.as_ref()))) {
+        Ok(page) => Ok(cache.respond(&req, None, &page.map_items(|items| Rendered::all(items, render_query.render))).await),
         Err(_) => Ok(HttpResponse::InternalServerError().finish()),
     }
 }
@@ -123,6 +227,7 @@ async fn update_worklog(
     path: web::Path<String>,
     worklog: web::Json<WorklogInput>,
     pool: web::Data<crate::controllers::worklog_controller::DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let worklog_id_path = path.into_inner();
     let worklog_input = worklog.into_inner();
@@ -131,37 +236,61 @@ async fn update_worklog(
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    // First, retrieve the title of the post before deleting it
+    // First, retrieve the title and version of the worklog being updated
     let work_title =
         worklog::table
             .filter(worklog::worklog_id.eq(&worklog_id_path))
-            .select(worklog::work_title)
-            .first::<String>(&mut conn)
+            .filter(worklog::deleted_at.is_null())
+            .select((worklog::work_title, worklog::updated_at))
+            .first::<(String, chrono::NaiveDateTime)>(&mut conn)
             .optional()
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error retrieving worklog name: {}", e))
             })?;
 
     match work_title {
-        Some(worklog) => {
-            match diesel::update(worklog::table.filter(worklog::worklog_id.eq(worklog_id_path)))
-                .set((
-                    worklog::id.eq(worklog_input.id),
-                    worklog::worklog_id.eq(worklog_input.worklog_id),
-                    worklog::work_title.eq(worklog_input.work_title),
-                    worklog::body.eq(worklog_input.body),
-                ))
-                .execute(&mut conn)
-            {
-                Ok(_) => {
+        Some((worklog, updated_at)) => {
+            let expected_version = match if_match_version(&req, updated_at) {
+                Ok(expected_version) => expected_version,
+                Err(response) => return Ok(response),
+            };
+
+            let mut update =
+                diesel::update(worklog::table.filter(worklog::worklog_id.eq(&worklog_id_path)).filter(worklog::deleted_at.is_null()))
+                    .set((
+                        worklog::work_title.eq(worklog_input.work_title),
+                        worklog::body.eq(worklog_input.body),
+                        worklog::updated_at.eq(chrono::Utc::now().naive_utc()),
+                    ))
+                    .returning(worklog::updated_at)
+                    .into_boxed();
+            // With If-Match, only overwrite the version that was checked so a concurrent write in between fails the precondition
+            if let Some(expected_version) = expected_version {
+                update = update.filter(worklog::updated_at.eq(expected_version));
+            }
+
+            let update_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let new_updated_at = update.get_result::<chrono::NaiveDateTime>(conn).optional()?;
+                if new_updated_at.is_some() {
+                    enqueue_worklog_event(conn, WebhookEvent::WorklogUpdated, &worklog_id_path)?;
+                }
+                Ok(new_updated_at)
+            });
+
+            match update_result {
+                Ok(Some(new_updated_at)) => {
+                    invalidate_cached(&req, CacheKind::Worklog).await;
+                    publish_live(&req, LiveKind::Worklog, LiveAction::Updated, &worklog_id_path).await;
                     let response_body =
                         json!({"message": format!("Work '{}' has been updated", worklog)});
                     Ok(HttpResponse::Ok()
                         .content_type("application/json")
+                        .insert_header(ETag(etag_for(new_updated_at)))
                         .json(response_body))
                 }
+                Ok(None) => Ok(precondition_failed(updated_at)),
                 Err(e) => {
-                    eprintln!("Error updating post: {:?}", e);
+                    eprintln!("Error updating worklog: {:?}", e);
                     Ok(HttpResponse::InternalServerError().finish())
                 }
             }
@@ -184,6 +313,7 @@ async fn update_worklog(
 async fn delete_worklog(
     path: web::Path<String>,  // Changed to String since worklog_id is a varchar
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let worklog_id = path.into_inner();
     let mut conn = pool.get().map_err(|e| {
@@ -194,20 +324,44 @@ async fn delete_worklog(
     let work_title =
         worklog::table
             .filter(worklog::worklog_id.eq(&worklog_id))
-            .select(worklog::work_title)
-            .first::<String>(&mut conn)
+            .filter(worklog::deleted_at.is_null())
+            .select((worklog::work_title, worklog::updated_at))
+            .first::<(String, chrono::NaiveDateTime)>(&mut conn)
             .optional()
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error retrieving worklog title: {}", e))
             })?;
 
     match work_title {
-        Some(title) => {
-            // Now delete the worklog
-            match diesel::delete(worklog::table.filter(worklog::worklog_id.eq(&worklog_id))).execute(&mut conn) {
+        Some((title, updated_at)) => {
+            let expected_version = match if_match_version(&req, updated_at) {
+                Ok(expected_version) => expected_version,
+                Err(response) => return Ok(response),
+            };
+
+            // Now move the worklog to the trash
+            let trash_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let mut trash =
+                    diesel::update(worklog::table.filter(worklog::worklog_id.eq(&worklog_id)).filter(worklog::deleted_at.is_null()))
+                        .set(worklog::deleted_at.eq(chrono::Utc::now().naive_utc()))
+                        .into_boxed();
+                if let Some(expected_version) = expected_version {
+                    trash = trash.filter(worklog::updated_at.eq(expected_version));
+                }
+                let trashed = trash.execute(conn)?;
+                if trashed > 0 {
+                    enqueue_worklog_event(conn, WebhookEvent::WorklogDeleted, &worklog_id)?;
+                }
+                Ok(trashed)
+            });
+
+            match trash_result {
+                Ok(0) => Ok(precondition_failed(updated_at)),
                 Ok(_) => {
+                    invalidate_cached(&req, CacheKind::Worklog).await;
+                    publish_live(&req, LiveKind::Worklog, LiveAction::Deleted, &worklog_id).await;
                     let response_body = json!({
-                        "message": format!("Blog worklog '{}' has been deleted", title)
+                        "message": format!("Blog worklog '{}' has been moved to the trash", title)
                     });
 
                     Ok(HttpResponse::Ok()
@@ -236,13 +390,32 @@ async fn delete_worklog(
 #[delete("/blog/worklog/all")]
 async fn delete_all_worklog(
     pool: web::Data<DbPool>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut conn = pool.get().map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Couldn't get db connection from pool: {}", e))
     })?;
 
-    match diesel::sql_query("DELETE FROM worklog").execute(&mut conn) {
-        Ok(_) => Ok(HttpResponse::NoContent().finish()),
+    let trash_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let trashed =
+            diesel::update(worklog::table.filter(worklog::deleted_at.is_null()))
+                .set(worklog::deleted_at.eq(chrono::Utc::now().naive_utc()))
+                .returning(worklog::worklog_id)
+                .get_results::<String>(conn)?;
+        for worklog_id in &trashed {
+            enqueue_worklog_event(conn, WebhookEvent::WorklogDeleted, worklog_id)?;
+        }
+        Ok(trashed)
+    });
+
+    match trash_result {
+        Ok(trashed) => {
+            invalidate_cached(&req, CacheKind::Worklog).await;
+            for worklog_id in &trashed {
+                publish_live(&req, LiveKind::Worklog, LiveAction::Deleted, worklog_id).await;
+            }
+            Ok(HttpResponse::NoContent().finish())
+        }
         Err(e) => {
             eprintln!("Error deleting worklog: {:?}", e);
             Ok(HttpResponse::InternalServerError().finish())
@@ -0,0 +1,4 @@
+pub mod publish_scheduler;
+pub mod trash_purger;
+pub mod view_rollup;
+pub mod webhook_dispatcher;
@@ -0,0 +1,75 @@
+use actix_web::web;
+use diesel::prelude::*;
+use std::time::Duration;
+
+use crate::connectors::content_cache::{CacheKind, ContentCache};
+use crate::connectors::live_events::{LiveAction, LiveEvents, LiveKind};
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::blog_controller::enqueue_post_event;
+use crate::models::PostStatus::PostStatus;
+use crate::models::WebhookEvent::WebhookEvent;
+use crate::schemas::blog_schema::posts;
+
+// Flips every scheduled post whose publish time has passed to published, returns the post_ids that changed.
+// Each one goes to the webhook outbox as post.updated in the same transaction.
+pub fn publish_due_posts(conn: &mut PgConnection) -> QueryResult<Vec<String>> {
+    let now = chrono::Utc::now().naive_utc();
+
+    conn.transaction(|conn| {
+        let published: Vec<String> =
+            diesel::update(
+                posts::table
+                    .filter(posts::status.eq(PostStatus::Scheduled))
+                    .filter(posts::deleted_at.is_null())
+                    .filter(posts::publish_at.le(now)),
+            )
+                .set((
+                    posts::status.eq(PostStatus::Published),
+                    posts::updated_at.eq(now),
+                ))
+                .returning(posts::post_id)
+                .get_results(conn)?;
+
+        for post_id in &published {
+            enqueue_post_event(conn, WebhookEvent::PostUpdated, post_id)?;
+        }
+        Ok(published)
+    })
+}
+
+// Runs publish_due_posts on a fixed interval for the lifetime of the server.
+// Newly published posts drop the cached post reads so they show up straight away, and go out as live events.
+pub fn spawn_publish_scheduler(
+    pool: DbPool,
+    interval: Duration,
+    content_cache: web::Data<ContentCache>,
+    live_events: web::Data<LiveEvents>,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let pool = pool.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                publish_due_posts(&mut conn).map_err(|e| e.to_string())
+            })
+                .await;
+
+            match result {
+                Ok(Ok(published)) if published.is_empty() => {}
+                Ok(Ok(published)) => {
+                    log::info!("Published {} scheduled post(s)", published.len());
+                    content_cache.invalidate(CacheKind::Posts).await;
+                    for post_id in &published {
+                        live_events.publish(LiveKind::Post, LiveAction::Updated, post_id).await;
+                    }
+                }
+                Ok(Err(e)) => log::error!("Failed to publish scheduled posts: {}", e),
+                Err(e) => log::error!("Publish scheduler task panicked: {:?}", e),
+            }
+        }
+    });
+}
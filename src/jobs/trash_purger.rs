@@ -0,0 +1,41 @@
+use diesel::prelude::*;
+use std::time::Duration;
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::controllers::trash_controller::{purge_trash, TrashKind};
+
+// Permanently removes everything that has been in the trash for longer than `retention`, returns how many items went
+pub fn purge_expired_trash(conn: &mut PgConnection, retention: chrono::Duration) -> QueryResult<usize> {
+    let cutoff = chrono::Utc::now().naive_utc() - retention;
+
+    let mut purged = 0;
+    for kind in TrashKind::ALL {
+        purged += purge_trash(conn, kind, None, Some(cutoff))?;
+    }
+    Ok(purged)
+}
+
+// Runs purge_expired_trash on a fixed interval for the lifetime of the server
+pub fn spawn_trash_purger(pool: DbPool, interval: Duration, retention: chrono::Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let pool = pool.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                purge_expired_trash(&mut conn, retention).map_err(|e| e.to_string())
+            })
+                .await;
+
+            match result {
+                Ok(Ok(0)) => {}
+                Ok(Ok(purged)) => log::info!("Purged {} item(s) from the trash", purged),
+                Ok(Err(e)) => log::error!("Failed to purge the trash: {}", e),
+                Err(e) => log::error!("Trash purger task panicked: {:?}", e),
+            }
+        }
+    });
+}
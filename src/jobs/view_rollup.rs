@@ -0,0 +1,75 @@
+use actix_web::web;
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use std::time::Duration;
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::connectors::view_counter::{DailyViews, ViewCounter};
+use crate::schemas::post_view_schema::post_views;
+use crate::table_models::post_view_models::NewPostViews;
+
+// Writes one day of counts into post_views, returns how many rows were written.
+// Counts only ever go up, so a Redis that lost its data cannot wipe out what was rolled up before.
+pub fn store_daily_views(conn: &mut PgConnection, day: NaiveDate, daily_views: &[DailyViews]) -> QueryResult<usize> {
+    if daily_views.is_empty() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let rows: Vec<NewPostViews> =
+        daily_views
+            .iter()
+            .map(|daily| NewPostViews {
+                post_id: daily.post_id.clone(),
+                day,
+                views: daily.views,
+                uniques: daily.uniques,
+                updated_at: now,
+            })
+            .collect();
+
+    diesel::insert_into(post_views::table)
+        .values(&rows)
+        .on_conflict((post_views::post_id, post_views::day))
+        .do_update()
+        .set((
+            post_views::views.eq(diesel::dsl::sql::<BigInt>("GREATEST(post_views.views, excluded.views)")),
+            post_views::uniques.eq(diesel::dsl::sql::<BigInt>("GREATEST(post_views.uniques, excluded.uniques)")),
+            post_views::updated_at.eq(now),
+        ))
+        .execute(conn)
+}
+
+// Copies the counts for `day` from Redis into Postgres
+pub async fn roll_up_views(pool: DbPool, counter: &ViewCounter, day: NaiveDate) -> Result<usize, String> {
+    let daily_views = counter.load_day(day).await?;
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        store_daily_views(&mut conn, day, &daily_views).map_err(|e| e.to_string())
+    })
+        .await
+        .map_err(|e| format!("View rollup task panicked: {:?}", e))?
+}
+
+// Runs roll_up_views on a fixed interval for the lifetime of the server.
+// Each run covers yesterday as well as today, so the last views of a day are rolled up once it is over.
+pub fn spawn_view_rollup(pool: DbPool, interval: Duration, counter: web::Data<ViewCounter>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let today = chrono::Utc::now().date_naive();
+            for day in [today.pred_opt().unwrap_or(today), today] {
+                match roll_up_views(pool.clone(), &counter, day).await {
+                    Ok(0) => {}
+                    Ok(rolled_up) => log::info!("Rolled up views of {} post(s) for {}", rolled_up, day),
+                    Err(e) => log::error!("Failed to roll up views for {}: {}", day, e),
+                }
+            }
+        }
+    });
+}
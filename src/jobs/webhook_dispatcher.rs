@@ -0,0 +1,403 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text, Timestamp};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::connectors::postgres_connector::DbPool;
+use crate::models::WebhookEvent::DeliveryStatus;
+use crate::schemas::webhook_schema::webhook_deliveries;
+
+// Deliveries are given up on after this many tries
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+// The wait after the first failed try, doubling after every further one up to MAX_RETRY_DELAY_SECONDS
+const BASE_RETRY_DELAY_SECONDS: i64 = 30;
+const MAX_RETRY_DELAY_SECONDS: i64 = 6 * 3600;
+// A claimed delivery is left alone by other dispatchers for this long, in case this one dies while sending
+const CLAIM_SECONDS: i64 = 300;
+const DELIVERY_BATCH_SIZE: i32 = 50;
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+// Error texts from receivers are cut to this length in the delivery log
+const MAX_ERROR_LENGTH: usize = 500;
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+// A delivery that is due, with what is needed to send it
+#[derive(Debug, QueryableByName)]
+pub struct DueDelivery {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
+    #[diesel(sql_type = Integer)]
+    pub attempts: i32,
+    #[diesel(sql_type = Text)]
+    pub url: String,
+    #[diesel(sql_type = Text)]
+    pub secret: String,
+    #[diesel(sql_type = Integer)]
+    pub event_id: i32,
+    #[diesel(sql_type = Text)]
+    pub event: String,
+    #[diesel(sql_type = Text)]
+    pub payload: String,
+    #[diesel(sql_type = Timestamp)]
+    pub created_at: NaiveDateTime,
+}
+
+// How one try went, the receiver's status code if it answered
+#[derive(Debug, PartialEq)]
+pub struct DeliveryOutcome {
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+}
+
+impl DeliveryOutcome {
+    fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+// Hex HMAC-SHA256 of the body under the webhook's secret, sent as "sha256=<signature>"
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// How long to wait before the next try after `attempts` failed ones
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 30) as u32;
+    let seconds = BASE_RETRY_DELAY_SECONDS.saturating_mul(2_i64.saturating_pow(doublings));
+    chrono::Duration::seconds(seconds.min(MAX_RETRY_DELAY_SECONDS))
+}
+
+// Hands every event in the outbox that hasn't been yet to the active webhooks whose filter matches it,
+// as pending deliveries. Returns how many deliveries were created.
+pub fn fan_out_events(conn: &mut PgConnection) -> QueryResult<usize> {
+    diesel::sql_query(
+        "WITH events AS (
+             UPDATE webhook_events SET dispatched_at = $1
+             WHERE id IN (SELECT id FROM webhook_events WHERE dispatched_at IS NULL ORDER BY id FOR UPDATE SKIP LOCKED)
+             RETURNING id, event
+         )
+         INSERT INTO webhook_deliveries (webhook_id, event_id, status, attempts, next_attempt_at, created_at, updated_at)
+         SELECT w.id, e.id, 'pending', 0, $1, $1, $1
+         FROM events e
+         JOIN webhooks w ON w.active
+             AND (cardinality(w.events) = 0
+                  OR e.event = ANY(w.events)
+                  OR split_part(e.event, '.', 1) || '.*' = ANY(w.events))
+         ON CONFLICT (webhook_id, event_id) DO NOTHING",
+    )
+        .bind::<Timestamp, _>(chrono::Utc::now().naive_utc())
+        .execute(conn)
+}
+
+// Claims up to a batch of deliveries that are due at `now`, oldest first. Claiming pushes their next try
+// out by CLAIM_SECONDS, so dispatchers running side by side don't send the same delivery twice.
+pub fn claim_due_deliveries(conn: &mut PgConnection, now: NaiveDateTime) -> QueryResult<Vec<DueDelivery>> {
+    diesel::sql_query(
+        "WITH claimed AS (
+             UPDATE webhook_deliveries SET next_attempt_at = $2
+             WHERE id IN (SELECT id FROM webhook_deliveries
+                          WHERE status = 'pending' AND next_attempt_at <= $1
+                          ORDER BY next_attempt_at, id
+                          LIMIT $3
+                          FOR UPDATE SKIP LOCKED)
+             RETURNING id, webhook_id, event_id, attempts
+         )
+         SELECT c.id, c.attempts, w.url, w.secret, e.id AS event_id, e.event, e.payload, e.created_at
+         FROM claimed c
+         JOIN webhooks w ON w.id = c.webhook_id
+         JOIN webhook_events e ON e.id = c.event_id
+         ORDER BY c.id",
+    )
+        .bind::<Timestamp, _>(now)
+        .bind::<Timestamp, _>(now + chrono::Duration::seconds(CLAIM_SECONDS))
+        .bind::<Integer, _>(DELIVERY_BATCH_SIZE)
+        .load::<DueDelivery>(conn)
+}
+
+// Writes the outcome of a try to the delivery log. Failures are retried with backoff until
+// MAX_DELIVERY_ATTEMPTS tries have been made, then the delivery is marked failed.
+pub fn record_attempt(conn: &mut PgConnection, delivery: &DueDelivery, outcome: &DeliveryOutcome, now: NaiveDateTime) -> QueryResult<usize> {
+    let attempts = delivery.attempts + 1;
+    let (status, next_attempt_at, delivered_at) =
+        if outcome.succeeded() {
+            (DeliveryStatus::Delivered, now, Some(now))
+        } else if attempts >= MAX_DELIVERY_ATTEMPTS {
+            (DeliveryStatus::Failed, now, None)
+        } else {
+            (DeliveryStatus::Pending, now + retry_delay(attempts), None)
+        };
+
+    diesel::update(webhook_deliveries::table.find(delivery.id))
+        .set((
+            webhook_deliveries::status.eq(status),
+            webhook_deliveries::attempts.eq(attempts),
+            webhook_deliveries::next_attempt_at.eq(next_attempt_at),
+            webhook_deliveries::response_status.eq(outcome.response_status),
+            webhook_deliveries::last_error.eq(outcome.error.as_deref()),
+            webhook_deliveries::delivered_at.eq(delivered_at),
+            webhook_deliveries::updated_at.eq(now),
+        ))
+        .execute(conn)
+}
+
+// The body sent for a delivery. The event's id stays the same across retries, so receivers can drop repeats.
+pub fn delivery_body(delivery: &DueDelivery) -> String {
+    let data = serde_json::from_str::<serde_json::Value>(&delivery.payload).unwrap_or(serde_json::Value::Null);
+    serde_json::json!({
+        "id": delivery.event_id,
+        "event": delivery.event,
+        "created_at": delivery.created_at,
+        "data": data,
+    })
+        .to_string()
+}
+
+pub fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        // A receiver answering with a redirect hasn't taken the delivery
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build the webhook HTTP client")
+}
+
+// POSTs the signed body to the webhook's URL, any 2xx answer counts as delivered
+pub async fn send_delivery(client: &reqwest::Client, delivery: &DueDelivery) -> DeliveryOutcome {
+    let body = delivery_body(delivery);
+    let signature = sign_payload(&delivery.secret, body.as_bytes());
+
+    let response =
+        client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => {
+            DeliveryOutcome { response_status: Some(response.status().as_u16() as i32), error: None }
+        }
+        Ok(response) => {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            DeliveryOutcome {
+                response_status: Some(status.as_u16() as i32),
+                error: Some(format!("{}: {}", status, text.chars().take(MAX_ERROR_LENGTH).collect::<String>())),
+            }
+        }
+        Err(e) => DeliveryOutcome { response_status: None, error: Some(e.to_string().chars().take(MAX_ERROR_LENGTH).collect()) },
+    }
+}
+
+// One round of the dispatcher: fans new events out, then sends everything that is due.
+// Returns how many deliveries were tried.
+pub async fn dispatch_webhooks(pool: &DbPool, client: &reqwest::Client) -> Result<usize, String> {
+    let claim_pool = pool.clone();
+    let due =
+        tokio::task::spawn_blocking(move || {
+            let mut conn = claim_pool.get().map_err(|e| e.to_string())?;
+            fan_out_events(&mut conn).map_err(|e| e.to_string())?;
+            claim_due_deliveries(&mut conn, chrono::Utc::now().naive_utc()).map_err(|e| e.to_string())
+        })
+            .await
+            .map_err(|e| format!("Webhook dispatcher task panicked: {:?}", e))??;
+
+    let tried = due.len();
+    let outcomes = futures::future::join_all(due.iter().map(|delivery| send_delivery(client, delivery))).await;
+
+    let record_pool = pool.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = record_pool.get().map_err(|e| e.to_string())?;
+        let now = chrono::Utc::now().naive_utc();
+        for (delivery, outcome) in due.iter().zip(&outcomes) {
+            record_attempt(&mut conn, delivery, outcome, now).map_err(|e| e.to_string())?;
+        }
+        Ok::<_, String>(())
+    })
+        .await
+        .map_err(|e| format!("Webhook dispatcher task panicked: {:?}", e))??;
+
+    Ok(tried)
+}
+
+// Runs dispatch_webhooks on a fixed interval for the lifetime of the server
+pub fn spawn_webhook_dispatcher(pool: DbPool, interval: Duration) {
+    tokio::spawn(async move {
+        let client = http_client();
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match dispatch_webhooks(&pool, &client).await {
+                Ok(0) => {}
+                Ok(tried) => log::info!("Tried {} webhook delivery(ies)", tried),
+                Err(e) => log::error!("Failed to dispatch webhooks: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controllers::webhook_controller::{enqueue_event, load_delivery_log, new_webhook, WebhookInput};
+    use crate::models::WebhookEvent::WebhookEvent;
+    use crate::schemas::webhook_schema::webhooks;
+    use crate::table_models::webhook_models::Webhook;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use diesel::r2d2::{self, ConnectionManager};
+    use dotenv::dotenv;
+    use std::env;
+    use std::sync::{Arc, Mutex};
+
+    fn establish_connection() -> DbPool {
+        dotenv().ok();
+        let database_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        r2d2::Pool::builder().build(manager).expect("Failed to create pool.")
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), chrono::Duration::seconds(30));
+        assert_eq!(retry_delay(2), chrono::Duration::seconds(60));
+        assert_eq!(retry_delay(4), chrono::Duration::seconds(240));
+        assert_eq!(retry_delay(40), chrono::Duration::seconds(MAX_RETRY_DELAY_SECONDS));
+    }
+
+    #[test]
+    fn test_new_webhook() {
+        let now = chrono::Utc::now().naive_utc();
+        let input = |url: &str, secret: Option<&str>, events: &[&str]| WebhookInput {
+            url: url.to_string(),
+            secret: secret.map(str::to_string),
+            events: Some(events.iter().map(|event| event.to_string()).collect()),
+        };
+
+        let webhook = new_webhook(input(" https://example.com/hook ", None, &["post.*", "skill.created", "post.*"]), now).unwrap();
+        assert_eq!(webhook.url, "https://example.com/hook");
+        assert_eq!(webhook.secret.len(), 64);
+        assert_eq!(webhook.events, vec!["post.*", "skill.created"]);
+
+        assert!(new_webhook(input("ftp://example.com", None, &[]), now).is_err());
+        assert!(new_webhook(input("not a url", None, &[]), now).is_err());
+        assert!(new_webhook(input("https://example.com", Some("short"), &[]), now).is_err());
+        assert!(new_webhook(input("https://example.com", None, &["post.published"]), now).is_err());
+    }
+
+    // Stands in for a receiver, answering 200 on /ok and 500 on /fail and keeping what it was sent
+    type Received = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    async fn receive(req: HttpRequest, body: web::Bytes, received: web::Data<Received>) -> HttpResponse {
+        let signature = req.headers().get(SIGNATURE_HEADER).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        received.lock().unwrap().push((req.path().to_string(), signature.to_string(), String::from_utf8_lossy(&body).to_string()));
+        if req.path() == "/ok" { HttpResponse::Ok().finish() } else { HttpResponse::InternalServerError().body("stub says no") }
+    }
+
+    fn add_webhook(conn: &mut PgConnection, url: String, events: &[&str]) -> Webhook {
+        let input = WebhookInput {
+            url,
+            secret: Some("spec-webhook-secret".to_string()),
+            events: Some(events.iter().map(|event| event.to_string()).collect()),
+        };
+        diesel::insert_into(webhooks::table)
+            .values(&new_webhook(input, chrono::Utc::now().naive_utc()).unwrap())
+            .returning(Webhook::as_returning())
+            .get_result(conn)
+            .expect("Failed to insert test webhook")
+    }
+
+    #[actix_rt::test]
+    async fn test_dispatch_webhooks() {
+        let received: Received = Arc::new(Mutex::new(Vec::new()));
+        let stub_data = web::Data::new(received.clone());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let stub =
+            HttpServer::new(move || App::new().app_data(stub_data.clone()).default_service(web::to(receive)))
+                .workers(1)
+                .listen(listener)
+                .unwrap()
+                .run();
+        let stub_handle = stub.handle();
+        actix_rt::spawn(stub);
+
+        let pool = establish_connection();
+        let mut conn = pool.get().unwrap();
+        let ok_hook = add_webhook(&mut conn, format!("http://{}/ok", address), &["worklog.*"]);
+        let fail_hook = add_webhook(&mut conn, format!("http://{}/fail", address), &["worklog.deleted"]);
+
+        let event_id = enqueue_event(&mut conn, WebhookEvent::WorklogDeleted, serde_json::json!({ "worklog_id": "spec-webhook-worklog" })).unwrap();
+        let client = http_client();
+        dispatch_webhooks(&pool, &client).await.unwrap();
+
+        // Other tests may delete worklog at the same time, so only our event is looked at
+        let ours = |entries: Vec<crate::controllers::webhook_controller::DeliveryLogEntry>| {
+            entries.into_iter().find(|entry| entry.delivery.event_id == event_id).expect("No delivery of the test event")
+        };
+        let delivered = ours(load_delivery_log(&mut conn, ok_hook.id).unwrap());
+        assert_eq!(delivered.delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivered.delivery.response_status, Some(200));
+        assert_eq!(delivered.delivery.attempts, 1);
+
+        let (_, signature, body) =
+            received
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(path, _, body)| path == "/ok" && body.contains("spec-webhook-worklog"))
+                .cloned()
+                .expect("The stub didn't get the event");
+        assert_eq!(signature, format!("sha256={}", sign_payload("spec-webhook-secret", body.as_bytes())));
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["event"], "worklog.deleted");
+        assert_eq!(body["id"], event_id);
+
+        // A failing receiver is tried again later, with a growing wait in between
+        let retrying = ours(load_delivery_log(&mut conn, fail_hook.id).unwrap());
+        assert_eq!(retrying.delivery.status, DeliveryStatus::Pending);
+        assert_eq!(retrying.delivery.response_status, Some(500));
+        assert!(retrying.delivery.last_error.as_deref().unwrap().contains("stub says no"));
+        assert!(retrying.delivery.next_attempt_at > chrono::Utc::now().naive_utc() + chrono::Duration::seconds(20));
+
+        // Not due yet, so the next round leaves it alone
+        dispatch_webhooks(&pool, &client).await.unwrap();
+        assert_eq!(ours(load_delivery_log(&mut conn, fail_hook.id).unwrap()).delivery.attempts, 1);
+
+        // Once the last try fails as well it is given up on
+        diesel::update(webhook_deliveries::table.find(retrying.delivery.id))
+            .set((
+                webhook_deliveries::attempts.eq(MAX_DELIVERY_ATTEMPTS - 1),
+                webhook_deliveries::next_attempt_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+        dispatch_webhooks(&pool, &client).await.unwrap();
+        let failed = ours(load_delivery_log(&mut conn, fail_hook.id).unwrap());
+        assert_eq!(failed.delivery.status, DeliveryStatus::Failed);
+        assert_eq!(failed.delivery.attempts, MAX_DELIVERY_ATTEMPTS);
+
+        diesel::delete(webhooks::table.filter(webhooks::id.eq_any([ok_hook.id, fail_hook.id]))).execute(&mut conn).unwrap();
+        stub_handle.stop(true).await;
+    }
+}
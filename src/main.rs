@@ -1,19 +1,47 @@
 pub mod connectors;
 pub mod controllers;
+pub mod jobs;
 pub mod models;
 pub mod middleware;
 pub mod schemas;
 pub mod table_models;
+pub mod utils;
 
+use crate::connectors::content_cache::ContentCache;
+use crate::connectors::media_store::media_store_from_env;
 use crate::connectors::postgres_connector::{DbConnector, RealDbConnector};
+use crate::connectors::live_events::LiveEvents;
+use crate::connectors::view_counter::ViewCounter;
+use crate::controllers::analytics_controller::*;
 use crate::controllers::auth_handler::*;
 use crate::controllers::blog_controller::*;
+use crate::controllers::cache_controller::*;
+use crate::controllers::comment_controller::*;
+use crate::controllers::feed_controller::*;
+use crate::controllers::import_export_controller::*;
 use crate::controllers::login_controller::*;
+use crate::controllers::media_controller::*;
+use crate::controllers::post_revision_controller::*;
+use crate::controllers::reaction_controller::*;
 use crate::controllers::register_user_controller::*;
+use crate::controllers::related_controller::*;
+use crate::controllers::live_events_controller::*;
+use crate::controllers::stats_controller::*;
+use crate::controllers::webhook_controller::*;
+use crate::controllers::search_controller::*;
+use crate::controllers::series_controller::*;
 use crate::controllers::skills_controller::*;
+use crate::controllers::slug_controller::*;
+use crate::controllers::tag_controller::*;
+use crate::controllers::trash_controller::*;
+use crate::controllers::translation_controller::*;
 use crate::controllers::validate_user_controller::*;
 use crate::controllers::worklog_controller::create_worklog;
 use crate::controllers::worklog_controller::*;
+use crate::jobs::publish_scheduler::spawn_publish_scheduler;
+use crate::jobs::trash_purger::spawn_trash_purger;
+use crate::jobs::view_rollup::spawn_view_rollup;
+use crate::jobs::webhook_dispatcher::spawn_webhook_dispatcher;
 use actix_cors::Cors;
 use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use dotenv::dotenv;
@@ -74,6 +102,7 @@ async fn test_handler() -> impl Responder {
     HttpResponse::Ok().body("This is a test")
 }
 
+use crate::middleware::cache_control::CacheControl;
 use crate::middleware::rate_limiter::RateLimiter;
 // Import rate limiter
 use actix_web::middleware::Logger;
@@ -98,6 +127,75 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
+    // Content reads are cached in Redis, see CONTENT_CACHE_TTL_SECONDS
+    let content_cache = web::Data::new(ContentCache::from_env(redis_client.clone()));
+
+    // Changes are streamed to /events through Redis pub/sub, so every instance sends the same events
+    let live_events = web::Data::new(LiveEvents::new(redis_client.clone()));
+
+    // Publish scheduled posts in the background once their publish time arrives
+    let publish_interval_seconds =
+        env::var("PUBLISH_SCHEDULER_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .unwrap_or(60);
+    spawn_publish_scheduler(
+        pool.get_ref().clone(),
+        std::time::Duration::from_secs(publish_interval_seconds),
+        content_cache.clone(),
+        live_events.clone(),
+    );
+
+    // Empty the trash of anything deleted longer ago than the retention period
+    let trash_retention_days =
+        env::var("TRASH_RETENTION_DAYS")
+            .ok()
+            .and_then(|days| days.parse::<i64>().ok())
+            .unwrap_or(30);
+    let trash_purge_interval_seconds =
+        env::var("TRASH_PURGE_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .unwrap_or(3600);
+    spawn_trash_purger(
+        pool.get_ref().clone(),
+        std::time::Duration::from_secs(trash_purge_interval_seconds),
+        chrono::Duration::days(trash_retention_days),
+    );
+
+    // Post views are counted in Redis and rolled up into Postgres in the background
+    let view_counter = web::Data::new(ViewCounter::new(redis_client.clone()));
+    let view_rollup_interval_seconds =
+        env::var("VIEW_ROLLUP_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .unwrap_or(3600);
+    spawn_view_rollup(
+        pool.get_ref().clone(),
+        std::time::Duration::from_secs(view_rollup_interval_seconds),
+        view_counter.clone(),
+    );
+
+    // Content events in the outbox are delivered to webhooks in the background
+    let webhook_dispatch_interval_seconds =
+        env::var("WEBHOOK_DISPATCH_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .unwrap_or(10);
+    spawn_webhook_dispatcher(
+        pool.get_ref().clone(),
+        std::time::Duration::from_secs(webhook_dispatch_interval_seconds),
+    );
+
+    // Uploaded images and video go through the configured media store
+    let media_store = match media_store_from_env() {
+        Ok(media_store) => web::Data::from(media_store),
+        Err(e) => {
+            log::error!("Failed to set up the media store: {}", e);
+            return Err(std::io::Error::other("Failed to set up the media store"));
+        }
+    };
+
     // Store Redis client in a web::Data container
     let redis_client_data = Data::new(redis_client.clone());
 
@@ -119,16 +217,67 @@ async fn main() -> std::io::Result<()> {
                 .wrap(cors) // No need for Arc, create a new instance of Cors
                 .app_data(pool.clone()) // Pass the PostgreSQL connection pool to handlers
                 .app_data(redis_client_data.clone()) // Pass the Redis client to handlers
+                .app_data(media_store.clone()) // Pass the media store to the upload and download handlers
+                .app_data(content_cache.clone()) // Pass the content cache to the read and write handlers
+                .app_data(view_counter.clone()) // Pass the view counter to the post read handlers
+                .app_data(live_events.clone()) // Pass the live events to the write handlers and /events
                 .wrap(RateLimiter::new(redis_client_data.clone(), 300, 60)) // Rate limiter
+                .wrap(CacheControl) // Cache-Control for every read route, see CACHE_POLICIES
                 .service(health_check)
                 .service(test_handler)
                 .service(create_post)
+                .service(preview_post)
+                .service(get_highlight_css)
                 .service(get_post)
                 .service(get_by_post_id)
+                .service(get_by_slug)
                 .service(get_all_posts)
                 .service(update_post)
                 .service(delete_post)
                 .service(delete_all_posts)
+                .service(get_post_revisions)
+                .service(diff_post_revisions)
+                .service(get_post_revision)
+                .service(restore_post_revision)
+                .service(create_comment)
+                .service(get_post_comments)
+                .service(get_moderation_queue)
+                .service(moderate)
+                .service(create_tag)
+                .service(get_all_tags)
+                .service(get_tag_cloud)
+                .service(get_content_stats)
+                .service(get_archive)
+                .service(get_tag)
+                .service(update_tag)
+                .service(delete_tag)
+                .service(create_series)
+                .service(get_all_series)
+                .service(get_series)
+                .service(update_series)
+                .service(delete_series)
+                .service(add_post_to_series)
+                .service(remove_post_from_series)
+                .service(get_related_posts)
+                .service(get_post_reactions)
+                .service(add_post_reaction)
+                .service(remove_post_reaction)
+                .service(get_post_locales)
+                .service(put_post_translation)
+                .service(delete_post_translation)
+                .service(get_trash)
+                .service(restore_from_trash)
+                .service(purge_from_trash)
+                .service(empty_trash)
+                .service(get_cache_stats)
+                .service(export_content)
+                .service(import_content)
+                .service(get_post_views)
+                .service(create_webhook)
+                .service(get_webhooks)
+                .service(delete_webhook)
+                .service(get_webhook_deliveries)
+                .service(get_live_events)
                 .service(create_worklog)
                 .service(get_worklog)
                 .service(get_by_worklog_id)
@@ -141,6 +290,12 @@ async fn main() -> std::io::Result<()> {
                 .service(update_skill)
                 .service(delete_skill)
                 .service(get_all_skills)
+                .service(search)
+                .service(rss_feed)
+                .service(atom_feed)
+                .service(json_feed)
+                .service(upload_media)
+                .service(download_media)
                 .service(create_user)
 
                 .service(login)
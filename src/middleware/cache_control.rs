@@ -0,0 +1,136 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderValue, CACHE_CONTROL};
+use actix_web::http::{Method, StatusCode};
+use actix_web::Error;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::task::{Context, Poll};
+
+// Cache-Control for every cacheable route, matched by path prefix with the first match winning.
+// Reads that clients revalidate with ETag/Last-Modified use no-cache, which still allows storing the response.
+const CACHE_POLICIES: &[(&str, &str)] = &[
+    // Uploads are stored under a fresh id and never change afterwards
+    ("/media/", "public, max-age=31536000, immutable"),
+    ("/blog/markdown/highlight.css", "public, max-age=86400"),
+    ("/feed.xml", "public, max-age=300"),
+    ("/atom.xml", "public, max-age=300"),
+    ("/feed.json", "public, max-age=300"),
+    ("/blog/comments/moderation", "no-store"),
+    ("/trash", "no-store"),
+    ("/cache/", "no-store"),
+    ("/export", "no-store"),
+    ("/analytics/", "no-store"),
+    ("/search", "public, max-age=60"),
+    ("/blog/", "public, no-cache"),
+];
+
+// The Cache-Control value for a GET or HEAD of `path`, if the route has a policy.
+// Signed-in users can see drafts, so shared caches must not keep what they were sent.
+pub fn cache_policy(path: &str, signed_in: bool) -> Option<String> {
+    let (_, policy) = CACHE_POLICIES.iter().find(|(prefix, _)| path.starts_with(prefix))?;
+
+    match policy.strip_prefix("public") {
+        Some(rest) if signed_in => Some(format!("private{}", rest)),
+        _ => Some(policy.to_string()),
+    }
+}
+
+// Applies CACHE_POLICIES to successful and 304 responses that haven't set their own Cache-Control
+pub struct CacheControl;
+
+impl<S, B> Transform<S, ServiceRequest> for CacheControl
+where
+    S: Service<ServiceRequest, Response=ServiceResponse<B>, Error=Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CacheControlMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CacheControlMiddleware { service })
+    }
+}
+
+pub struct CacheControlMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for CacheControlMiddleware<S>
+where
+    S: Service<ServiceRequest, Response=ServiceResponse<B>, Error=Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        // Writes are never cached, so only reads get a policy
+        let policy =
+            if req.method() == Method::GET || req.method() == Method::HEAD {
+                cache_policy(req.path(), req.cookie("session_id").is_some())
+            } else {
+                None
+            };
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+
+            let cacheable = res.status().is_success() || res.status() == StatusCode::NOT_MODIFIED;
+            if let Some(policy) = policy.filter(|_| cacheable && !res.headers().contains_key(CACHE_CONTROL)) {
+                if let Ok(value) = HeaderValue::from_str(&policy) {
+                    res.headers_mut().insert(CACHE_CONTROL, value);
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    #[test]
+    fn test_cache_policy() {
+        assert_eq!(cache_policy("/blog/post/get/all", false).as_deref(), Some("public, no-cache"));
+        assert_eq!(cache_policy("/blog/post/get/all", true).as_deref(), Some("private, no-cache"));
+        assert_eq!(cache_policy("/blog/comments/moderation", true).as_deref(), Some("no-store"));
+        assert_eq!(cache_policy("/media/abc", false).as_deref(), Some("public, max-age=31536000, immutable"));
+        assert_eq!(cache_policy("/api/check-email", false), None);
+    }
+
+    #[actix_web::test]
+    async fn test_cache_control_middleware() {
+        let app = init_service(
+            App::new()
+                .wrap(CacheControl)
+                .route("/blog/post/get/all", web::get().to(HttpResponse::Ok))
+                .route("/blog/post/create", web::post().to(HttpResponse::Ok))
+                .route("/blog/post/retrieve/1", web::get().to(HttpResponse::NotFound))
+        ).await;
+
+        let res = call_service(&app, TestRequest::get().uri("/blog/post/get/all").to_request()).await;
+        assert_eq!(res.headers().get(CACHE_CONTROL).unwrap(), "public, no-cache");
+
+        let res = call_service(&app, TestRequest::post().uri("/blog/post/create").to_request()).await;
+        assert!(res.headers().get(CACHE_CONTROL).is_none());
+
+        // Errors aren't cached
+        let res = call_service(&app, TestRequest::get().uri("/blog/post/retrieve/1").to_request()).await;
+        assert!(res.headers().get(CACHE_CONTROL).is_none());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod cache_control;
+
 pub mod jwt_token_service;
 
 pub mod rate_limiter;
\ No newline at end of file
@@ -0,0 +1,23 @@
+use diesel::Queryable;
+use serde::{Deserialize, Serialize};
+
+// The account a post, skill or worklog entry was written by, as shown next to it
+#[derive(Debug, Clone, Queryable, Serialize, Deserialize, PartialEq)]
+pub struct Author {
+    pub user_id: String,
+    pub username: String,
+}
+
+// An item with its author alongside. Content from before authors were recorded has none.
+#[derive(Serialize, Deserialize)]
+pub struct Authored<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub author: Option<Author>,
+}
+
+// Query string for filtering a list endpoint by author username, e.g. ?author=mikey
+#[derive(Debug, Default, Deserialize)]
+pub struct AuthorQuery {
+    pub author: Option<String>,
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::io::Write;
+
+// Moderation state of a comment, only approved comments are shown publicly
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, AsExpression, FromSqlRow)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum CommentStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
+impl CommentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentStatus::Pending => "pending",
+            CommentStatus::Approved => "approved",
+            CommentStatus::Rejected => "rejected",
+            CommentStatus::Spam => "spam",
+        }
+    }
+
+    pub fn parse(value: &[u8]) -> Option<CommentStatus> {
+        match value {
+            b"pending" => Some(CommentStatus::Pending),
+            b"approved" => Some(CommentStatus::Approved),
+            b"rejected" => Some(CommentStatus::Rejected),
+            b"spam" => Some(CommentStatus::Spam),
+            _ => None,
+        }
+    }
+}
+
+use diesel::pg::{Pg, PgValue};
+
+// Stored as its lowercase name in comments.status
+impl ToSql<Text, Pg> for CommentStatus {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for CommentStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        CommentStatus::parse(value.as_bytes()).ok_or_else(|| "Unrecognized comment status".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_status_round_trip() {
+        for status in [CommentStatus::Pending, CommentStatus::Approved, CommentStatus::Rejected, CommentStatus::Spam] {
+            assert_eq!(CommentStatus::parse(status.as_str().as_bytes()), Some(status));
+        }
+        assert_eq!(CommentStatus::parse(b"hidden"), None);
+    }
+}
@@ -0,0 +1,306 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 20;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+// Columns a list endpoint can be sorted by
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    CreatedAt,
+    UpdatedAt,
+    Title,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+// Raw query string for the list endpoints, e.g. ?limit=10&after=<cursor>&sort=title&order=asc&from=2024-01-01
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub limit: Option<i64>,
+    pub after: Option<String>,
+    pub sort: Option<SortField>,
+    pub order: Option<SortOrder>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// Value of the sort column for the last row of a page
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum CursorKey {
+    Timestamp(NaiveDateTime),
+    Text(String),
+}
+
+// Opaque keyset cursor handed back to clients as `next_cursor`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Cursor {
+    pub sort: SortField,
+    pub key: CursorKey,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(raw)
+            .map_err(|_| "Cursor is not valid base64".to_string())?;
+
+        let cursor: Cursor =
+            serde_json::from_slice(&bytes).map_err(|_| "Cursor could not be parsed".to_string())?;
+
+        // The key type has to line up with the column the cursor is compared against
+        match (cursor.sort, &cursor.key) {
+            (SortField::Title, CursorKey::Text(_)) => Ok(cursor),
+            (SortField::CreatedAt | SortField::UpdatedAt, CursorKey::Timestamp(_)) => Ok(cursor),
+            _ => Err("Cursor does not match its sort field".to_string()),
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<NaiveDateTime> {
+        match &self.key {
+            CursorKey::Timestamp(timestamp) => Some(*timestamp),
+            CursorKey::Text(_) => None,
+        }
+    }
+
+    pub fn text(&self) -> Option<String> {
+        match &self.key {
+            CursorKey::Text(text) => Some(text.clone()),
+            CursorKey::Timestamp(_) => None,
+        }
+    }
+}
+
+// Validated form of `ListQuery` that the controllers build their Diesel queries from
+#[derive(Debug, Clone)]
+pub struct ListParams {
+    pub limit: i64,
+    pub after: Option<Cursor>,
+    pub sort: SortField,
+    pub order: SortOrder,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl ListQuery {
+    // Each endpoint keeps its historical ordering when the client does not ask for one
+    pub fn into_params(self, default_order: SortOrder) -> Result<ListParams, String> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+            return Err(format!("limit must be between 1 and {}", MAX_PAGE_LIMIT));
+        }
+
+        let sort = self.sort.unwrap_or(SortField::CreatedAt);
+
+        let after = match self.after {
+            Some(raw) => {
+                let cursor = Cursor::decode(&raw)?;
+                if cursor.sort != sort {
+                    return Err("Cursor was issued for a different sort field".to_string());
+                }
+                Some(cursor)
+            }
+            None => None,
+        };
+
+        Ok(ListParams {
+            limit,
+            after,
+            sort,
+            order: self.order.unwrap_or(default_order),
+            from: self.from.as_deref().map(parse_date_filter).transpose()?,
+            to: self.to.as_deref().map(parse_date_filter).transpose()?,
+        })
+    }
+}
+
+// Accepts either a full RFC 3339 timestamp or a plain YYYY-MM-DD date (midnight UTC)
+pub fn parse_date_filter(raw: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(timestamp.naive_utc());
+    }
+
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+        .map_err(|_| format!("'{}' is not a valid date, expected YYYY-MM-DD or RFC 3339", raw))
+}
+
+// A single page of results returned by the list endpoints
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+impl<T> Page<T> {
+    // `rows` should hold up to `limit + 1` rows; the extra row only signals that another page exists
+    pub fn from_rows<F>(mut rows: Vec<T>, params: &ListParams, total_count: i64, cursor_for: F) -> Self
+    where
+        F: Fn(&T) -> (CursorKey, i32),
+    {
+        let has_more = rows.len() as i64 > params.limit;
+        rows.truncate(params.limit as usize);
+
+        let next_cursor =
+            if has_more {
+                rows.last().map(|row| {
+                    let (key, id) = cursor_for(row);
+                    Cursor { sort: params.sort, key, id }.encode()
+                })
+            } else {
+                None
+            };
+
+        Page {
+            items: rows,
+            next_cursor,
+            total_count,
+        }
+    }
+
+    // Swaps the rows of a page for their response shape, the cursor and total stay as they are
+    pub fn map_items<U, F>(self, map: F) -> Page<U>
+    where
+        F: FnOnce(Vec<T>) -> Vec<U>,
+    {
+        Page {
+            items: map(self.items),
+            next_cursor: self.next_cursor,
+            total_count: self.total_count,
+        }
+    }
+
+    pub fn try_map_items<U, E, F>(self, map: F) -> Result<Page<U>, E>
+    where
+        F: FnOnce(Vec<T>) -> Result<Vec<U>, E>,
+    {
+        Ok(Page {
+            items: map(self.items)?,
+            next_cursor: self.next_cursor,
+            total_count: self.total_count,
+        })
+    }
+}
+
+// Applies the keyset condition for `params.after` and the matching ORDER BY to a boxed query.
+// Rows are ordered by the sort column with the serial id as a tie breaker so the cursor is stable.
+#[macro_export]
+macro_rules! keyset_paginate {
+    ($query:expr, $column:expr, $id:expr, $order:expr, $after:expr) => {{
+        use $crate::models::Pagination::SortOrder;
+
+        let mut query = $query;
+        if let Some((value, id)) = $after {
+            query = match $order {
+                SortOrder::Desc => query.filter($column.lt(value.clone()).or($column.eq(value).and($id.lt(id)))),
+                SortOrder::Asc => query.filter($column.gt(value.clone()).or($column.eq(value).and($id.gt(id)))),
+            };
+        }
+
+        match $order {
+            SortOrder::Desc => query.order(($column.desc(), $id.desc())),
+            SortOrder::Asc => query.order(($column.asc(), $id.asc())),
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip_timestamp() {
+        let timestamp =
+            NaiveDateTime::parse_from_str("2024-08-29 14:30:00.123456", "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        let cursor = Cursor { sort: SortField::CreatedAt, key: CursorKey::Timestamp(timestamp), id: 42 };
+
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+
+        assert_eq!(decoded, cursor);
+        assert_eq!(decoded.timestamp(), Some(timestamp));
+    }
+
+    #[test]
+    fn test_cursor_round_trip_title() {
+        let cursor = Cursor { sort: SortField::Title, key: CursorKey::Text("Hello & goodbye".to_string()), id: 7 };
+
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+
+        assert_eq!(decoded.text(), Some("Hello & goodbye".to_string()));
+        assert_eq!(decoded.id, 7);
+    }
+
+    #[test]
+    fn test_cursor_rejects_garbage() {
+        assert!(Cursor::decode("not a cursor!").is_err());
+    }
+
+    #[test]
+    fn test_into_params_rejects_cursor_for_other_sort() {
+        let cursor = Cursor { sort: SortField::Title, key: CursorKey::Text("a".to_string()), id: 1 };
+        let query = ListQuery {
+            after: Some(cursor.encode()),
+            sort: Some(SortField::UpdatedAt),
+            ..Default::default()
+        };
+
+        assert!(query.into_params(SortOrder::Desc).is_err());
+    }
+
+    #[test]
+    fn test_into_params_limits() {
+        let too_big = ListQuery { limit: Some(MAX_PAGE_LIMIT + 1), ..Default::default() };
+        assert!(too_big.into_params(SortOrder::Desc).is_err());
+
+        let defaults = ListQuery::default().into_params(SortOrder::Asc).unwrap();
+        assert_eq!(defaults.limit, DEFAULT_PAGE_LIMIT);
+        assert_eq!(defaults.sort, SortField::CreatedAt);
+        assert_eq!(defaults.order, SortOrder::Asc);
+    }
+
+    #[test]
+    fn test_parse_date_filter() {
+        let date = parse_date_filter("2024-08-29").unwrap();
+        assert_eq!(date.to_string(), "2024-08-29 00:00:00");
+
+        let timestamp = parse_date_filter("2024-08-29T14:00:00Z").unwrap();
+        assert_eq!(timestamp.to_string(), "2024-08-29 14:00:00");
+
+        assert!(parse_date_filter("29/08/2024").is_err());
+    }
+
+    #[test]
+    fn test_page_from_rows_sets_next_cursor_only_when_more_rows_exist() {
+        let params = ListQuery { limit: Some(2), sort: Some(SortField::Title), ..Default::default() }
+            .into_params(SortOrder::Asc)
+            .unwrap();
+
+        let page = Page::from_rows(vec![(1, "a"), (2, "b"), (3, "c")], &params, 3, |row| {
+            (CursorKey::Text(row.1.to_string()), row.0)
+        });
+        assert_eq!(page.items.len(), 2);
+        let cursor = Cursor::decode(page.next_cursor.as_deref().unwrap()).unwrap();
+        assert_eq!(cursor.id, 2);
+        assert_eq!(cursor.text(), Some("b".to_string()));
+
+        let last_page = Page::from_rows(vec![(3, "c")], &params, 3, |row| {
+            (CursorKey::Text(row.1.to_string()), row.0)
+        });
+        assert!(last_page.next_cursor.is_none());
+    }
+}
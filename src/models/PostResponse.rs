@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Author::Author;
+use crate::models::Reaction::ReactionCounts;
+use crate::models::SeriesNavigation::SeriesNavigation;
+use crate::table_models::blog_models::Post;
+use crate::table_models::tag_models::TagLabel;
+
+// A post as returned by the blog endpoints, the post columns with its tags, author, series and reactions alongside
+#[derive(Serialize, Deserialize)]
+pub struct PostResponse {
+    #[serde(flatten)]
+    pub post: Post,
+    pub tags: Vec<TagLabel>,
+    pub author: Option<Author>,
+    pub series: Option<SeriesNavigation>,
+    pub reactions: ReactionCounts,
+    // The translation title and body are in, None for the original
+    pub locale: Option<String>,
+}
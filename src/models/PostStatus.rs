@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::io::Write;
+
+// Lifecycle of a blog post, only published posts are visible to the public endpoints
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, AsExpression, FromSqlRow)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum PostStatus {
+    Draft,
+    Published,
+    Scheduled,
+    Archived,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Published => "published",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Archived => "archived",
+        }
+    }
+
+    pub fn parse(value: &[u8]) -> Option<PostStatus> {
+        match value {
+            b"draft" => Some(PostStatus::Draft),
+            b"published" => Some(PostStatus::Published),
+            b"scheduled" => Some(PostStatus::Scheduled),
+            b"archived" => Some(PostStatus::Archived),
+            _ => None,
+        }
+    }
+}
+
+use diesel::pg::{Pg, PgValue};
+
+// Stored as its lowercase name in posts.status
+impl ToSql<Text, Pg> for PostStatus {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for PostStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        PostStatus::parse(value.as_bytes()).ok_or_else(|| "Unrecognized post status".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_status_round_trip() {
+        for status in [PostStatus::Draft, PostStatus::Published, PostStatus::Scheduled, PostStatus::Archived] {
+            assert_eq!(PostStatus::parse(status.as_str().as_bytes()), Some(status));
+        }
+        assert_eq!(PostStatus::parse(b"deleted"), None);
+    }
+
+    #[test]
+    fn test_post_status_json() {
+        assert_eq!(serde_json::to_string(&PostStatus::Scheduled).unwrap(), "\"scheduled\"");
+        assert_eq!(serde_json::from_str::<PostStatus>("\"draft\"").unwrap(), PostStatus::Draft);
+    }
+}
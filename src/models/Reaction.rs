@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::io::Write;
+
+use crate::table_models::blog_models::Post;
+
+// The reactions a reader can leave on a post, each at most once
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, AsExpression, FromSqlRow)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum Reaction {
+    Like,
+    Love,
+    Insightful,
+}
+
+impl Reaction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reaction::Like => "like",
+            Reaction::Love => "love",
+            Reaction::Insightful => "insightful",
+        }
+    }
+
+    pub fn parse(value: &[u8]) -> Option<Reaction> {
+        match value {
+            b"like" => Some(Reaction::Like),
+            b"love" => Some(Reaction::Love),
+            b"insightful" => Some(Reaction::Insightful),
+            _ => None,
+        }
+    }
+}
+
+use diesel::pg::{Pg, PgValue};
+
+// Stored as its lowercase name in post_reactions.reaction
+impl ToSql<Text, Pg> for Reaction {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for Reaction {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        Reaction::parse(value.as_bytes()).ok_or_else(|| "Unrecognized reaction".into())
+    }
+}
+
+// How often each reaction was left on a post, as kept in the post's count columns
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ReactionCounts {
+    pub like: i32,
+    pub love: i32,
+    pub insightful: i32,
+}
+
+impl From<&Post> for ReactionCounts {
+    fn from(post: &Post) -> Self {
+        ReactionCounts {
+            like: post.like_count,
+            love: post.love_count,
+            insightful: post.insightful_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reaction_round_trip() {
+        for reaction in [Reaction::Like, Reaction::Love, Reaction::Insightful] {
+            assert_eq!(Reaction::parse(reaction.as_str().as_bytes()), Some(reaction));
+        }
+        assert_eq!(Reaction::parse(b"dislike"), None);
+    }
+}
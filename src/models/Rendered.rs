@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Author::Authored;
+use crate::models::PostResponse::PostResponse;
+use crate::table_models::blog_models::Post;
+use crate::table_models::skills_models::Skill;
+use crate::table_models::worklog_models::Worklog;
+use crate::utils::markdown::{render_markdown, RenderedMarkdown};
+
+// Query string for the retrieval endpoints, ?render=true adds body_html and toc to every item
+#[derive(Debug, Default, Deserialize)]
+pub struct RenderQuery {
+    #[serde(default)]
+    pub render: bool,
+}
+
+// Anything whose body is stored as Markdown
+pub trait MarkdownBody {
+    fn markdown_body(&self) -> &str;
+}
+
+impl MarkdownBody for Post {
+    fn markdown_body(&self) -> &str {
+        &self.body
+    }
+}
+
+impl MarkdownBody for PostResponse {
+    fn markdown_body(&self) -> &str {
+        &self.post.body
+    }
+}
+
+impl MarkdownBody for Skill {
+    fn markdown_body(&self) -> &str {
+        &self.body
+    }
+}
+
+impl MarkdownBody for Worklog {
+    fn markdown_body(&self) -> &str {
+        &self.body
+    }
+}
+
+impl<T: MarkdownBody> MarkdownBody for Authored<T> {
+    fn markdown_body(&self) -> &str {
+        self.item.markdown_body()
+    }
+}
+
+// An item as stored, plus its rendered body when the client asked for one
+#[derive(Serialize)]
+pub struct Rendered<T> {
+    #[serde(flatten)]
+    pub item: T,
+    #[serde(flatten)]
+    pub rendered: Option<RenderedMarkdown>,
+}
+
+impl<T: MarkdownBody> Rendered<T> {
+    pub fn new(item: T, render: bool) -> Self {
+        let rendered = if render { Some(render_markdown(item.markdown_body())) } else { None };
+        Rendered { item, rendered }
+    }
+
+    pub fn all(items: Vec<T>, render: bool) -> Vec<Self> {
+        items.into_iter().map(|item| Rendered::new(item, render)).collect()
+    }
+}
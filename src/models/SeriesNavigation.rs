@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+// A neighbouring post in a series, enough to link to it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SeriesPostLink {
+    pub post_id: String,
+    pub slug: String,
+    pub title: String,
+    pub position: i32,
+}
+
+// A post's place in its series as returned with the post. Only published posts are counted and linked,
+// and positions are part numbers among them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SeriesNavigation {
+    pub slug: String,
+    pub title: String,
+    pub position: i32,
+    pub post_count: usize,
+    pub previous: Option<SeriesPostLink>,
+    pub next: Option<SeriesPostLink>,
+}
@@ -5,4 +5,15 @@ use serde::{Deserialize, Serialize};
 pub struct SessionData {
     pub user_id: String,
     pub role: String, // admin, viewer, etc.
-}
\ No newline at end of file
+}
+
+impl SessionData {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    // Admins and editors can see unpublished content and moderate what viewers submit
+    pub fn is_editor_or_admin(&self) -> bool {
+        self.role == "admin" || self.role == "editor"
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::io::Write;
+
+// A change to content that webhooks can subscribe to, named <kind>.<action> on the wire
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum WebhookEvent {
+    #[serde(rename = "post.created")]
+    PostCreated,
+    #[serde(rename = "post.updated")]
+    PostUpdated,
+    #[serde(rename = "post.deleted")]
+    PostDeleted,
+    #[serde(rename = "skill.created")]
+    SkillCreated,
+    #[serde(rename = "skill.updated")]
+    SkillUpdated,
+    #[serde(rename = "skill.deleted")]
+    SkillDeleted,
+    #[serde(rename = "worklog.created")]
+    WorklogCreated,
+    #[serde(rename = "worklog.updated")]
+    WorklogUpdated,
+    #[serde(rename = "worklog.deleted")]
+    WorklogDeleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 9] = [
+        WebhookEvent::PostCreated,
+        WebhookEvent::PostUpdated,
+        WebhookEvent::PostDeleted,
+        WebhookEvent::SkillCreated,
+        WebhookEvent::SkillUpdated,
+        WebhookEvent::SkillDeleted,
+        WebhookEvent::WorklogCreated,
+        WebhookEvent::WorklogUpdated,
+        WebhookEvent::WorklogDeleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::PostCreated => "post.created",
+            WebhookEvent::PostUpdated => "post.updated",
+            WebhookEvent::PostDeleted => "post.deleted",
+            WebhookEvent::SkillCreated => "skill.created",
+            WebhookEvent::SkillUpdated => "skill.updated",
+            WebhookEvent::SkillDeleted => "skill.deleted",
+            WebhookEvent::WorklogCreated => "worklog.created",
+            WebhookEvent::WorklogUpdated => "worklog.updated",
+            WebhookEvent::WorklogDeleted => "worklog.deleted",
+        }
+    }
+
+    pub fn parse(value: &[u8]) -> Option<WebhookEvent> {
+        WebhookEvent::ALL.into_iter().find(|event| event.as_str().as_bytes() == value)
+    }
+
+    // Whether an entry of a webhook's event filter is an event name or a whole kind such as "post.*"
+    pub fn is_valid_filter(filter: &str) -> bool {
+        WebhookEvent::ALL
+            .iter()
+            .any(|event| event.as_str() == filter || event.as_str().split('.').next().map(|kind| format!("{}.*", kind)).as_deref() == Some(filter))
+    }
+}
+
+use diesel::pg::{Pg, PgValue};
+
+// Stored by its wire name in webhook_events.event
+impl ToSql<Text, Pg> for WebhookEvent {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for WebhookEvent {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        WebhookEvent::parse(value.as_bytes()).ok_or_else(|| "Unrecognized webhook event".into())
+    }
+}
+
+// Where a delivery of an event to one webhook stands
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, AsExpression, FromSqlRow)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &[u8]) -> Option<DeliveryStatus> {
+        match value {
+            b"pending" => Some(DeliveryStatus::Pending),
+            b"delivered" => Some(DeliveryStatus::Delivered),
+            b"failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for DeliveryStatus {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for DeliveryStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        DeliveryStatus::parse(value.as_bytes()).ok_or_else(|| "Unrecognized delivery status".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_event_round_trip() {
+        for event in WebhookEvent::ALL {
+            assert_eq!(WebhookEvent::parse(event.as_str().as_bytes()), Some(event));
+            assert_eq!(serde_json::to_value(event).unwrap(), event.as_str());
+        }
+        assert_eq!(WebhookEvent::parse(b"post.published"), None);
+
+        assert!(WebhookEvent::is_valid_filter("skill.updated"));
+        assert!(WebhookEvent::is_valid_filter("worklog.*"));
+        assert!(!WebhookEvent::is_valid_filter("*"));
+        assert!(!WebhookEvent::is_valid_filter("comment.*"));
+    }
+}
@@ -1,10 +1,22 @@
+pub mod Author;
+
+pub mod CommentStatus;
+
 pub mod LoginRequest;
 
 pub mod LogoutRequest;
 
 pub mod LogoutResponse;
+pub mod Pagination;
+pub mod PostResponse;
+pub mod PostStatus;
+pub mod Reaction;
+pub mod Rendered;
+pub mod SeriesNavigation;
 pub mod SessionData;
 
 pub mod UserType;
 
+pub mod WebhookEvent;
+
 pub mod UserRoleResponse;
\ No newline at end of file
@@ -3,12 +3,26 @@
 use diesel::table;
 
 table! {
+    use diesel::sql_types::*;
+    use crate::schemas::sql_types::Tsvector;
+
     posts (id) {
         id -> Int4,
         post_id -> Varchar,
         title -> Varchar,
         body -> Text,
         created_at -> Timestamp,
-        updated_at -> Timestamp
+        updated_at -> Timestamp,
+        search_vector -> Tsvector,
+        status -> Varchar,
+        publish_at -> Nullable<Timestamp>,
+        slug -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+        author_id -> Nullable<Varchar>,
+        series_id -> Nullable<Int4>,
+        series_position -> Nullable<Int4>,
+        like_count -> Int4,
+        love_count -> Int4,
+        insightful_count -> Int4
     }
 }
\ No newline at end of file
@@ -0,0 +1,19 @@
+// src/comment_schema
+
+use diesel::table;
+
+table! {
+    comments (id) {
+        id -> Int4,
+        post_id -> Varchar,
+        parent_id -> Nullable<Int4>,
+        author_id -> Nullable<Varchar>,
+        author_name -> Varchar,
+        body -> Text,
+        status -> Varchar,
+        moderated_by -> Nullable<Varchar>,
+        moderated_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp
+    }
+}
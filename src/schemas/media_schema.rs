@@ -0,0 +1,16 @@
+// src/media_schema
+
+use diesel::table;
+
+table! {
+    media (id) {
+        id -> Int4,
+        media_id -> Varchar,
+        file_name -> Varchar,
+        content_type -> Varchar,
+        size_bytes -> Int8,
+        checksum_sha256 -> Varchar,
+        owner_id -> Nullable<Varchar>,
+        created_at -> Timestamp
+    }
+}
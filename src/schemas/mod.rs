@@ -1,4 +1,26 @@
 pub mod blog_schema;
+pub mod comment_schema;
+pub mod media_schema;
+pub mod post_reaction_schema;
+pub mod post_revision_schema;
+pub mod post_translation_schema;
+pub mod post_view_schema;
+pub mod series_schema;
 pub mod skills_schema;
+pub mod slug_history_schema;
+pub mod sql_types;
+pub mod tag_schema;
 pub mod user_schema;
+pub mod webhook_schema;
 pub mod worklog_schema;
+
+use blog_schema::posts;
+use post_reaction_schema::post_reactions;
+use post_view_schema::post_views;
+use series_schema::series;
+use slug_history_schema::slug_history;
+use tag_schema::{post_tags, tags};
+use webhook_schema::{webhook_deliveries, webhook_events, webhooks};
+
+diesel::allow_tables_to_appear_in_same_query!(posts, post_reactions, post_tags, post_views, series, slug_history, tags);
+diesel::allow_tables_to_appear_in_same_query!(webhooks, webhook_events, webhook_deliveries);
@@ -0,0 +1,14 @@
+// src/post_reaction_schema
+
+use diesel::table;
+
+table! {
+    post_reactions (id) {
+        id -> Int4,
+        post_id -> Varchar,
+        reaction -> Varchar,
+        user_id -> Nullable<Varchar>,
+        reader_token -> Nullable<Varchar>,
+        created_at -> Timestamp
+    }
+}
@@ -0,0 +1,15 @@
+// src/post_revision_schema
+
+use diesel::table;
+
+table! {
+    post_revisions (id) {
+        id -> Int4,
+        post_id -> Varchar,
+        revision_number -> Int4,
+        title -> Varchar,
+        body -> Text,
+        author_id -> Nullable<Varchar>,
+        created_at -> Timestamp
+    }
+}
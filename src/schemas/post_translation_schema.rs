@@ -0,0 +1,15 @@
+// src/post_translation_schema
+
+use diesel::table;
+
+table! {
+    post_translations (id) {
+        id -> Int4,
+        post_id -> Varchar,
+        locale -> Varchar,
+        title -> Varchar,
+        body -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp
+    }
+}
@@ -0,0 +1,14 @@
+// src/post_view_schema
+
+use diesel::table;
+
+table! {
+    post_views (id) {
+        id -> Int4,
+        post_id -> Varchar,
+        day -> Date,
+        views -> Int8,
+        uniques -> Int8,
+        updated_at -> Timestamp
+    }
+}
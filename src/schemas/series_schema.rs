@@ -0,0 +1,14 @@
+// src/series_schema
+
+use diesel::table;
+
+table! {
+    series (id) {
+        id -> Int4,
+        title -> Varchar,
+        slug -> Varchar,
+        description -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp
+    }
+}
@@ -3,12 +3,18 @@
 use diesel::table;
 
 table! {
+    use diesel::sql_types::*;
+    use crate::schemas::sql_types::Tsvector;
+
     skills (id) {
         id -> Int4,
         skill_id -> Varchar,
         skill_name -> Varchar,
         body -> Text,
         created_at -> Timestamp,
-        updated_at -> Timestamp
+        updated_at -> Timestamp,
+        search_vector -> Tsvector,
+        deleted_at -> Nullable<Timestamp>,
+        author_id -> Nullable<Varchar>
     }
 }
\ No newline at end of file
@@ -0,0 +1,12 @@
+// src/slug_history_schema
+
+use diesel::table;
+
+table! {
+    slug_history (id) {
+        id -> Int4,
+        post_id -> Varchar,
+        slug -> Varchar,
+        created_at -> Timestamp
+    }
+}
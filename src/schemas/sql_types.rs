@@ -0,0 +1,8 @@
+// Postgres types that Diesel does not ship with
+
+use diesel::query_builder::QueryId;
+use diesel::sql_types::SqlType;
+
+#[derive(QueryId, SqlType)]
+#[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+pub struct Tsvector;
@@ -0,0 +1,22 @@
+// src/tag_schema
+
+use diesel::{joinable, table};
+
+table! {
+    tags (id) {
+        id -> Int4,
+        name -> Varchar,
+        slug -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp
+    }
+}
+
+table! {
+    post_tags (post_id, tag_id) {
+        post_id -> Varchar,
+        tag_id -> Int4
+    }
+}
+
+joinable!(post_tags -> tags (tag_id));
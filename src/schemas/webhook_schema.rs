@@ -0,0 +1,44 @@
+// src/webhook_schema
+
+use diesel::{joinable, table};
+
+table! {
+    webhooks (id) {
+        id -> Int4,
+        url -> Text,
+        secret -> Varchar,
+        events -> Array<Text>,
+        active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp
+    }
+}
+
+table! {
+    webhook_events (id) {
+        id -> Int4,
+        event -> Varchar,
+        payload -> Text,
+        created_at -> Timestamp,
+        dispatched_at -> Nullable<Timestamp>
+    }
+}
+
+table! {
+    webhook_deliveries (id) {
+        id -> Int4,
+        webhook_id -> Int4,
+        event_id -> Int4,
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp
+    }
+}
+
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhook_deliveries -> webhook_events (event_id));
@@ -2,6 +2,9 @@ use diesel::table;
 
 
 table! {
+    use diesel::sql_types::*;
+    use crate::schemas::sql_types::Tsvector;
+
     worklog (id) {
         id -> Int4,
         worklog_id -> Varchar,
@@ -9,5 +12,8 @@ table! {
         body -> Text,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        search_vector -> Tsvector,
+        deleted_at -> Nullable<Timestamp>,
+        author_id -> Nullable<Varchar>,
     }
 }
@@ -1,10 +1,12 @@
 use chrono::NaiveDateTime;
-use diesel::{Insertable, Queryable};
+use diesel::{Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 
+use crate::models::PostStatus::PostStatus;
 use crate::schemas::blog_schema::posts;
 
-#[derive(Insertable, Queryable, Serialize, Deserialize)]
+#[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = posts)]
 pub struct Post {
     pub id: i32,
     pub post_id: String,
@@ -12,6 +14,20 @@ pub struct Post {
     pub body: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub status: PostStatus,
+    pub publish_at: Option<NaiveDateTime>,
+    pub slug: String,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub author_id: Option<String>,
+    pub series_id: Option<i32>,
+    pub series_position: Option<i32>,
+    // Reaction totals, returned as `reactions` alongside the post
+    #[serde(skip)]
+    pub like_count: i32,
+    #[serde(skip)]
+    pub love_count: i32,
+    #[serde(skip)]
+    pub insightful_count: i32,
 }
 
 #[derive(Insertable, Queryable, Serialize, Deserialize)]
@@ -23,4 +39,8 @@ pub struct NewPost {
     pub body: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub status: PostStatus,
+    pub publish_at: Option<NaiveDateTime>,
+    pub slug: String,
+    pub author_id: Option<String>,
 }
\ No newline at end of file
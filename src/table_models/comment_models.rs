@@ -0,0 +1,35 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::models::CommentStatus::CommentStatus;
+use crate::schemas::comment_schema::comments;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = comments)]
+pub struct Comment {
+    pub id: i32,
+    pub post_id: String,
+    pub parent_id: Option<i32>,
+    pub author_id: Option<String>,
+    pub author_name: String,
+    pub body: String,
+    pub status: CommentStatus,
+    pub moderated_by: Option<String>,
+    pub moderated_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = comments)]
+pub struct NewComment {
+    pub post_id: String,
+    pub parent_id: Option<i32>,
+    pub author_id: Option<String>,
+    pub author_name: String,
+    pub body: String,
+    pub status: CommentStatus,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
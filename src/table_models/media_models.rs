@@ -0,0 +1,30 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::media_schema::media;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = media)]
+pub struct Media {
+    pub id: i32,
+    pub media_id: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub checksum_sha256: String,
+    pub owner_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = media)]
+pub struct NewMedia {
+    pub media_id: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub checksum_sha256: String,
+    pub owner_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
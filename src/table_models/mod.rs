@@ -1,4 +1,14 @@
 pub mod blog_models;
+pub mod comment_models;
+pub mod media_models;
+pub mod post_reaction_models;
+pub mod post_revision_models;
+pub mod post_translation_models;
+pub mod post_view_models;
+pub mod series_models;
 pub mod skills_models;
+pub mod slug_history_models;
+pub mod tag_models;
 pub mod users;
+pub mod webhook_models;
 pub mod worklog_models;
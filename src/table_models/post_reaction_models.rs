@@ -0,0 +1,17 @@
+use chrono::NaiveDateTime;
+use diesel::Insertable;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Reaction::Reaction;
+use crate::schemas::post_reaction_schema::post_reactions;
+
+// Exactly one of `user_id` and `reader_token` is set
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = post_reactions)]
+pub struct NewPostReaction {
+    pub post_id: String,
+    pub reaction: Reaction,
+    pub user_id: Option<String>,
+    pub reader_token: Option<String>,
+    pub created_at: NaiveDateTime,
+}
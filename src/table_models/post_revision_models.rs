@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::post_revision_schema::post_revisions;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = post_revisions)]
+pub struct PostRevision {
+    pub id: i32,
+    pub post_id: String,
+    pub revision_number: i32,
+    pub title: String,
+    pub body: String,
+    pub author_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = post_revisions)]
+pub struct NewPostRevision {
+    pub post_id: String,
+    pub revision_number: i32,
+    pub title: String,
+    pub body: String,
+    pub author_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::post_translation_schema::post_translations;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = post_translations)]
+pub struct PostTranslation {
+    pub id: i32,
+    pub post_id: String,
+    pub locale: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = post_translations)]
+pub struct NewPostTranslation {
+    pub post_id: String,
+    pub locale: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
@@ -0,0 +1,15 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::Insertable;
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::post_view_schema::post_views;
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = post_views)]
+pub struct NewPostViews {
+    pub post_id: String,
+    pub day: NaiveDate,
+    pub views: i64,
+    pub uniques: i64,
+    pub updated_at: NaiveDateTime,
+}
@@ -0,0 +1,26 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::series_schema::series;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = series)]
+pub struct Series {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub description: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = series)]
+pub struct NewSeries {
+    pub title: String,
+    pub slug: String,
+    pub description: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
@@ -1,16 +1,19 @@
 use chrono::NaiveDateTime;
-use diesel::{Insertable, Queryable};
+use diesel::{Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use crate::schemas::skills_schema::skills;  // Make sure this is the correct import for your schema
 
-#[derive(Queryable, Serialize, Deserialize)]
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = skills)]
 pub struct Skill {
     pub id: i32,
     pub skill_id: String,
     pub skill_name: String,
     pub body: String,
     pub created_at: NaiveDateTime,
-    pub updated_at: NaiveDateTime
+    pub updated_at: NaiveDateTime,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub author_id: Option<String>,
 }
 
 #[derive(Insertable, Serialize, Deserialize)]
@@ -20,5 +23,6 @@ pub struct NewSkill {
     pub skill_name: String,
     pub body: String,
     pub created_at: NaiveDateTime,
-    pub updated_at: NaiveDateTime
+    pub updated_at: NaiveDateTime,
+    pub author_id: Option<String>,
 }
@@ -0,0 +1,13 @@
+use chrono::NaiveDateTime;
+use diesel::Insertable;
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::slug_history_schema::slug_history;
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = slug_history)]
+pub struct NewSlugHistory {
+    pub post_id: String,
+    pub slug: String,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::tag_schema::{post_tags, tags};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = tags)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+    pub slug: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = tags)]
+pub struct NewTag {
+    pub name: String,
+    pub slug: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_tags)]
+pub struct NewPostTag {
+    pub post_id: String,
+    pub tag_id: i32,
+}
+
+// The name and slug of a tag as embedded in post responses
+#[derive(Queryable, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TagLabel {
+    pub name: String,
+    pub slug: String,
+}
@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+
+use crate::models::WebhookEvent::{DeliveryStatus, WebhookEvent};
+use crate::schemas::webhook_schema::{webhook_deliveries, webhook_events, webhooks};
+
+// The secret signs every delivery, it is only shown once when the webhook is created
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = webhooks)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// `payload` is the JSON of the event's data, as it was when the change was made
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = webhook_events)]
+pub struct NewWebhookEvent {
+    pub event: WebhookEvent,
+    pub payload: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_id: i32,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
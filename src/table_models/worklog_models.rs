@@ -1,7 +1,8 @@
 use chrono::NaiveDateTime;
-use diesel::Queryable;
+use diesel::{Queryable, Selectable};
 use serde::{Deserialize, Serialize};
-#[derive(Queryable, Serialize, Deserialize)]
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = worklog)]
 pub struct Worklog {
     pub id: i32,
     pub worklog_id: String,
@@ -9,6 +10,8 @@ pub struct Worklog {
     pub body: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub author_id: Option<String>,
 }
 
 use crate::schemas::worklog_schema::worklog;
@@ -22,4 +25,5 @@ pub struct NewWorklog {
     pub body: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub author_id: Option<String>,
 }
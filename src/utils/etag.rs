@@ -0,0 +1,196 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header::{self, EntityTag, HttpDate, IfMatch, IfModifiedSince, IfNoneMatch, LastModified};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+// Posts, skills and worklog entries are versioned by updated_at, which every write bumps
+pub fn etag_for(updated_at: NaiveDateTime) -> EntityTag {
+    EntityTag::new_strong(format!("{:x}", updated_at.and_utc().timestamp_micros()))
+}
+
+// A post's body also carries reactions, tags and series navigation, which change without bumping updated_at,
+// so its tag is the version followed by a digest of the body. If-Match only compares the version part.
+pub fn versioned_etag(updated_at: NaiveDateTime, body: &[u8]) -> EntityTag {
+    EntityTag::new_strong(format!("{}-{}", etag_for(updated_at).tag(), digest_hex(body)))
+}
+
+// The version a write has to find unchanged, given the one it just read as `updated_at`.
+// None without an If-Match header, a 412 response when If-Match names another version or can't be parsed.
+pub fn if_match_version(req: &HttpRequest, updated_at: NaiveDateTime) -> Result<Option<NaiveDateTime>, HttpResponse> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Ok(None);
+    }
+
+    let holds =
+        match req.get_header::<IfMatch>() {
+            Some(IfMatch::Any) => true,
+            Some(IfMatch::Items(tags)) => {
+                let current = etag_for(updated_at);
+                tags.iter().any(|tag| tag.strong_eq(&current) || (!tag.weak && is_version_of(tag.tag(), current.tag())))
+            }
+            None => false,
+        };
+
+    if holds { Ok(Some(updated_at)) } else { Err(precondition_failed(updated_at)) }
+}
+
+// Whether `tag` is a versioned_etag of `version`
+fn is_version_of(tag: &str, version: &str) -> bool {
+    tag.strip_prefix(version).is_some_and(|digest| digest.starts_with('-'))
+}
+
+// Sent when the If-Match version is stale, with the current ETag so the client can refetch and retry
+pub fn precondition_failed(updated_at: NaiveDateTime) -> HttpResponse {
+    HttpResponse::PreconditionFailed()
+        .insert_header(header::ETag(etag_for(updated_at)))
+        .json(json!({ "error": "The resource has been modified since it was fetched" }))
+}
+
+// Lists have no single updated_at, so they are tagged by a hash of the body itself
+pub fn content_etag(body: &[u8]) -> EntityTag {
+    EntityTag::new_weak(digest_hex(body))
+}
+
+fn digest_hex(body: &[u8]) -> String {
+    Sha256::digest(body)[..16].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn to_system_time(timestamp: NaiveDateTime) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(timestamp.and_utc().timestamp().max(0) as u64)
+}
+
+// HTTP dates only have whole seconds, so compare at that precision
+pub fn is_not_modified(if_modified_since: Option<SystemTime>, last_modified: Option<NaiveDateTime>) -> bool {
+    match (if_modified_since, last_modified) {
+        (Some(since), Some(last_modified)) => to_system_time(last_modified) <= since,
+        _ => false,
+    }
+}
+
+// Whether the client's cached copy is still current. If-None-Match wins over If-Modified-Since when both are sent.
+fn client_is_current(req: &HttpRequest, etag: &EntityTag, last_modified: Option<NaiveDateTime>) -> bool {
+    if req.headers().contains_key(header::IF_NONE_MATCH) {
+        return match req.get_header::<IfNoneMatch>() {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+            None => false,
+        };
+    }
+
+    let if_modified_since = req.get_header::<IfModifiedSince>().map(|header| SystemTime::from(header.0));
+    is_not_modified(if_modified_since, last_modified)
+}
+
+// A 200 JSON response carrying ETag and Last-Modified, or an empty 304 when the client already has this version.
+// Without an `etag` the body is tagged by its content.
+pub fn conditional_json<T: Serialize>(
+    req: &HttpRequest,
+    etag: Option<EntityTag>,
+    last_modified: Option<NaiveDateTime>,
+    body: &T,
+) -> HttpResponse {
+    match serde_json::to_vec(body) {
+        Ok(body) => conditional_body(req, etag, last_modified, body),
+        Err(e) => {
+            eprintln!("Error serializing response: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// conditional_json for a body that is already serialized
+pub fn conditional_body(
+    req: &HttpRequest,
+    etag: Option<EntityTag>,
+    last_modified: Option<NaiveDateTime>,
+    body: Vec<u8>,
+) -> HttpResponse {
+    let etag = etag.unwrap_or_else(|| content_etag(&body));
+
+    let not_modified = client_is_current(req, &etag, last_modified);
+
+    let mut response = if not_modified { HttpResponse::NotModified() } else { HttpResponse::Ok() };
+    response.insert_header(header::ETag(etag));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(LastModified(HttpDate::from(to_system_time(last_modified))));
+    }
+
+    if not_modified { response.finish() } else { response.content_type("application/json").body(body) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_if_match_version() {
+        let updated_at =
+            NaiveDateTime::parse_from_str("2024-08-29 14:00:00.123456", "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        let current = etag_for(updated_at).to_string();
+        let with_if_match = |value: &str| TestRequest::default().insert_header(("If-Match", value)).to_http_request();
+
+        assert_eq!(if_match_version(&TestRequest::default().to_http_request(), updated_at).ok(), Some(None));
+        assert_eq!(if_match_version(&with_if_match(&current), updated_at).ok(), Some(Some(updated_at)));
+        assert_eq!(if_match_version(&with_if_match("*"), updated_at).ok(), Some(Some(updated_at)));
+
+        let stale = if_match_version(&with_if_match("\"stale\""), updated_at).unwrap_err();
+        assert_eq!(stale.status(), actix_web::http::StatusCode::PRECONDITION_FAILED);
+        assert_eq!(stale.headers().get("etag").unwrap().to_str().unwrap(), current);
+        // If-Match uses the strong comparison, so a weak tag never matches
+        assert!(if_match_version(&with_if_match(&format!("W/{}", current)), updated_at).is_err());
+
+        // A post's tag matches on its version, whatever reactions or series navigation its body had
+        let post_tag = versioned_etag(updated_at, b"{\"reactions\":1}").to_string();
+        assert_eq!(if_match_version(&with_if_match(&post_tag), updated_at).ok(), Some(Some(updated_at)));
+        assert_ne!(post_tag, versioned_etag(updated_at, b"{\"reactions\":2}").to_string());
+        let newer = updated_at + chrono::Duration::microseconds(1);
+        assert!(if_match_version(&with_if_match(&versioned_etag(newer, b"{}").to_string()), updated_at).is_err());
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let last_modified = NaiveDateTime::parse_from_str("2024-08-29 14:00:00.750", "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        let same_second = to_system_time(last_modified);
+
+        assert!(is_not_modified(Some(same_second), Some(last_modified)));
+        assert!(!is_not_modified(Some(same_second - Duration::from_secs(1)), Some(last_modified)));
+        assert!(!is_not_modified(None, Some(last_modified)));
+        assert!(!is_not_modified(Some(same_second), None));
+    }
+
+    #[test]
+    fn test_conditional_json() {
+        let updated_at = NaiveDateTime::parse_from_str("2024-08-29 14:00:00.750", "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        let body = json!({ "title": "Hello" });
+        let get = |name: &str, value: &str| TestRequest::default().insert_header((name, value)).to_http_request();
+
+        let fresh = conditional_json(&TestRequest::default().to_http_request(), Some(etag_for(updated_at)), Some(updated_at), &body);
+        assert_eq!(fresh.status(), actix_web::http::StatusCode::OK);
+        let etag = fresh.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        let last_modified = fresh.headers().get("last-modified").unwrap().to_str().unwrap().to_string();
+
+        let cached = conditional_json(&get("If-None-Match", &etag), Some(etag_for(updated_at)), Some(updated_at), &body);
+        assert_eq!(cached.status(), actix_web::http::StatusCode::NOT_MODIFIED);
+        let cached = conditional_json(&get("If-Modified-Since", &last_modified), Some(etag_for(updated_at)), Some(updated_at), &body);
+        assert_eq!(cached.status(), actix_web::http::StatusCode::NOT_MODIFIED);
+        // A stale ETag means a full response even if the date would still match
+        let stale =
+            TestRequest::default()
+                .insert_header(("If-None-Match", "\"stale\""))
+                .insert_header(("If-Modified-Since", last_modified.as_str()))
+                .to_http_request();
+        assert_eq!(conditional_json(&stale, Some(etag_for(updated_at)), Some(updated_at), &body).status(), actix_web::http::StatusCode::OK);
+
+        // Content tags are stable for the same body and change with it
+        let listed = conditional_json(&TestRequest::default().to_http_request(), None, None, &body);
+        let listed_etag = listed.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        assert!(listed_etag.starts_with("W/"));
+        assert_eq!(conditional_json(&get("If-None-Match", &listed_etag), None, None, &body).status(), actix_web::http::StatusCode::NOT_MODIFIED);
+        assert_eq!(conditional_json(&get("If-None-Match", &listed_etag), None, None, &json!([])).status(), actix_web::http::StatusCode::OK);
+    }
+}
@@ -0,0 +1,110 @@
+use serde_json::{Map, Value};
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+
+fn to_yaml(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(flag) => Yaml::Boolean(*flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => Yaml::Integer(integer),
+            None => Yaml::Real(number.to_string()),
+        },
+        Value::String(text) => Yaml::String(text.clone()),
+        Value::Array(items) => Yaml::Array(items.iter().map(to_yaml).collect()),
+        Value::Object(fields) => {
+            Yaml::Hash(fields.iter().map(|(key, value)| (Yaml::String(key.clone()), to_yaml(value))).collect())
+        }
+    }
+}
+
+fn from_yaml(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::Boolean(flag) => Value::Bool(*flag),
+        Yaml::Integer(integer) => Value::from(*integer),
+        Yaml::Real(real) => real.parse::<f64>().map(Value::from).unwrap_or_else(|_| Value::String(real.clone())),
+        Yaml::String(text) => Value::String(text.clone()),
+        Yaml::Array(items) => Value::Array(items.iter().map(from_yaml).collect()),
+        Yaml::Hash(fields) => Value::Object(
+            fields
+                .iter()
+                .filter_map(|(key, value)| key.as_str().map(|key| (key.to_string(), from_yaml(value))))
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+// A Markdown document with `fields` as YAML front matter, in the order given, followed by the body
+pub fn render_front_matter(fields: &[(String, Value)], body: &str) -> String {
+    let front_matter: Hash = fields.iter().map(|(key, value)| (Yaml::String(key.clone()), to_yaml(value))).collect();
+
+    let mut document = String::new();
+    if let Err(e) = YamlEmitter::new(&mut document).dump(&Yaml::Hash(front_matter)) {
+        eprintln!("Error writing front matter: {:?}", e);
+    }
+    document.push_str("\n---\n");
+    document.push_str(body);
+    document
+}
+
+// Splits a Markdown document into its front matter fields and body.
+// The document has to start with a `---` line, and the front matter ends at the next one.
+pub fn parse_front_matter(document: &str) -> Result<(Map<String, Value>, String), String> {
+    let document = document.strip_prefix('\u{feff}').unwrap_or(document);
+    let rest =
+        document
+            .strip_prefix("---\n")
+            .or_else(|| document.strip_prefix("---\r\n"))
+            .ok_or_else(|| "missing front matter".to_string())?;
+
+    let (front_matter, body) =
+        if let Some(body) = rest.strip_prefix("---\n").or_else(|| rest.strip_prefix("---\r\n")) {
+            ("", body)
+        } else {
+            let end = rest.find("\n---\n").or_else(|| rest.find("\n---\r\n")).ok_or_else(|| "front matter is not closed".to_string())?;
+            let body = &rest[end + 1..];
+            (&rest[..end], body.split_once('\n').map(|(_, body)| body).unwrap_or(""))
+        };
+
+    let documents = YamlLoader::load_from_str(front_matter).map_err(|e| format!("invalid front matter: {}", e))?;
+    match documents.first().map(from_yaml) {
+        Some(Value::Object(fields)) => Ok((fields, body.to_string())),
+        None => Ok((Map::new(), body.to_string())),
+        Some(_) => Err("front matter has to be a mapping of fields".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_front_matter_round_trip() {
+        let fields = vec![
+            ("post_id".to_string(), json!("123")),
+            ("title".to_string(), json!("Fish: & Chips")),
+            ("publish_at".to_string(), Value::Null),
+            ("tags".to_string(), json!(["Rust", "Web Dev"])),
+        ];
+        let document = render_front_matter(&fields, "# Hello\n\n---\n\nBody");
+        assert!(document.starts_with("---\n"));
+
+        let (parsed, body) = parse_front_matter(&document).unwrap();
+        assert_eq!(Value::Object(parsed), json!({ "post_id": "123", "title": "Fish: & Chips", "publish_at": null, "tags": ["Rust", "Web Dev"] }));
+        assert_eq!(body, "# Hello\n\n---\n\nBody");
+    }
+
+    #[test]
+    fn test_parse_handwritten_front_matter() {
+        let (fields, body) = parse_front_matter("---\ntitle: Hello\ncount: 3\n---\nHi\n").unwrap();
+        assert_eq!(fields["title"], "Hello");
+        assert_eq!(fields["count"], 3);
+        assert_eq!(body, "Hi\n");
+
+        assert!(parse_front_matter("No front matter").is_err());
+        assert!(parse_front_matter("---\ntitle: Hello\n").is_err());
+        assert!(parse_front_matter("---\n- a list\n---\n").is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::env;
+
+const MAX_LOCALE_LENGTH: usize = 20;
+
+// Lowercases a language tag and checks its shape, e.g. "pt_BR" -> "pt-br". None for anything that isn't one.
+pub fn normalize_locale(value: &str) -> Option<String> {
+    let locale = value.trim().replace('_', "-").to_ascii_lowercase();
+    if locale.len() > MAX_LOCALE_LENGTH {
+        return None;
+    }
+
+    let mut subtags = locale.split('-');
+    let language = subtags.next()?;
+    let language_valid = (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic());
+    let rest_valid = subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    if language_valid && rest_valid { Some(locale) } else { None }
+}
+
+// The locales of an Accept-Language header, most preferred first. Wildcards, q=0 and malformed entries are left out.
+pub fn accept_language(header: &str) -> Vec<String> {
+    let mut weighted: Vec<(String, f32)> =
+        header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let locale = normalize_locale(parts.next()?)?;
+                let quality =
+                    parts
+                        .find_map(|param| param.trim().strip_prefix("q="))
+                        .map(|q| q.trim().parse::<f32>().ok())
+                        .unwrap_or(Some(1.0))?;
+                (quality > 0.0).then_some((locale, quality))
+            })
+            .collect();
+
+    // A stable sort keeps the header's own order between equal weights
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    weighted.into_iter().map(|(locale, _)| locale).collect()
+}
+
+fn language(locale: &str) -> &str {
+    locale.split('-').next().unwrap_or(locale)
+}
+
+// The locale of `available` to answer in, going through `preferred` in order.
+// An exact match wins, otherwise one in the same language, e.g. "de" for "de-at".
+pub fn negotiate_locale<'a>(preferred: &[String], available: &'a [String]) -> Option<&'a str> {
+    preferred.iter().find_map(|wanted| {
+        available
+            .iter()
+            .find(|locale| *locale == wanted)
+            .or_else(|| available.iter().find(|locale| language(locale) == language(wanted)))
+            .map(String::as_str)
+    })
+}
+
+// The language posts are written in, from SITE_LOCALE. Readers who prefer it get the original.
+pub fn site_locale() -> String {
+    env::var("SITE_LOCALE")
+        .ok()
+        .and_then(|locale| normalize_locale(&locale))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+// The translation to answer with. The original in `original` is a candidate as well, so None when
+// it suits the reader better than any translation, or nothing does.
+pub fn negotiate_translation<'a>(preferred: &[String], original: &str, translations: &'a [String]) -> Option<&'a str> {
+    let candidates: Vec<String> = std::iter::once(original.to_string()).chain(translations.iter().cloned()).collect();
+    let locale = negotiate_locale(preferred, &candidates).filter(|locale| *locale != original)?;
+    translations.iter().find(|translation| *translation == locale).map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("de").as_deref(), Some("de"));
+        assert_eq!(normalize_locale(" pt_BR ").as_deref(), Some("pt-br"));
+        assert_eq!(normalize_locale("zh-Hant-TW").as_deref(), Some("zh-hant-tw"));
+        assert_eq!(normalize_locale("*"), None);
+        assert_eq!(normalize_locale("english"), None);
+        assert_eq!(normalize_locale("de-"), None);
+    }
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5"), vec!["fr-ch", "fr", "en", "de"]);
+        assert_eq!(accept_language("en;q=0.5, de"), vec!["de", "en"]);
+        assert_eq!(accept_language("nl;q=0, es;q=oops, it"), vec!["it"]);
+        assert!(accept_language("").is_empty());
+    }
+
+    #[test]
+    fn test_negotiate_locale() {
+        let available = vec!["de".to_string(), "pt-br".to_string()];
+        let preferred = |locales: &[&str]| locales.iter().map(|locale| locale.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(negotiate_locale(&preferred(&["de"]), &available), Some("de"));
+        assert_eq!(negotiate_locale(&preferred(&["de-at"]), &available), Some("de"));
+        assert_eq!(negotiate_locale(&preferred(&["pt"]), &available), Some("pt-br"));
+        assert_eq!(negotiate_locale(&preferred(&["fr", "pt-br"]), &available), Some("pt-br"));
+        assert_eq!(negotiate_locale(&preferred(&["fr"]), &available), None);
+    }
+
+    #[test]
+    fn test_negotiate_translation() {
+        let translations = vec!["de".to_string(), "en-gb".to_string()];
+        let preferred = |locales: &[&str]| locales.iter().map(|locale| locale.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(negotiate_translation(&preferred(&["en", "de"]), "en", &translations), None);
+        assert_eq!(negotiate_translation(&preferred(&["en-us", "de"]), "en", &translations), None);
+        assert_eq!(negotiate_translation(&preferred(&["en-gb"]), "en", &translations), Some("en-gb"));
+        assert_eq!(negotiate_translation(&preferred(&["fr", "de-at", "en"]), "en", &translations), Some("de"));
+        assert_eq!(negotiate_translation(&preferred(&["fr"]), "en", &translations), None);
+    }
+}
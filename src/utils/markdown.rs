@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::utils::slug::slugify;
+
+// Highlighted code is marked up with classes rather than inline styles, see highlight_css for the stylesheet
+const HIGHLIGHT_CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const HIGHLIGHT_THEME: &str = "InspiredGitHub";
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref SANITIZER: ammonia::Builder<'static> = {
+        let mut builder = ammonia::Builder::default();
+        builder
+            .add_tag_attributes("h1", &["id"])
+            .add_tag_attributes("h2", &["id"])
+            .add_tag_attributes("h3", &["id"])
+            .add_tag_attributes("h4", &["id"])
+            .add_tag_attributes("h5", &["id"])
+            .add_tag_attributes("h6", &["id"])
+            .add_tag_attributes("pre", &["class"])
+            .add_tag_attributes("code", &["class"])
+            .add_tag_attributes("span", &["class"])
+            .add_tag_attributes("input", &["type", "checked", "disabled"])
+            .add_tags(&["input"]);
+        builder
+    };
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+}
+
+// A Markdown body rendered to sanitized HTML, with the headings it contains in document order
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct RenderedMarkdown {
+    pub body_html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+// Headings with the same text get -1, -2, ... so every anchor on the page is unique
+fn unique_anchor(text: &str, used_anchors: &mut HashMap<String, usize>) -> String {
+    let base = match slugify(text) {
+        slug if slug.is_empty() => "section".to_string(),
+        slug => slug,
+    };
+
+    let seen = used_anchors.entry(base.clone()).or_insert(0);
+    let anchor = if *seen == 0 { base } else { format!("{}-{}", base, seen) };
+    *seen += 1;
+    anchor
+}
+
+fn highlight_code(code: &str, language: Option<&str>) -> String {
+    let syntax =
+        language
+            .and_then(|language| SYNTAX_SET.find_syntax_by_token(language))
+            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAX_SET, HIGHLIGHT_CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        if generator.parse_html_for_line_which_includes_newline(line).is_err() {
+            // Fall back to plain, escaped code rather than failing the whole render
+            let mut escaped = String::new();
+            html::push_html(&mut escaped, std::iter::once(Event::Text(code.into())));
+            return format!("<pre class=\"hl-code\"><code>{}</code></pre>", escaped);
+        }
+    }
+
+    let language_class =
+        language
+            .map(|language| language.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect::<String>())
+            .filter(|language| !language.is_empty())
+            .map(|language| format!(" class=\"language-{}\"", language))
+            .unwrap_or_default();
+
+    format!("<pre class=\"hl-code\"><code{}>{}</code></pre>", language_class, generator.finalize())
+}
+
+// Renders Markdown to HTML that is safe to embed: headings get anchors and feed the table of contents,
+// fenced code is syntax highlighted, and the result goes through the sanitizer so raw HTML in a body can't run scripts.
+pub fn render_markdown(source: &str) -> RenderedMarkdown {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let mut parser = Parser::new_ext(source, options);
+    let mut events = Vec::new();
+    let mut toc = Vec::new();
+    let mut used_anchors = HashMap::new();
+
+    while let Some(event) = parser.next() {
+        match event {
+            Event::Start(Tag::Heading { level, classes, attrs, .. }) => {
+                let mut inner = Vec::new();
+                let mut text = String::new();
+                for event in parser.by_ref() {
+                    match &event {
+                        Event::End(TagEnd::Heading(_)) => break,
+                        Event::Text(value) | Event::Code(value) => text.push_str(value),
+                        _ => {}
+                    }
+                    inner.push(event);
+                }
+
+                let anchor = unique_anchor(&text, &mut used_anchors);
+                toc.push(TocEntry { level: level as u8, text: text.trim().to_string(), anchor: anchor.clone() });
+
+                events.push(Event::Start(Tag::Heading { level, id: Some(anchor.into()), classes, attrs }));
+                events.extend(inner);
+                events.push(Event::End(TagEnd::Heading(level)));
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let mut code = String::new();
+                for event in parser.by_ref() {
+                    match event {
+                        Event::End(TagEnd::CodeBlock) => break,
+                        Event::Text(value) => code.push_str(&value),
+                        _ => {}
+                    }
+                }
+
+                let language = match &kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next(),
+                    CodeBlockKind::Indented => None,
+                };
+                events.push(Event::Html(highlight_code(&code, language).into()));
+            }
+            event => events.push(event),
+        }
+    }
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events.into_iter());
+
+    RenderedMarkdown {
+        body_html: SANITIZER.clean(&unsafe_html).to_string(),
+        toc,
+    }
+}
+
+// Stylesheet for the hl- classes used in highlighted code blocks
+pub fn highlight_css() -> String {
+    let themes = ThemeSet::load_defaults();
+    css_for_theme_with_class_style(&themes.themes[HIGHLIGHT_THEME], HIGHLIGHT_CLASS_STYLE).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown_strips_scripts() {
+        let rendered = render_markdown("Hello <script>alert('x')</script> **world** <a href=\"javascript:alert(1)\">link</a>");
+
+        assert!(!rendered.body_html.contains("<script"));
+        assert!(!rendered.body_html.contains("javascript:"));
+        assert!(rendered.body_html.contains("<strong>world</strong>"));
+    }
+
+    #[test]
+    fn test_render_markdown_anchors_headings_and_builds_toc() {
+        let rendered = render_markdown("# Intro\n\ntext\n\n## Setup `cargo`\n\n## Intro\n");
+
+        assert!(rendered.body_html.contains("<h1 id=\"intro\">Intro</h1>"));
+        assert!(rendered.body_html.contains("<h2 id=\"intro-1\">Intro</h2>"));
+        assert_eq!(
+            rendered.toc,
+            vec![
+                TocEntry { level: 1, text: "Intro".to_string(), anchor: "intro".to_string() },
+                TocEntry { level: 2, text: "Setup cargo".to_string(), anchor: "setup-cargo".to_string() },
+                TocEntry { level: 2, text: "Intro".to_string(), anchor: "intro-1".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_highlights_fenced_code() {
+        let rendered = render_markdown("```rust\nfn main() {}\n```\n");
+
+        assert!(rendered.body_html.contains("<pre class=\"hl-code\"><code class=\"language-rust\">"));
+        assert!(rendered.body_html.contains("<span class=\"hl-"));
+        assert!(highlight_css().contains(".hl-"));
+    }
+}
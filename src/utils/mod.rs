@@ -0,0 +1,6 @@
+pub mod etag;
+pub mod front_matter;
+pub mod locale;
+pub mod markdown;
+pub mod slug;
+pub mod tar;
@@ -0,0 +1,27 @@
+// Turns a human readable name into a lowercase, hyphen separated URL segment, e.g. "Rust & Actix" -> "rust-actix"
+pub fn slugify(value: &str) -> String {
+    let mut slug = String::with_capacity(value.len());
+
+    for character in value.chars() {
+        if character.is_ascii_alphanumeric() {
+            slug.push(character.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Rust & Actix"), "rust-actix");
+        assert_eq!(slugify("  Hello,   World!  "), "hello-world");
+        assert_eq!(slugify("Web-Dev 2024"), "web-dev-2024");
+        assert_eq!(slugify("!!!"), "");
+    }
+}
@@ -0,0 +1,111 @@
+// Just enough of the ustar format to write and read archives of plain files
+
+const BLOCK_SIZE: usize = 512;
+const MAX_NAME_LENGTH: usize = 100;
+
+fn write_octal(field: &mut [u8], value: u64) {
+    // Zero padded, leaving the last byte as the terminating NUL
+    let width = field.len() - 1;
+    let digits = format!("{:0width$o}", value, width = width);
+    field[..width].copy_from_slice(digits.as_bytes());
+}
+
+fn read_octal(field: &[u8]) -> Option<u64> {
+    let digits = std::str::from_utf8(field).ok()?.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+// One file entry, a header block followed by the data padded to whole blocks.
+// Names longer than 100 bytes are cut short.
+pub fn tar_entry(name: &str, data: &[u8], modified: i64) -> Vec<u8> {
+    let mut header = [0u8; BLOCK_SIZE];
+
+    let mut name_length = name.len().min(MAX_NAME_LENGTH);
+    while !name.is_char_boundary(name_length) {
+        name_length -= 1;
+    }
+    header[..name_length].copy_from_slice(&name.as_bytes()[..name_length]);
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], data.len() as u64);
+    write_octal(&mut header[136..148], modified.max(0) as u64);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is taken with its own field filled with spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u64 = header.iter().map(|byte| *byte as u64).sum();
+    write_octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+
+    let mut entry = header.to_vec();
+    entry.extend_from_slice(data);
+    entry.resize(entry.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+    entry
+}
+
+// The two empty blocks that close an archive
+pub fn tar_end() -> Vec<u8> {
+    vec![0u8; BLOCK_SIZE * 2]
+}
+
+// The regular files in an archive as (name, data), in archive order. Directories and other entry types are skipped.
+pub fn read_tar(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+    let mut offset = 0;
+
+    while offset + BLOCK_SIZE <= archive.len() {
+        let header = &archive[offset..offset + BLOCK_SIZE];
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+
+        let name_end = header[..MAX_NAME_LENGTH].iter().position(|byte| *byte == 0).unwrap_or(MAX_NAME_LENGTH);
+        let mut name = String::from_utf8_lossy(&header[..name_end]).to_string();
+        // ustar keeps the leading directories of long names in a separate prefix field
+        if &header[257..262] == b"ustar" && header[345] != 0 {
+            let prefix_end = header[345..500].iter().position(|byte| *byte == 0).unwrap_or(155);
+            name = format!("{}/{}", String::from_utf8_lossy(&header[345..345 + prefix_end]), name);
+        }
+
+        let size = read_octal(&header[124..136]).ok_or_else(|| format!("Entry '{}' has an invalid size", name))? as usize;
+        let data_start = offset + BLOCK_SIZE;
+        let data_end = data_start + size;
+        if data_end > archive.len() {
+            return Err(format!("Entry '{}' is cut short", name));
+        }
+
+        if header[156] == b'0' || header[156] == 0 {
+            files.push((name, archive[data_start..data_end].to_vec()));
+        }
+        offset = data_start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tar_round_trip() {
+        let mut archive = tar_entry("posts/hello.md", b"---\ntitle: Hello\n---\nHi", 1_724_940_000);
+        archive.extend(tar_entry("skills/rust.md", b"", 1_724_940_000));
+        archive.extend(tar_end());
+        assert_eq!(archive.len() % BLOCK_SIZE, 0);
+
+        let files = read_tar(&archive).unwrap();
+        assert_eq!(files, vec![
+            ("posts/hello.md".to_string(), b"---\ntitle: Hello\n---\nHi".to_vec()),
+            ("skills/rust.md".to_string(), Vec::new()),
+        ]);
+
+        assert!(read_tar(&archive[..BLOCK_SIZE + 10]).is_err());
+    }
+}
@@ -235,8 +235,10 @@ mod skills_controller_spec {
         let body: Bytes = to_bytes(resp.into_body()).await.unwrap();
         let json_body: Value = serde_json::from_slice(&body).unwrap();
 
-        // Assuming the response is an array of skills
-        let skill_json_array = json_body.as_array().expect("Expected an array of skills");
+        // The response is a page with the skills under "items"
+        let skill_json_array = json_body["items"].as_array().expect("Expected an array of skills");
+        assert_eq!(json_body["total_count"], 3);
+        assert!(json_body["next_cursor"].is_null());
 
         // Assert the length of the array
         assert_eq!(skill_json_array.len(), 3);
@@ -355,7 +357,8 @@ mod skills_controller_spec {
         let deleted_skill_1 =
             skills::table
                 .filter(skills::skill_id.eq("abc200"))
-                .first::<Skill>(&mut conn)
+                .select(Skill::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted skill");
 
@@ -364,7 +367,8 @@ mod skills_controller_spec {
         let deleted_skill_2 =
             skills::table
                 .filter(skills::skill_id.eq("def456"))
-                .first::<Skill>(&mut conn)
+                .select(Skill::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted skill");
 
@@ -419,7 +423,8 @@ mod skills_controller_spec {
 
         let deleted_skill_1 = skills::table
             .filter(skills::skill_id.eq("fake_id_1"))
-            .first::<Skill>(&mut conn)
+            .select(Skill::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted skill");
 
@@ -427,7 +432,8 @@ mod skills_controller_spec {
 
         let deleted_skill_2 = skills::table
             .filter(skills::skill_id.eq("fake_id_2"))
-            .first::<Skill>(&mut conn)
+            .select(Skill::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted skill");
 
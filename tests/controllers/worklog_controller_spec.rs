@@ -178,8 +178,10 @@ mod worklog_controller_spec {
         let body: Bytes = to_bytes(resp.into_body()).await.unwrap();
         let json_body: Value = serde_json::from_slice(&body).unwrap();
 
-        // Assuming the response is an array of worklog
-        let worklog_json_array = json_body.as_array().expect("Expected an array of worklog");
+        // The response is a page with the worklog under "items"
+        let worklog_json_array = json_body["items"].as_array().expect("Expected an array of worklog");
+        assert_eq!(json_body["total_count"], 3);
+        assert!(json_body["next_cursor"].is_null());
 
         // Assert the length of the array
         assert_eq!(worklog_json_array.len(), 3);
@@ -357,7 +359,8 @@ mod worklog_controller_spec {
         let deleted_worklog_1 =
             worklog::table
                 .filter(worklog::worklog_id.eq("abc200"))
-                .first::<Worklog>(&mut conn)
+                .select(Worklog::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted worklog");
 
@@ -366,7 +369,8 @@ mod worklog_controller_spec {
         let deleted_worklog_2 =
             worklog::table
                 .filter(worklog::worklog_id.eq("def456"))
-                .first::<Worklog>(&mut conn)
+                .select(Worklog::as_select())
+                .first(&mut conn)
                 .optional()
                 .expect("Failed to check for deleted worklog");
 
@@ -421,7 +425,8 @@ mod worklog_controller_spec {
 
         let deleted_worklog_1 = worklog::table
             .filter(worklog::worklog_id.eq("fake_id_1"))
-            .first::<Worklog>(&mut conn)
+            .select(Worklog::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted worklog");
 
@@ -429,7 +434,8 @@ mod worklog_controller_spec {
 
         let deleted_worklog_2 = worklog::table
             .filter(worklog::worklog_id.eq("fake_id_2"))
-            .first::<Worklog>(&mut conn)
+            .select(Worklog::as_select())
+            .first(&mut conn)
             .optional()
             .expect("Failed to check for deleted worklog");
 